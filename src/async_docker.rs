@@ -0,0 +1,258 @@
+// Async facade over `DockerClient`, built with the `async` feature.
+//
+// Backend calls block (socket reads or docker CLI processes), so each one runs
+// on tokio's blocking pool. It covers containers, images, networks, volumes,
+// stats, logs and events; builds, pushes, copies and interactive sessions stay
+// on `DockerClient`. Over the Engine API, per-container stats are fanned out
+// with a JoinSet so dozens of containers refresh at once instead of one by
+// one; the docker CLI already reports every container from one process.
+
+use std::future::Future;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use crate::docker::{Container, ContainerSpec, ContainerStats, DockerClient, Image, Network, Volume};
+use crate::error::DockerError;
+use crate::events::{DockerEvent, EventFilter};
+use crate::logs::{LogLine, LogOptions};
+
+#[derive(Clone)]
+pub struct AsyncDockerClient {
+    client: DockerClient,
+}
+
+impl AsyncDockerClient {
+    pub fn new(client: DockerClient) -> Self {
+        AsyncDockerClient { client }
+    }
+
+    // Runs a blocking client call on the blocking pool
+    async fn run<T, F>(&self, call: F) -> Result<T, DockerError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&DockerClient) -> Result<T, DockerError> + Send + 'static,
+    {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || call(&client))
+            .await
+            .unwrap_or_else(|e| Err(format!("Docker task failed: {}", e).into()))
+    }
+
+    pub async fn list_containers(&self) -> Result<Vec<Container>, DockerError> {
+        self.run(|client| client.list_containers()).await
+    }
+
+    pub async fn get_stats_for_container(&self, container: &str) -> Result<ContainerStats, DockerError> {
+        let container = container.to_string();
+        self.run(move |client| client.get_stats_for_container(&container)).await
+    }
+
+    /// Stats for every running container, fetched concurrently when the
+    /// backend samples them one container at a time.
+    pub async fn get_container_stats(&self) -> Result<Vec<ContainerStats>, DockerError> {
+        if !self.client.samples_stats_per_container() {
+            return self.run(|client| client.get_container_stats()).await;
+        }
+        let running: Vec<String> = self
+            .list_containers()
+            .await?
+            .into_iter()
+            .filter(|container| container.status.starts_with("Up"))
+            .map(|container| container.name)
+            .collect();
+
+        let mut tasks = JoinSet::new();
+        for (index, name) in running.iter().enumerate() {
+            let client = self.clone();
+            let name = name.clone();
+            tasks.spawn(async move { (index, client.get_stats_for_container(&name).await) });
+        }
+
+        let mut results: Vec<Option<ContainerStats>> = vec![None; running.len()];
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((index, Ok(stats))) => results[index] = Some(stats),
+                Ok((index, Err(e))) => eprintln!("Failed to get stats for {}: {}", running[index], e),
+                Err(e) => eprintln!("Stats task failed: {}", e),
+            }
+        }
+        // Keep the listing order so redraws don't shuffle rows
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// A container's log lines, delivered through a channel as they are read.
+    /// As with `events`, a followed stream is closed at the next line once the
+    /// receiver is dropped.
+    pub async fn logs(&self, container: &str, options: LogOptions) -> Result<mpsc::Receiver<LogLine>, DockerError> {
+        let container = container.to_string();
+        let lines = self.run(move |client| client.get_container_logs(&container, &options)).await?;
+        let (sender, receiver) = mpsc::channel(256);
+        tokio::task::spawn_blocking(move || {
+            for line in lines {
+                if sender.blocking_send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
+    /// Daemon events that pass `filter`, delivered through a channel. Once the
+    /// receiver is dropped, the stream behind it is closed when the next event
+    /// arrives; until then a quiet daemon keeps it open.
+    pub async fn events(&self, filter: EventFilter) -> Result<mpsc::Receiver<DockerEvent>, DockerError> {
+        let stream = self.run(move |client| client.subscribe(filter)).await?;
+        let (sender, receiver) = mpsc::channel(64);
+        tokio::task::spawn_blocking(move || {
+            for event in stream {
+                if sender.blocking_send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+// The rest of the client's surface; the monitor itself only needs the calls above
+#[allow(dead_code)]
+impl AsyncDockerClient {
+    pub async fn create_container(&self, spec: ContainerSpec) -> Result<(), DockerError> {
+        self.run(move |client| client.create_container(&spec)).await
+    }
+
+    pub async fn start_container(&self, name: &str) -> Result<(), DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.start_container(&name)).await
+    }
+
+    pub async fn stop_container(&self, name: &str) -> Result<(), DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.stop_container(&name)).await
+    }
+
+    pub async fn restart_container(&self, name: &str) -> Result<(), DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.restart_container(&name)).await
+    }
+
+    pub async fn pause_container(&self, name: &str) -> Result<(), DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.pause_container(&name)).await
+    }
+
+    pub async fn unpause_container(&self, name: &str) -> Result<(), DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.unpause_container(&name)).await
+    }
+
+    pub async fn kill_container(&self, name: &str, signal: Option<&str>) -> Result<(), DockerError> {
+        let name = name.to_string();
+        let signal = signal.map(str::to_string);
+        self.run(move |client| client.kill_container(&name, signal.as_deref())).await
+    }
+
+    pub async fn rename_container(&self, old_name: &str, new_name: &str) -> Result<(), DockerError> {
+        let (old_name, new_name) = (old_name.to_string(), new_name.to_string());
+        self.run(move |client| client.rename_container(&old_name, &new_name)).await
+    }
+
+    pub async fn remove_container(&self, name: &str) -> Result<(), DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.remove_container(&name)).await
+    }
+
+    /// `docker inspect` output for the container, as JSON text.
+    pub async fn inspect_container(&self, name: &str) -> Result<String, DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.inspect_container(&name)).await
+    }
+
+    pub async fn list_images(&self) -> Result<Vec<Image>, DockerError> {
+        self.run(|client| client.list_images()).await
+    }
+
+    pub async fn inspect_image(&self, name: &str) -> Result<String, DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.inspect_image(&name)).await
+    }
+
+    pub async fn tag_image(&self, source: &str, target: &str) -> Result<(), DockerError> {
+        let (source, target) = (source.to_string(), target.to_string());
+        self.run(move |client| client.tag_image(&source, &target)).await
+    }
+
+    pub async fn remove_image(&self, name: &str) -> Result<(), DockerError> {
+        let name = name.to_string();
+        self.run(move |client| client.remove_image(&name)).await
+    }
+
+    pub async fn list_networks(&self) -> Result<Vec<Network>, DockerError> {
+        self.run(|client| client.list_networks()).await
+    }
+
+    pub async fn list_volumes(&self) -> Result<Vec<Volume>, DockerError> {
+        self.run(|client| client.list_volumes()).await
+    }
+}
+
+/// The runtime shared by every async call, started on first use.
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("Failed to start the async runtime"))
+}
+
+/// Drives a future to completion from synchronous command handlers.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::fake::FakeBackend;
+
+    fn demo_client() -> AsyncDockerClient {
+        AsyncDockerClient::new(DockerClient::with_backend(Arc::new(FakeBackend::demo())))
+    }
+
+    #[tokio::test]
+    async fn test_stats_for_running_containers_in_listing_order() {
+        let stats = demo_client().get_container_stats().await.unwrap();
+        let names: Vec<&str> = stats.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["web", "db"]);
+    }
+
+    #[tokio::test]
+    async fn test_container_lifecycle() {
+        let docker = demo_client();
+        docker.stop_container("web").await.unwrap();
+        docker.rename_container("web", "front").await.unwrap();
+        assert!(docker.inspect_container("front").await.is_ok());
+        docker.remove_container("front").await.unwrap();
+        let names: Vec<String> = docker.list_containers().await.unwrap().into_iter().map(|c| c.name).collect();
+        assert!(!names.contains(&"front".to_string()));
+        assert!(docker.start_container("front").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_events_channel_closes_with_stream() {
+        let filter = EventFilter { actions: vec!["start".to_string()], ..Default::default() };
+        let mut events = demo_client().events(filter).await.unwrap();
+        assert_eq!(events.recv().await.unwrap().actor.attributes["name"], "web");
+        assert!(events.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_logs_channel_delivers_every_line() {
+        let mut lines = demo_client().logs("db", LogOptions::default()).await.unwrap();
+        let mut count = 0;
+        while lines.recv().await.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        assert!(demo_client().logs("ghost", LogOptions::default()).await.is_err());
+    }
+}
@@ -0,0 +1,141 @@
+// Operations every Docker backend has to provide.
+//
+// `DockerClient` wraps one of these: the Engine API client, the docker CLI,
+// or the in-memory fake used for tests and demos.
+
+use std::io::Read;
+use crate::copy::PathStat;
+use crate::disk::DiskUsage;
+use crate::docker::{BuildOptions, Container, ContainerProcess, ContainerSpec, ContainerStats, ExecOptions, Image, Network, Volume};
+use crate::error::DockerError;
+use crate::events::{DockerEvent, EventFilter};
+use crate::logs::{LogLine, LogOptions};
+use crate::progress::ProgressMessage;
+
+pub type EventStream = Box<dyn Iterator<Item = DockerEvent> + Send>;
+pub type LogLines = Box<dyn Iterator<Item = LogLine> + Send>;
+pub type Archive = Box<dyn Read + Send>;
+pub type ProgressStream = Box<dyn Iterator<Item = ProgressMessage> + Send>;
+/// Stats for every running container, a new snapshot each time the daemon samples.
+pub type StatsStream = Box<dyn Iterator<Item = Vec<ContainerStats>> + Send>;
+/// Build output lines as they are printed; a failed build ends with an `Err`.
+pub type BuildOutput = Box<dyn Iterator<Item = Result<String, DockerError>> + Send>;
+
+pub trait DockerBackend: Send + Sync {
+    // ===== DAEMON =====
+
+    /// Whether the backend can be used at all (binary installed, socket present).
+    fn is_available(&self) -> bool;
+
+    /// Whether the daemon behind the backend answers requests.
+    fn ping(&self) -> bool;
+
+    // ===== CONTAINERS =====
+
+    fn list_containers(&self) -> Result<Vec<Container>, DockerError>;
+    /// Creates and starts a container from `spec`.
+    fn create_container(&self, spec: &ContainerSpec) -> Result<(), DockerError>;
+    fn start_container(&self, name: &str) -> Result<(), DockerError>;
+    fn stop_container(&self, name: &str) -> Result<(), DockerError>;
+    fn restart_container(&self, name: &str) -> Result<(), DockerError>;
+    fn pause_container(&self, name: &str) -> Result<(), DockerError>;
+    fn unpause_container(&self, name: &str) -> Result<(), DockerError>;
+    fn remove_container(&self, name: &str) -> Result<(), DockerError>;
+    fn kill_container(&self, container: &str, signal: Option<&str>) -> Result<(), DockerError>;
+    fn rename_container(&self, old_name: &str, new_name: &str) -> Result<(), DockerError>;
+    fn attach_container(&self, name: &str) -> Result<(), DockerError>;
+    /// Runs `command` through `sh -c` and returns its stdout.
+    fn exec_container(&self, name: &str, command: &str, options: &ExecOptions) -> Result<String, DockerError>;
+    /// Runs `command` directly (no shell needed) with the terminal handed over,
+    /// allocating a PTY when stdin is a terminal. Returns the program's exit
+    /// code once it exits, or 0 if the session detaches.
+    fn exec_interactive(&self, name: &str, command: &[String], options: &ExecOptions) -> Result<i32, DockerError>;
+    fn commit_container(&self, container: &str, repository: &str, tag: Option<&str>) -> Result<(), DockerError>;
+    /// Streams `path` out of the container as a tar archive whose top-level
+    /// entry is named after the path's last component.
+    fn copy_from_container(&self, container: &str, path: &str) -> Result<Archive, DockerError>;
+    /// Extracts a tar archive into `dir`, which must already exist in the container.
+    fn copy_to_container(&self, container: &str, dir: &str, archive: &mut dyn Read) -> Result<(), DockerError>;
+    /// What `path` is in the container, or None when nothing is there.
+    fn stat_container_path(&self, container: &str, path: &str) -> Result<Option<PathStat>, DockerError>;
+    fn diff_container(&self, container: &str) -> Result<String, DockerError>;
+    fn export_container(&self, container: &str, output_file: &str) -> Result<(), DockerError>;
+    fn get_container_ports(&self, container: &str) -> Result<String, DockerError>;
+    fn get_container_processes(&self, container: &str) -> Result<Vec<ContainerProcess>, DockerError>;
+    fn update_container(&self, container: &str, cpu_period: Option<&str>, cpu_quota: Option<&str>,
+                        memory: Option<&str>, memory_swap: Option<&str>) -> Result<(), DockerError>;
+    fn wait_for_container(&self, container: &str) -> Result<String, DockerError>;
+    fn inspect_container(&self, name: &str) -> Result<String, DockerError>;
+    fn get_container_size(&self, name: &str) -> Result<String, DockerError>;
+    /// Streams the container's stdout and stderr; with `options.follow` the
+    /// stream stays open until the container stops or the reader is dropped.
+    fn get_container_logs(&self, name: &str, options: &LogOptions) -> Result<LogLines, DockerError>;
+
+    // ===== STATS =====
+
+    fn get_container_stats(&self) -> Result<Vec<ContainerStats>, DockerError>;
+
+    /// Live stats from one long-lived connection or process, rather than a
+    /// one-shot sample per call. Ends when the daemon goes away.
+    fn stream_stats(&self) -> Result<StatsStream, DockerError>;
+
+    /// Whether stats are sampled one container per request, so asking for
+    /// several at once is faster than `get_container_stats` alone. A backend
+    /// that gets every container's stats in one call says no.
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    fn samples_stats_per_container(&self) -> bool {
+        false
+    }
+
+    /// One-shot stats for a single running container.
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    fn get_stats_for_container(&self, container: &str) -> Result<ContainerStats, DockerError> {
+        self.get_container_stats()?
+            .into_iter()
+            .find(|stats| stats.name == container)
+            .ok_or_else(|| DockerError::NoSuchContainer(format!("Error response from daemon: No such container: {}", container)))
+    }
+
+    // ===== IMAGES =====
+
+    fn list_images(&self) -> Result<Vec<Image>, DockerError>;
+    fn image_exists(&self, name: &str) -> Result<bool, DockerError>;
+    /// "repository@digest" for each repository the image was pulled from or
+    /// pushed to; empty for an image that was only ever built locally.
+    fn image_repo_digests(&self, name: &str) -> Result<Vec<String>, DockerError>;
+    /// `docker image inspect` JSON: an array with the image's details.
+    fn inspect_image(&self, name: &str) -> Result<String, DockerError>;
+    /// Progress messages until the pull ends; a failure arrives as a message
+    /// with `error` set rather than as an `Err`, once streaming has begun.
+    fn pull_image(&self, name: &str) -> Result<ProgressStream, DockerError>;
+    fn push_image(&self, name: &str) -> Result<ProgressStream, DockerError>;
+    fn build_image(&self, options: &BuildOptions) -> Result<BuildOutput, DockerError>;
+    fn tag_image(&self, source: &str, target: &str) -> Result<(), DockerError>;
+    fn remove_image(&self, name: &str) -> Result<(), DockerError>;
+    fn get_image_history(&self, image: &str) -> Result<String, DockerError>;
+    fn import_image(&self, file: &str, repository: &str, tag: Option<&str>) -> Result<(), DockerError>;
+    fn load_image(&self, file: &str) -> Result<(), DockerError>;
+    fn save_image(&self, image: &str, output_file: &str) -> Result<(), DockerError>;
+    /// The `docker save` archive of `image`, streamed rather than written to a file.
+    fn export_image(&self, image: &str) -> Result<Archive, DockerError>;
+
+    // ===== NETWORKS & VOLUMES =====
+
+    fn list_networks(&self) -> Result<Vec<Network>, DockerError>;
+    fn list_volumes(&self) -> Result<Vec<Volume>, DockerError>;
+    fn remove_network(&self, name: &str) -> Result<(), DockerError>;
+    fn remove_volume(&self, name: &str) -> Result<(), DockerError>;
+    /// Containers attached to `network`, running or not.
+    fn network_containers(&self, network: &str) -> Result<usize, DockerError>;
+
+    // ===== SYSTEM & EVENTS =====
+
+    fn get_system_info(&self) -> Result<String, DockerError>;
+    /// Space used per image, container, volume and build cache record.
+    fn disk_usage(&self) -> Result<DiskUsage, DockerError>;
+
+    /// Streams daemon events until the daemon closes the stream. Backends pass
+    /// `filter` on to the daemon where they can; `DockerClient::subscribe`
+    /// applies it again.
+    fn events(&self, filter: &EventFilter) -> Result<EventStream, DockerError>;
+}
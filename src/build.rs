@@ -0,0 +1,254 @@
+// Following `docker build` output.
+//
+// BuildKit's plain progress ("#5 [2/4] RUN make", "#5 CACHED", "#5 DONE 1.2s"),
+// the legacy builder ("Step 2/4 : RUN make", " ---> Using cache") and podman
+// ("STEP 2/4: RUN make", "--> Using cache ...") are folded into one list of
+// steps, so the end of a build can say which steps were cached and, when it
+// failed, which step failed and what it printed last.
+
+use std::collections::VecDeque;
+
+/// Output lines kept per step, for showing a failed step.
+const KEPT_OUTPUT: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepState {
+    Running,
+    Cached,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildStep {
+    /// BuildKit's "#5", or the legacy "2/4".
+    key: String,
+    /// e.g. "[2/4] RUN make" or "[internal] load build definition from Dockerfile".
+    pub name: String,
+    pub state: StepState,
+    /// The step's last few output lines.
+    pub output: VecDeque<String>,
+}
+
+impl BuildStep {
+    /// Whether this step is a Dockerfile instruction other than FROM, i.e.
+    /// one that is either cached or rebuilt.
+    pub fn is_instruction(&self) -> bool {
+        let (position, instruction) = match self.name.strip_prefix('[').and_then(|name| name.split_once("] ")) {
+            Some(parts) => parts,
+            None => return false,
+        };
+        // Multi-stage builds name the stage first, e.g. "[builder 2/5]"
+        let counted = position.rsplit(' ').next().unwrap_or("");
+        let numbered = counted
+            .split_once('/')
+            .is_some_and(|(step, total)| step.parse::<u32>().is_ok() && total.parse::<u32>().is_ok());
+        numbered && !instruction.to_uppercase().starts_with("FROM ")
+    }
+
+    fn push_output(&mut self, line: &str) {
+        if self.output.len() == KEPT_OUTPUT {
+            self.output.pop_front();
+        }
+        self.output.push_back(line.to_string());
+    }
+}
+
+/// What a line of build output was, for colouring it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildLineKind {
+    /// A step started.
+    Step,
+    /// A step was taken from the cache.
+    Cached,
+    Error,
+    Output,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildLog {
+    pub steps: Vec<BuildStep>,
+    // Legacy and podman output has no step keys; output belongs to the last step
+    current: Option<usize>,
+}
+
+impl BuildLog {
+    /// Folds in one line of output and says what it was.
+    pub fn apply(&mut self, line: &str) -> BuildLineKind {
+        let trimmed = line.trim();
+        if let Some(kind) = self.apply_buildkit(trimmed) {
+            return kind;
+        }
+
+        let legacy_step = trimmed
+            .strip_prefix("Step ")
+            .and_then(|rest| rest.split_once(" : "))
+            .or_else(|| trimmed.strip_prefix("STEP ").and_then(|rest| rest.split_once(": ")));
+        if let Some((position, instruction)) = legacy_step {
+            if let Some(current) = self.current_step() {
+                if current.state == StepState::Running {
+                    current.state = StepState::Done;
+                }
+            }
+            self.start(position, format!("[{}] {}", position, instruction));
+            return BuildLineKind::Step;
+        }
+        if let Some(arrow) = trimmed.strip_prefix("---> ").or_else(|| trimmed.strip_prefix("--> ")) {
+            if arrow.starts_with("Using cache") {
+                if let Some(current) = self.current_step() {
+                    current.state = StepState::Cached;
+                }
+                return BuildLineKind::Cached;
+            }
+            return BuildLineKind::Output;
+        }
+        if trimmed.starts_with("ERROR") || trimmed.starts_with("Error") || trimmed.contains("returned a non-zero code") {
+            if let Some(current) = self.current_step() {
+                current.state = StepState::Failed;
+                current.push_output(trimmed);
+            }
+            return BuildLineKind::Error;
+        }
+        if !trimmed.is_empty() {
+            if let Some(current) = self.current_step() {
+                current.push_output(trimmed);
+            }
+        }
+        BuildLineKind::Output
+    }
+
+    // "#5 [2/4] RUN make", "#5 0.412 compiling", "#5 CACHED", "#5 DONE 1.2s", "#5 ERROR: ..."
+    fn apply_buildkit(&mut self, line: &str) -> Option<BuildLineKind> {
+        let (key, rest) = line.split_once(' ')?;
+        if !key.strip_prefix('#').is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())) {
+            return None;
+        }
+        let index = match self.steps.iter().position(|step| step.key == key) {
+            Some(index) => index,
+            None => {
+                self.start(key, rest.to_string());
+                return Some(BuildLineKind::Step);
+            }
+        };
+        self.current = Some(index);
+        let step = &mut self.steps[index];
+        if rest == step.name {
+            return Some(BuildLineKind::Step);
+        }
+        if rest == "CACHED" {
+            step.state = StepState::Cached;
+            return Some(BuildLineKind::Cached);
+        }
+        if rest.starts_with("DONE") {
+            if step.state == StepState::Running {
+                step.state = StepState::Done;
+            }
+            return Some(BuildLineKind::Output);
+        }
+        if let Some(error) = rest.strip_prefix("ERROR") {
+            step.state = StepState::Failed;
+            step.push_output(error.trim_start_matches(':').trim());
+            return Some(BuildLineKind::Error);
+        }
+        // Command output carries the seconds since the step started
+        let output = match rest.split_once(' ') {
+            Some((seconds, text)) if seconds.parse::<f64>().is_ok() => text,
+            _ => rest,
+        };
+        step.push_output(output);
+        Some(BuildLineKind::Output)
+    }
+
+    fn start(&mut self, key: &str, name: String) {
+        self.steps.push(BuildStep { key: key.to_string(), name, state: StepState::Running, output: VecDeque::new() });
+        self.current = Some(self.steps.len() - 1);
+    }
+
+    fn current_step(&mut self) -> Option<&mut BuildStep> {
+        self.steps.get_mut(self.current?)
+    }
+
+    /// Instruction steps taken from the cache and rebuilt.
+    pub fn cache_counts(&self) -> (usize, usize) {
+        let instructions = self.steps.iter().filter(|step| step.is_instruction());
+        instructions.fold((0, 0), |(cached, rebuilt), step| match step.state {
+            StepState::Cached => (cached + 1, rebuilt),
+            _ => (cached, rebuilt + 1),
+        })
+    }
+
+    /// e.g. "6 steps: 4 cached, 2 rebuilt"
+    pub fn summary(&self) -> String {
+        let (cached, rebuilt) = self.cache_counts();
+        let steps = if cached + rebuilt == 1 { "1 step".to_string() } else { format!("{} steps", cached + rebuilt) };
+        format!("{}: {} cached, {} rebuilt", steps, cached, rebuilt)
+    }
+
+    /// The step that failed: one marked as failed, or else the step that
+    /// was still running when the build stopped.
+    pub fn failed_step(&self) -> Option<&BuildStep> {
+        self.steps
+            .iter()
+            .find(|step| step.state == StepState::Failed)
+            .or_else(|| self.steps.iter().rev().find(|step| step.state == StepState::Running && step.is_instruction()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(lines: &[&str]) -> BuildLog {
+        let mut log = BuildLog::default();
+        for line in lines {
+            log.apply(line);
+        }
+        log
+    }
+
+    #[test]
+    fn test_buildkit_cache_summary_and_failure() {
+        let log = log(&[
+            "#1 [internal] load build definition from Dockerfile",
+            "#1 DONE 0.0s",
+            "#4 [1/4] FROM docker.io/library/rust:1.75@sha256:abc",
+            "#4 DONE 0.0s",
+            "#5 [2/4] WORKDIR /app",
+            "#5 CACHED",
+            "#6 [3/4] COPY . .",
+            "#6 DONE 0.3s",
+            "#7 [4/4] RUN cargo build --release",
+            "#7 0.512 Compiling app v0.1.0",
+            "#7 3.104 error[E0425]: cannot find value `x` in this scope",
+            "#7 ERROR: process \"/bin/sh -c cargo build --release\" did not complete successfully: exit code: 101",
+        ]);
+        assert_eq!(log.cache_counts(), (1, 2));
+        assert_eq!(log.summary(), "3 steps: 1 cached, 2 rebuilt");
+        let failed = log.failed_step().unwrap();
+        assert_eq!(failed.name, "[4/4] RUN cargo build --release");
+        assert_eq!(failed.output[1], "error[E0425]: cannot find value `x` in this scope");
+        assert!(failed.output[2].starts_with("process \"/bin/sh -c cargo build --release\""));
+    }
+
+    #[test]
+    fn test_legacy_and_podman_output() {
+        let legacy = log(&[
+            "Step 1/3 : FROM alpine:3.19",
+            " ---> 05455a08881e",
+            "Step 2/3 : RUN apk add curl",
+            " ---> Using cache",
+            " ---> 9b2d1c3e4f5a",
+            "Step 3/3 : RUN make",
+            " ---> Running in 1f2e3d4c5b6a",
+            "make: *** No targets specified and no makefile found.  Stop.",
+            "The command '/bin/sh -c make' returned a non-zero code: 2",
+        ]);
+        assert_eq!(legacy.cache_counts(), (1, 1));
+        assert_eq!(legacy.failed_step().unwrap().name, "[3/3] RUN make");
+        assert_eq!(legacy.failed_step().unwrap().output[0], "make: *** No targets specified and no makefile found.  Stop.");
+
+        let podman = log(&["STEP 1/2: FROM alpine:3.19", "STEP 2/2: RUN apk add curl", "--> Using cache 9b2d1c3e4f5a", "COMMIT app"]);
+        assert_eq!(podman.summary(), "1 step: 1 cached, 0 rebuilt");
+        assert!(podman.failed_step().is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use colored::*;
+use crate::disk::{DiskUsage, UsageKind};
 use crate::docker::ContainerStats;
-use crate::utils::truncate_string;
+use crate::utils::{format_size, truncate_string};
 
 pub struct ChartRenderer;
 
@@ -20,28 +21,21 @@ impl ChartRenderer {
         println!("{}", "─".repeat(80).dimmed());
 
         for stat in stats {
-            let cpu_percent = stat.cpu_percent.replace('%', "").parse::<f32>().unwrap_or(0.0);
-            let bar_length = ((cpu_percent / 100.0) * 50.0) as usize;
-            
-            let bar = "█".repeat(bar_length);
-            let empty = "░".repeat(50 - bar_length);
-            
-            let color = if cpu_percent > 80.0 {
-                bar.red()
-            } else if cpu_percent > 50.0 {
-                bar.yellow()
-            } else {
-                bar.green()
-            };
-
+            // Normalised against the cores available, so a container using
+            // two of eight cores shows a quarter-full bar instead of 200%
+            let share = stat.cpu_share();
             let container_name = truncate_string(&stat.name, 20);
+            let cores = match stat.online_cpus {
+                0 => String::new(),
+                n => format!(" of {} {}", n, if n == 1 { "core" } else { "cores" }),
+            };
 
             println!(
-                "{:<20} {}{} {}%",
+                "{:<20} {} {}{}",
                 container_name.white(),
-                color,
-                empty.dimmed(),
-                cpu_percent.to_string().bold()
+                usage_bar(share),
+                format!("{:.1}%", share * 100.0).bold(),
+                cores.dimmed()
             );
         }
         println!();
@@ -58,29 +52,15 @@ impl ChartRenderer {
         println!("{}", "─".repeat(80).dimmed());
 
         for stat in stats {
-            let mem_percent = stat.memory_percent.replace('%', "").parse::<f32>().unwrap_or(0.0);
-            let bar_length = ((mem_percent / 100.0) * 50.0) as usize;
-            
-            let bar = "█".repeat(bar_length);
-            let empty = "░".repeat(50 - bar_length);
-            
-            let color = if mem_percent > 80.0 {
-                bar.red()
-            } else if mem_percent > 50.0 {
-                bar.yellow()
-            } else {
-                bar.green()
-            };
-
+            let mem_percent = stat.memory_percent();
             let container_name = truncate_string(&stat.name, 20);
 
             println!(
-                "{:<20} {}{} {}% ({})",
+                "{:<20} {} {} ({})",
                 container_name.white(),
-                color,
-                empty.dimmed(),
-                mem_percent.to_string().bold(),
-                stat.memory_usage.cyan()
+                usage_bar(mem_percent / 100.0),
+                format!("{:.1}%", mem_percent).bold(),
+                stat.memory_usage().cyan()
             );
         }
         println!();
@@ -96,18 +76,12 @@ impl ChartRenderer {
         println!("{}", "🍰 System Resource Overview".cyan().bold());
         println!("{}", "─".repeat(80).dimmed());
 
-        let total_cpu: f32 = stats.iter()
-            .map(|s| s.cpu_percent.replace('%', "").parse::<f32>().unwrap_or(0.0))
-            .sum();
-
-        let total_memory: f32 = stats.iter()
-            .map(|s| s.memory_percent.replace('%', "").parse::<f32>().unwrap_or(0.0))
-            .sum();
+        let total_cpu: f64 = stats.iter().map(|s| s.cpu_fraction).sum();
+        let total_memory: u64 = stats.iter().map(|s| s.memory_used).sum();
 
         println!("{}", "CPU Distribution:".yellow().bold());
         for stat in stats {
-            let cpu_percent = stat.cpu_percent.replace('%', "").parse::<f32>().unwrap_or(0.0);
-            let percentage = if total_cpu > 0.0 { (cpu_percent / total_cpu) * 100.0 } else { 0.0 };
+            let percentage = if total_cpu > 0.0 { stat.cpu_fraction / total_cpu * 100.0 } else { 0.0 };
             let slice = self.create_pie_slice(percentage);
             
             println!(
@@ -121,8 +95,11 @@ impl ChartRenderer {
         println!();
         println!("{}", "Memory Distribution:".yellow().bold());
         for stat in stats {
-            let mem_percent = stat.memory_percent.replace('%', "").parse::<f32>().unwrap_or(0.0);
-            let percentage = if total_memory > 0.0 { (mem_percent / total_memory) * 100.0 } else { 0.0 };
+            let percentage = if total_memory > 0 {
+                stat.memory_used as f64 / total_memory as f64 * 100.0
+            } else {
+                0.0
+            };
             let slice = self.create_pie_slice(percentage);
             
             println!(
@@ -135,7 +112,7 @@ impl ChartRenderer {
         println!();
     }
 
-    fn create_pie_slice(&self, percentage: f32) -> String {
+    fn create_pie_slice(&self, percentage: f64) -> String {
         let symbols = ["◐", "◑", "◒", "◓"];
         let index = ((percentage / 25.0) as usize).min(3);
         symbols[index].to_string()
@@ -152,7 +129,7 @@ impl ChartRenderer {
         println!("{}", "─".repeat(80).dimmed());
 
         for stat in stats {
-            let net_io = &stat.network_io;
+            let net_io = stat.network_io();
             println!(
                 "{:<20} {}",
                 stat.name.white(),
@@ -173,7 +150,7 @@ impl ChartRenderer {
         println!("{}", "─".repeat(80).dimmed());
 
         for stat in stats {
-            let block_io = &stat.block_io;
+            let block_io = stat.block_io();
             println!(
                 "{:<20} {}",
                 stat.name.white(),
@@ -271,6 +248,39 @@ impl ChartRenderer {
         println!();
     }
 
+    /// One bar per kind of resource, scaled to the largest, with the part a
+    /// prune would free drawn in yellow.
+    pub fn render_disk_usage_chart(&self, usage: &DiskUsage) {
+        if usage.items.is_empty() {
+            println!("{}", "No disk usage to display".yellow());
+            return;
+        }
+
+        println!();
+        println!("{}", "💽 Disk Usage".cyan().bold());
+        println!("{}", "─".repeat(80).dimmed());
+
+        let largest = UsageKind::ALL.iter().map(|kind| usage.total(*kind)).max().unwrap_or(0).max(1) as f64;
+        for kind in UsageKind::ALL {
+            let total = usage.total(kind);
+            let reclaimable = usage.reclaimable(kind);
+            let filled = bar_length(total as f64 / largest);
+            let freed = bar_length(reclaimable as f64 / largest).min(filled);
+            let note = if reclaimable > 0 { format!("({} reclaimable)", format_size(reclaimable)) } else { String::new() };
+            println!(
+                "{:<12} {}{}{} {} {}",
+                kind.label().white(),
+                "█".repeat(filled - freed).cyan(),
+                "█".repeat(freed).yellow(),
+                "░".repeat(BAR_WIDTH - filled).dimmed(),
+                format_size(total).bold(),
+                note.yellow()
+            );
+        }
+        println!("{:<12} {} in use  {} reclaimable", "", "█".cyan(), "█".yellow());
+        println!();
+    }
+
     fn parse_size(&self, size_str: &str) -> u64 {
         // Parse size strings like "1.2GB", "500MB", etc.
         let size_str = size_str.to_lowercase();
@@ -304,23 +314,23 @@ impl ChartRenderer {
         println!("{}", "─".repeat(100).dimmed());
 
         for stat in stats {
-            let cpu_percent = stat.cpu_percent.replace('%', "").parse::<f32>().unwrap_or(0.0);
-            let mem_percent = stat.memory_percent.replace('%', "").parse::<f32>().unwrap_or(0.0);
-            
-            let cpu_color = if cpu_percent > 80.0 {
-                cpu_percent.to_string().red()
-            } else if cpu_percent > 50.0 {
-                cpu_percent.to_string().yellow()
+            let cpu_share = stat.cpu_share() * 100.0;
+            let mem_percent = stat.memory_percent();
+
+            let cpu_color = if cpu_share > 80.0 {
+                stat.cpu_text().red()
+            } else if cpu_share > 50.0 {
+                stat.cpu_text().yellow()
             } else {
-                cpu_percent.to_string().green()
+                stat.cpu_text().green()
             };
 
             let mem_color = if mem_percent > 80.0 {
-                mem_percent.to_string().red()
+                stat.memory_text().red()
             } else if mem_percent > 50.0 {
-                mem_percent.to_string().yellow()
+                stat.memory_text().yellow()
             } else {
-                mem_percent.to_string().green()
+                stat.memory_text().green()
             };
 
             println!(
@@ -328,12 +338,56 @@ impl ChartRenderer {
                 stat.name.white(),
                 cpu_color,
                 mem_color,
-                stat.memory_usage.cyan(),
-                stat.network_io.dimmed(),
-                stat.block_io.magenta()
+                stat.memory_usage().cyan(),
+                stat.network_io().dimmed(),
+                stat.block_io().magenta()
             );
         }
         println!("{}", "═".repeat(100).dimmed());
         println!();
     }
-} 
\ No newline at end of file
+}
+
+const BAR_WIDTH: usize = 50;
+
+// Filled cells for a 0.0..=1.0 fraction; out-of-range samples are clamped
+// so an over-limit reading can't underflow the empty part of the bar
+fn bar_length(fraction: f64) -> usize {
+    (fraction.clamp(0.0, 1.0) * BAR_WIDTH as f64).round() as usize
+}
+
+fn usage_bar(fraction: f64) -> String {
+    let filled = bar_length(fraction);
+    let bar = "█".repeat(filled);
+    let bar = if fraction > 0.8 {
+        bar.red()
+    } else if fraction > 0.5 {
+        bar.yellow()
+    } else {
+        bar.green()
+    };
+    format!("{}{}", bar, "░".repeat(BAR_WIDTH - filled).dimmed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar_length_is_clamped() {
+        assert_eq!(bar_length(0.5), 25);
+        assert_eq!(bar_length(3.2), BAR_WIDTH);
+        assert_eq!(bar_length(-0.1), 0);
+        assert_eq!(bar_length(f64::NAN), 0);
+    }
+
+    #[test]
+    fn test_cpu_share_uses_available_cores() {
+        let stat = ContainerStats { cpu_fraction: 2.0, online_cpus: 8, ..Default::default() };
+        assert_eq!(stat.cpu_percent(), 200.0);
+        assert_eq!(bar_length(stat.cpu_share()), 13);
+
+        let unknown_cores = ContainerStats { cpu_fraction: 2.0, ..Default::default() };
+        assert_eq!(bar_length(unknown_cores.cpu_share()), BAR_WIDTH);
+    }
+}
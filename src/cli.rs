@@ -0,0 +1,1225 @@
+// Backend that shells out to the docker CLI.
+
+use std::io::{self, BufRead, BufReader, IsTerminal, Lines, Read};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use crate::backend::{Archive, BuildOutput, DockerBackend, EventStream, LogLines, ProgressStream, StatsStream};
+use crate::copy::PathStat;
+use crate::disk::DiskUsage;
+use crate::docker::{BuildOptions, Container, ContainerProcess, ContainerSpec, ContainerStats, ExecOptions, Image, Network, Volume};
+use crate::error::DockerError;
+use crate::events::{DockerEvent, EventFilter};
+use crate::logs::{LogLine, LogOptions, LogStream};
+use crate::progress::ProgressMessage;
+use crate::runtime::{json_records, container_from_record, images_from_record, stats_from_record, network_from_record, volume_from_record, Runtime, StatsBatches};
+use crate::tar::{EntryKind, TarReader};
+use crate::utils::format_size;
+
+#[derive(Debug, Clone)]
+pub struct CliBackend {
+    runtime: Runtime,
+    global_args: Vec<String>,
+    // Asked once, rather than with every stats call
+    online_cpus: Arc<OnceLock<u32>>,
+}
+
+impl Default for CliBackend {
+    fn default() -> Self {
+        CliBackend::new(Runtime::Docker, &[])
+    }
+}
+
+impl CliBackend {
+    /// `global_args` use docker's spelling, e.g. `--context buildbox`, and are
+    /// translated for the runtime before every subcommand.
+    pub fn new(runtime: Runtime, global_args: &[String]) -> Self {
+        CliBackend { runtime, global_args: runtime.cli_args(global_args), online_cpus: Arc::default() }
+    }
+
+    fn docker(&self) -> Command {
+        let mut command = Command::new(self.runtime.program());
+        command.args(&self.global_args);
+        command
+    }
+
+    // Stats rows only carry percentages, so the core count comes from `info`.
+    // 0 means unknown and charts fall back to one core; it is asked again next time.
+    fn online_cpus(&self) -> u32 {
+        if let Some(cpus) = self.online_cpus.get() {
+            return *cpus;
+        }
+        let format = match self.runtime {
+            Runtime::Docker => "{{.NCPU}}",
+            Runtime::Podman => "{{.Host.CPUs}}",
+        };
+        let cpus = self.docker()
+            .args(["info", "--format", format])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+            .unwrap_or(0);
+        if cpus > 0 {
+            let _ = self.online_cpus.set(cpus);
+        }
+        cpus
+    }
+}
+
+// Lines from a long-running docker process; the process is killed when
+// the consumer stops reading.
+struct ChildLines {
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+}
+
+impl Iterator for ChildLines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        match self.lines.next()? {
+            Ok(line) => Some(line),
+            Err(e) => {
+                eprintln!("Error reading docker output: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for ChildLines {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// `docker logs` output with each line tagged by the pipe it arrived on. Both
+// pipes are read on their own thread; the process is killed when the
+// consumer stops reading.
+struct ChildLogs {
+    child: Child,
+    lines: Receiver<LogLine>,
+}
+
+impl ChildLogs {
+    fn read_pipe(pipe: impl Read + Send + 'static, stream: LogStream, timestamps: bool, sender: Sender<LogLine>) {
+        thread::spawn(move || {
+            for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                if sender.send(LogLine::parse(stream, &line, timestamps)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+impl Iterator for ChildLogs {
+    type Item = LogLine;
+
+    fn next(&mut self) -> Option<LogLine> {
+        self.lines.recv().ok()
+    }
+}
+
+impl Drop for ChildLogs {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// `docker pull`/`push` output as progress messages. Docker writes progress to
+// stdout and podman to stderr, so layer lines are taken from both; other
+// stderr lines are kept as the error if the command fails.
+struct ChildProgress {
+    child: Option<Child>,
+    messages: Receiver<ProgressMessage>,
+    errors: Option<thread::JoinHandle<Vec<String>>>,
+}
+
+impl ChildProgress {
+    fn spawn(mut command: Command) -> Result<ChildProgress, DockerError> {
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+        let stdout = child.stdout.take().ok_or_else(|| "Failed to capture docker output".to_string())?;
+        let stderr = child.stderr.take().ok_or_else(|| "Failed to capture docker output".to_string())?;
+
+        let (sender, messages) = mpsc::channel();
+        let layers = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if let Some(message) = ProgressMessage::parse_cli(&line) {
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        let errors = thread::spawn(move || {
+            let mut errors = Vec::new();
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                match ProgressMessage::parse_cli(&line) {
+                    Some(message) if message.id.is_some() => {
+                        let _ = layers.send(message);
+                    }
+                    _ if !line.trim().is_empty() => errors.push(line),
+                    _ => {}
+                }
+            }
+            errors
+        });
+        Ok(ChildProgress { child: Some(child), messages, errors: Some(errors) })
+    }
+}
+
+impl Iterator for ChildProgress {
+    type Item = ProgressMessage;
+
+    fn next(&mut self) -> Option<ProgressMessage> {
+        if let Ok(message) = self.messages.recv() {
+            return Some(message);
+        }
+        // Both pipes are closed; the exit status decides how it ended
+        let status = self.child.take()?.wait();
+        let errors = self.errors.take().and_then(|errors| errors.join().ok()).unwrap_or_default();
+        match status {
+            Ok(status) if status.success() => None,
+            Ok(_) if !errors.is_empty() => Some(ProgressMessage::failure(errors.join("\n"))),
+            Ok(status) => Some(ProgressMessage::failure(format!("docker exited with {}", status))),
+            Err(e) => Some(ProgressMessage::failure(format!("Failed to wait for docker: {}", e))),
+        }
+    }
+}
+
+impl Drop for ChildProgress {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// Every line a command prints, from both pipes, as it prints them. If the
+// command fails the last item is an `Err` carrying its error line.
+struct ChildOutput {
+    child: Option<Child>,
+    lines: Receiver<String>,
+    subcommand: &'static str,
+    error: Option<String>,
+}
+
+impl ChildOutput {
+    fn spawn(mut command: Command, subcommand: &'static str) -> Result<ChildOutput, DockerError> {
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+        let stdout = child.stdout.take().ok_or_else(|| "Failed to capture docker output".to_string())?;
+        let stderr = child.stderr.take().ok_or_else(|| "Failed to capture docker output".to_string())?;
+
+        let (sender, lines) = mpsc::channel();
+        for pipe in [Box::new(stdout) as Box<dyn Read + Send>, Box::new(stderr)] {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        Ok(ChildOutput { child: Some(child), lines, subcommand, error: None })
+    }
+}
+
+impl Iterator for ChildOutput {
+    type Item = Result<String, DockerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Ok(line) = self.lines.recv() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("ERROR") || trimmed.starts_with("Error") || trimmed.contains("returned a non-zero code") {
+                self.error = Some(trimmed.to_string());
+            }
+            return Some(Ok(line));
+        }
+        match self.child.take()?.wait() {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(Err(match self.error.take() {
+                Some(error) => DockerError::from_message(error),
+                None => format!("docker {} exited with {}", self.subcommand, status).into(),
+            })),
+            Err(e) => Some(Err(format!("Failed to wait for docker {}: {}", self.subcommand, e).into())),
+        }
+    }
+}
+
+impl Drop for ChildOutput {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// The tar archive `docker cp` or `docker save` writes to stdout. If docker fails, its error
+// ends the stream; the process is killed when the reader stops early.
+struct ChildArchive {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for ChildArchive {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                let mut message = String::new();
+                if let Some(stderr) = self.child.stderr.as_mut() {
+                    stderr.read_to_string(&mut message)?;
+                }
+                return Err(io::Error::new(io::ErrorKind::Other, message.trim().to_string()));
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl Drop for ChildArchive {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// A program killed by a signal exits the way a shell reports it, 128 + signal
+#[cfg(unix)]
+fn signal_exit_code(status: &std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal().map_or(1, |signal| 128 + signal)
+}
+
+#[cfg(not(unix))]
+fn signal_exit_code(_status: &std::process::ExitStatus) -> i32 {
+    1
+}
+
+impl DockerBackend for CliBackend {
+    fn is_available(&self) -> bool {
+        self.docker()
+            .arg("--version")
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    fn ping(&self) -> bool {
+        self.docker()
+            .args(["info"])
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    // ===== CONTAINER COMMANDS =====
+
+    fn create_container(&self, spec: &ContainerSpec) -> Result<(), DockerError> {
+        spec.validate()?;
+
+        let output = self.docker()
+            .args(["run", "-d"])
+            .args(spec.cli_args())
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        // `run` only takes one network, the rest are joined once it exists
+        for network in spec.networks.iter().skip(1) {
+            let output = self.docker()
+                .args(["network", "connect", network, &spec.name])
+                .output()
+                .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+            if !output.status.success() {
+                return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_interactive(&self, name: &str, command: &[String], options: &ExecOptions) -> Result<i32, DockerError> {
+        let mut exec = self.docker();
+        exec.args(["exec", "--interactive"]).args(options.cli_args());
+        // docker refuses -t without a terminal; with one, the program's stderr
+        // goes through the PTY too, so the pipe only sees docker's own errors
+        let tty = io::stdin().is_terminal() && io::stdout().is_terminal();
+        if tty {
+            exec.arg("--tty").stderr(Stdio::piped());
+        }
+        let output = exec
+            .arg(name)
+            .args(command)
+            .spawn()
+            .and_then(|child| child.wait_with_output())
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if output.status.success() {
+            return Ok(0);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            return Err(DockerError::from_message(stderr));
+        }
+        // 125-127 are docker's own failures: daemon errors, or a command that can't run.
+        // Anything else is the program's own status, which the caller passes on
+        match output.status.code() {
+            Some(code @ 125..=127) => Err(format!("docker exec failed with exit code {}", code).into()),
+            Some(code) => Ok(code),
+            None => Ok(signal_exit_code(&output.status)),
+        }
+    }
+
+    fn attach_container(&self, name: &str) -> Result<(), DockerError> {
+        let mut child = self.docker()
+            .args(["attach", name])
+            .spawn()
+            .map_err(|e| format!("Failed to attach to container: {}", e))?;
+
+        child.wait()
+            .map_err(|e| format!("Failed to wait for attach process: {}", e))?;
+
+        Ok(())
+    }
+
+    fn commit_container(&self, container: &str, repository: &str, tag: Option<&str>) -> Result<(), DockerError> {
+        let mut args = vec!["commit"];
+        
+        if let Some(tag_value) = tag {
+            args.extend_from_slice(&[repository, tag_value]);
+        } else {
+            args.push(repository);
+        }
+        
+        args.push(container);
+
+        let output = self.docker()
+            .args(&args)
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn copy_from_container(&self, container: &str, path: &str) -> Result<Archive, DockerError> {
+        let mut child = self.docker()
+            .args(["cp", &format!("{}:{}", container, path), "-"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| "Failed to capture docker cp output".to_string())?;
+        Ok(Box::new(ChildArchive { child, stdout }))
+    }
+
+    fn copy_to_container(&self, container: &str, dir: &str, archive: &mut dyn Read) -> Result<(), DockerError> {
+        let mut child = self.docker()
+            .args(["cp", "-", &format!("{}:{}", container, dir)])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+        let mut stdin = child.stdin.take()
+            .ok_or_else(|| "Failed to open docker cp input".to_string())?;
+
+        // A broken pipe means docker gave up; its stderr says why
+        let sent = io::copy(archive, &mut stdin);
+        drop(stdin);
+        if let Err(e) = &sent {
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Failed to send archive to docker cp: {}", e).into());
+            }
+        }
+        let output = child.wait_with_output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn stat_container_path(&self, container: &str, path: &str) -> Result<Option<PathStat>, DockerError> {
+        // The CLI has no stat command; the first header of the archive `docker cp` would send says the same
+        let mut archive = TarReader::new(self.copy_from_container(container, path)?);
+        match archive.next_entry() {
+            Ok(Some(entry)) => Ok(Some(PathStat {
+                name: entry.path.trim_end_matches('/').to_string(),
+                size: entry.size,
+                is_dir: entry.kind == EntryKind::Directory,
+                link_target: entry.link.filter(|_| entry.kind == EntryKind::Symlink),
+            })),
+            Ok(None) => Ok(None),
+            Err(e) => {
+                let message = e.to_string();
+                let lower = message.to_lowercase();
+                if lower.contains("could not find") || lower.contains("could not be found") || lower.contains("no such file") {
+                    Ok(None)
+                } else {
+                    Err(DockerError::from_message(message))
+                }
+            }
+        }
+    }
+
+    fn diff_container(&self, container: &str) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["diff", container])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn export_container(&self, container: &str, output_file: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["export", "-o", output_file, container])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn get_image_history(&self, image: &str) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["history", image])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn import_image(&self, file: &str, repository: &str, tag: Option<&str>) -> Result<(), DockerError> {
+        let mut args = vec!["import"];
+        
+        if let Some(tag_value) = tag {
+            args.extend_from_slice(&[repository, tag_value]);
+        } else {
+            args.push(repository);
+        }
+        
+        args.push(file);
+
+        let output = self.docker()
+            .args(&args)
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn kill_container(&self, container: &str, signal: Option<&str>) -> Result<(), DockerError> {
+        let mut args = vec!["kill"];
+        
+        if let Some(sig) = signal {
+            args.extend_from_slice(&["-s", sig]);
+        }
+        
+        args.push(container);
+
+        let output = self.docker()
+            .args(&args)
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn load_image(&self, file: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["load", "-i", file])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn get_container_ports(&self, container: &str) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["port", container])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn rename_container(&self, old_name: &str, new_name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["rename", old_name, new_name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn save_image(&self, image: &str, output_file: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["save", "-o", output_file, image])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn export_image(&self, image: &str) -> Result<Archive, DockerError> {
+        let mut child = self.docker()
+            .args(["save", image])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| "Failed to capture docker save output".to_string())?;
+        Ok(Box::new(ChildArchive { child, stdout }))
+    }
+
+    fn get_container_processes(&self, container: &str) -> Result<Vec<ContainerProcess>, DockerError> {
+        let output = self.docker()
+            .args(["top", container])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let mut processes = Vec::new();
+
+        // Skip header line
+        for line in output_str.lines().skip(1) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 11 {
+                processes.push(ContainerProcess {
+                    user: parts[0].to_string(),
+                    pid: parts[1].to_string(),
+                    ppid: parts[2].to_string(),
+                    cpu: parts[3].to_string(),
+                    mem: parts[4].to_string(),
+                    vsz: parts[5].to_string(),
+                    rss: parts[6].to_string(),
+                    tty: parts[7].to_string(),
+                    stat: parts[8].to_string(),
+                    start: parts[9].to_string(),
+                    time: parts[10].to_string(),
+                    command: parts[11..].join(" "),
+                });
+            }
+        }
+
+        Ok(processes)
+    }
+
+    fn update_container(&self, container: &str, cpu_period: Option<&str>, cpu_quota: Option<&str>, 
+                          memory: Option<&str>, memory_swap: Option<&str>) -> Result<(), DockerError> {
+        let mut args = vec!["update"];
+        
+        if let Some(period) = cpu_period {
+            args.extend_from_slice(&["--cpu-period", period]);
+        }
+        
+        if let Some(quota) = cpu_quota {
+            args.extend_from_slice(&["--cpu-quota", quota]);
+        }
+        
+        if let Some(mem) = memory {
+            args.extend_from_slice(&["--memory", mem]);
+        }
+        
+        if let Some(mem_swap) = memory_swap {
+            args.extend_from_slice(&["--memory-swap", mem_swap]);
+        }
+        
+        args.push(container);
+
+        let output = self.docker()
+            .args(&args)
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn wait_for_container(&self, container: &str) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["wait", container])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    // ===== EXISTING CONTAINER COMMANDS =====
+
+    fn get_container_size(&self, name: &str) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["ps", "-s", "--format", "json", "--filter", &format!("name={}", name)])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        for json in json_records(&output_str, "container") {
+            match json.get("Size") {
+                Some(serde_json::Value::String(size)) => {
+                    // Parse size and format it
+                    if let Ok(size_bytes) = size.parse::<u64>() {
+                        return Ok(format_size(size_bytes));
+                    }
+                    return Ok(size.to_string());
+                }
+                // Podman reports the writable layer and root filesystem separately
+                Some(size) if size.is_object() => {
+                    let rw = size.get("rwSize").and_then(|v| v.as_u64()).unwrap_or(0);
+                    let root = size.get("rootFsSize").and_then(|v| v.as_u64()).unwrap_or(0);
+                    return Ok(format!("{} (virtual {})", format_size(rw), format_size(root)));
+                }
+                _ => continue,
+            }
+        }
+
+        Err("Container not found or size information unavailable".into())
+    }
+
+    fn list_containers(&self) -> Result<Vec<Container>, DockerError> {
+        let output = self.docker()
+            .args(["ps", "-a", "--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Docker command failed: {}", stderr).into());
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let containers: Vec<Container> = json_records(&output_str, "container")
+            .iter()
+            .filter_map(container_from_record)
+            .collect();
+
+        Ok(containers)
+    }
+
+    fn start_container(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["start", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn stop_container(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["stop", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn remove_container(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["rm", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn get_container_logs(&self, name: &str, options: &LogOptions) -> Result<LogLines, DockerError> {
+        // Once streaming, daemon errors would be indistinguishable from the
+        // container's own stderr, so check the container exists first
+        let output = self.docker()
+            .args(["container", "inspect", "--format", "{{.Id}}", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let mut child = self.docker()
+            .arg("logs")
+            .args(options.cli_args())
+            .arg(name)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start docker logs: {}", e))?;
+
+        let (sender, lines) = mpsc::channel();
+        let stdout = child.stdout.take()
+            .ok_or_else(|| "Failed to capture docker logs output".to_string())?;
+        let stderr = child.stderr.take()
+            .ok_or_else(|| "Failed to capture docker logs output".to_string())?;
+        ChildLogs::read_pipe(stdout, LogStream::Stdout, options.timestamps, sender.clone());
+        ChildLogs::read_pipe(stderr, LogStream::Stderr, options.timestamps, sender);
+
+        Ok(Box::new(ChildLogs { child, lines }))
+    }
+
+    fn get_container_stats(&self) -> Result<Vec<ContainerStats>, DockerError> {
+        let output = self.docker()
+            .args(["stats", "--no-stream", "--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Docker command failed: {}", stderr).into());
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let online_cpus = self.online_cpus();
+        let stats: Vec<ContainerStats> = json_records(&output_str, "stats")
+            .iter()
+            .filter_map(|record| stats_from_record(record, online_cpus))
+            .collect();
+
+        Ok(stats)
+    }
+
+    fn stream_stats(&self) -> Result<StatsStream, DockerError> {
+        let online_cpus = self.online_cpus();
+        let mut child = self.docker()
+            .args(["stats", "--format", "json"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start docker stats: {}", e))?;
+
+        let stdout = child.stdout.take()
+            .ok_or_else(|| "Failed to capture docker stats output".to_string())?;
+
+        let lines = ChildLines { lines: BufReader::new(stdout).lines(), child };
+        Ok(Box::new(StatsBatches::new(lines, online_cpus)))
+    }
+
+    fn get_stats_for_container(&self, container: &str) -> Result<ContainerStats, DockerError> {
+        let output = self.docker()
+            .args(["stats", "--no-stream", "--format", "json", container])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let online_cpus = self.online_cpus();
+        json_records(&output_str, "stats")
+            .iter()
+            .find_map(|record| stats_from_record(record, online_cpus))
+            .ok_or_else(|| format!("No stats reported for container: {}", container).into())
+    }
+
+    fn restart_container(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["restart", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn pause_container(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["pause", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn unpause_container(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["unpause", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn exec_container(&self, name: &str, command: &str, options: &ExecOptions) -> Result<String, DockerError> {
+        let output = self.docker()
+            .arg("exec")
+            .args(options.cli_args())
+            .args([name, "sh", "-c", command])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn inspect_container(&self, name: &str) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["inspect", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    // ===== IMAGE COMMANDS =====
+
+    fn list_images(&self) -> Result<Vec<Image>, DockerError> {
+        let output = self.docker()
+            .args(["images", "--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Docker command failed: {}", stderr).into());
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let images: Vec<Image> = json_records(&output_str, "image")
+            .iter()
+            .flat_map(images_from_record)
+            .collect();
+
+        Ok(images)
+    }
+
+    fn image_exists(&self, name: &str) -> Result<bool, DockerError> {
+        let output = self.docker()
+            .args(["image", "inspect", "--format", "{{.Id}}", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.to_lowercase().contains("no such image") || stderr.contains("image not known") {
+                return Ok(false);
+            }
+            return Err(DockerError::from_message(stderr));
+        }
+
+        Ok(true)
+    }
+
+    fn image_repo_digests(&self, name: &str) -> Result<Vec<String>, DockerError> {
+        let output = self.docker()
+            .args(["image", "inspect", "--format", "{{json .RepoDigests}}", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        // null for images that were never pulled or pushed
+        let digests: Option<Vec<String>> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Failed to parse image digests: {}", e))?;
+        Ok(digests.unwrap_or_default())
+    }
+
+    fn inspect_image(&self, name: &str) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["image", "inspect", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn pull_image(&self, name: &str) -> Result<ProgressStream, DockerError> {
+        let mut command = self.docker();
+        command.args(["pull", name]);
+        Ok(Box::new(ChildProgress::spawn(command)?))
+    }
+
+    fn remove_image(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["rmi", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn build_image(&self, options: &BuildOptions) -> Result<BuildOutput, DockerError> {
+        let mut command = self.docker();
+        command.arg("build");
+        // BuildKit otherwise draws a terminal UI that can't be followed line by line
+        if self.runtime == Runtime::Docker {
+            command.arg("--progress=plain");
+        }
+        command.args(options.cli_args());
+        Ok(Box::new(ChildOutput::spawn(command, "build")?))
+    }
+
+    fn tag_image(&self, source: &str, target: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["tag", source, target])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn push_image(&self, name: &str) -> Result<ProgressStream, DockerError> {
+        let mut command = self.docker();
+        command.args(["push", name]);
+        Ok(Box::new(ChildProgress::spawn(command)?))
+    }
+
+    // ===== SYSTEM COMMANDS =====
+
+    fn get_system_info(&self) -> Result<String, DockerError> {
+        let output = self.docker()
+            .args(["system", "info"])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn disk_usage(&self) -> Result<DiskUsage, DockerError> {
+        let output = self.docker()
+            .args(["system", "df", "-v"])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(DiskUsage::parse_verbose(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn events(&self, filter: &EventFilter) -> Result<EventStream, DockerError> {
+        let format = match self.runtime {
+            Runtime::Docker => "{{json .}}",
+            Runtime::Podman => "json",
+        };
+        let mut child = self.docker()
+            .args(["events", "--format", format])
+            .args(filter.cli_args())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start docker events: {}", e))?;
+
+        let stdout = child.stdout.take()
+            .ok_or_else(|| "Failed to capture docker events output".to_string())?;
+
+        let lines = ChildLines {
+            lines: BufReader::new(stdout).lines(),
+            child,
+        };
+        Ok(Box::new(lines.filter_map(|line| DockerEvent::from_json(&serde_json::from_str(&line).ok()?))))
+    }
+
+    // ===== NETWORK COMMANDS =====
+
+    fn list_networks(&self) -> Result<Vec<Network>, DockerError> {
+        let output = self.docker()
+            .args(["network", "ls", "--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Docker command failed: {}", stderr).into());
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let networks: Vec<Network> = json_records(&output_str, "network")
+            .iter()
+            .filter_map(network_from_record)
+            .collect();
+
+        Ok(networks)
+    }
+
+    // ===== VOLUME COMMANDS =====
+
+    fn list_volumes(&self) -> Result<Vec<Volume>, DockerError> {
+        let output = self.docker()
+            .args(["volume", "ls", "--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Docker command failed: {}", stderr).into());
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let volumes: Vec<Volume> = json_records(&output_str, "volume")
+            .iter()
+            .filter_map(volume_from_record)
+            .collect();
+
+        Ok(volumes)
+    }
+
+    fn remove_network(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["network", "rm", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn remove_volume(&self, name: &str) -> Result<(), DockerError> {
+        let output = self.docker()
+            .args(["volume", "rm", name])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(())
+    }
+
+    fn network_containers(&self, network: &str) -> Result<usize, DockerError> {
+        // `network inspect` only lists running endpoints; stopped containers still hold the network
+        let output = self.docker()
+            .args(["ps", "-a", "-q", "--filter", &format!("network={}", network)])
+            .output()
+            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+
+        if !output.status.success() {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).lines().filter(|line| !line.trim().is_empty()).count())
+    }
+}
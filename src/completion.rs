@@ -19,11 +19,11 @@ impl DockerCompleter {
 
     fn get_commands() -> Vec<&'static str> {
         vec![
-            "containers", "images", "networks", "volumes", "monitor", "interactive",
+            "containers", "images", "networks", "volumes", "monitor", "interactive", "shell",
             "list", "start", "stop", "restart", "pause", "unpause", "remove", "logs", "exec", "inspect", "create", "size", "info",
             "attach", "commit", "cp", "diff", "export", "kill", "port", "rename", "top", "update", "wait",
             "pull", "build", "tag", "push", "history", "import", "load", "save",
-            "stats", "system", "events", "dashboard", "charts",
+            "stats", "system", "events", "dashboard", "charts", "prune",
             "help", "exit", "quit", "back"
         ]
     }
@@ -113,6 +113,16 @@ impl Completer for DockerCompleter {
                         }
                     }
                 }
+                "shell" | "logs" => {
+                    for name in self.get_container_names() {
+                        if name.starts_with(partial) {
+                            completions.push(Pair {
+                                display: name.clone(),
+                                replacement: name,
+                            });
+                        }
+                    }
+                }
                 "monitor" => {
                     // Complete monitor subcommands
                     let subcommands = vec!["stats", "system", "events", "dashboard", "charts"];
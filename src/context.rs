@@ -0,0 +1,284 @@
+// Docker contexts and daemon selection.
+//
+// Follows the docker CLI's precedence: --host, --context, DOCKER_HOST,
+// DOCKER_CONTEXT, then `currentContext` from ~/.docker/config.json.
+// Context metadata lives in ~/.docker/contexts/meta/<hash>/meta.json.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use crate::error::DockerError;
+
+pub const DEFAULT_CONTEXT: &str = "default";
+
+#[cfg(unix)]
+const DEFAULT_HOST: &str = "unix:///var/run/docker.sock";
+#[cfg(not(unix))]
+const DEFAULT_HOST: &str = "npipe:////./pipe/docker_engine";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockerContext {
+    pub name: String,
+    pub description: String,
+    pub host: String,
+    pub tls: bool,
+}
+
+/// The daemon a `DockerClient` talks to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    /// Shown in the interactive prompt: a context name or the --host value.
+    pub label: String,
+    /// Endpoint address; None means the platform's default socket.
+    pub host: Option<String>,
+    pub tls: bool,
+    /// Global arguments that point the docker CLI at the same daemon.
+    pub cli_args: Vec<String>,
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target {
+            label: DEFAULT_CONTEXT.to_string(),
+            host: None,
+            tls: false,
+            cli_args: Vec::new(),
+        }
+    }
+}
+
+impl Target {
+    /// Picks the daemon from the global flags, then the environment, then
+    /// the docker config file.
+    pub fn resolve(context: Option<&str>, host: Option<&str>) -> Result<Target, DockerError> {
+        if let Some(host) = host {
+            return Ok(Target {
+                label: host.to_string(),
+                host: Some(host.to_string()),
+                tls: env_flag("DOCKER_TLS_VERIFY"),
+                cli_args: vec!["--host".to_string(), host.to_string()],
+            });
+        }
+        if let Some(name) = context {
+            return Target::for_context(name);
+        }
+        if let Some(host) = env_value("DOCKER_HOST") {
+            // The docker CLI reads DOCKER_HOST itself, no extra arguments needed
+            return Ok(Target {
+                label: DEFAULT_CONTEXT.to_string(),
+                host: Some(host),
+                tls: env_flag("DOCKER_TLS_VERIFY"),
+                cli_args: Vec::new(),
+            });
+        }
+        Target::for_context(&current_context_name())
+    }
+
+    fn for_context(name: &str) -> Result<Target, DockerError> {
+        if name == DEFAULT_CONTEXT {
+            return Ok(Target::default());
+        }
+        let context = find_context(name)?;
+        Ok(Target {
+            label: context.name,
+            host: Some(context.host),
+            tls: context.tls,
+            cli_args: vec!["--context".to_string(), name.to_string()],
+        })
+    }
+
+    /// Whether the daemon runs on this machine, so starting it locally makes sense.
+    pub fn is_local(&self) -> bool {
+        match &self.host {
+            None => true,
+            Some(host) => host.starts_with("unix://") || host.starts_with("npipe://"),
+        }
+    }
+}
+
+/// The context selected by DOCKER_CONTEXT or `docker context use`.
+pub fn current_context_name() -> String {
+    if let Some(name) = env_value("DOCKER_CONTEXT") {
+        return name;
+    }
+    config_dir()
+        .and_then(|dir| read_json(&dir.join("config.json")))
+        .and_then(|config| config.get("currentContext").and_then(|c| c.as_str()).map(|c| c.to_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_CONTEXT.to_string())
+}
+
+pub fn list_contexts() -> Result<Vec<DockerContext>, DockerError> {
+    let mut contexts = vec![DockerContext {
+        name: DEFAULT_CONTEXT.to_string(),
+        description: "Current DOCKER_HOST based configuration".to_string(),
+        host: env_value("DOCKER_HOST").unwrap_or_else(|| DEFAULT_HOST.to_string()),
+        tls: env_flag("DOCKER_TLS_VERIFY"),
+    }];
+    if let Some(dir) = config_dir() {
+        contexts.extend(read_contexts(&dir)?);
+    }
+    Ok(contexts)
+}
+
+pub fn find_context(name: &str) -> Result<DockerContext, DockerError> {
+    list_contexts()?
+        .into_iter()
+        .find(|context| context.name == name)
+        .ok_or_else(|| format!("context \"{}\" does not exist", name).into())
+}
+
+/// Makes `name` the current context for dui and the docker CLI alike.
+pub fn use_context(name: &str) -> Result<(), DockerError> {
+    find_context(name)?;
+    let dir = config_dir().ok_or("Cannot locate the docker config directory; set DOCKER_CONFIG")?;
+    write_current_context(&dir, name)
+}
+
+fn read_contexts(config_dir: &Path) -> Result<Vec<DockerContext>, DockerError> {
+    let meta_dir = config_dir.join("contexts").join("meta");
+    let entries = match fs::read_dir(&meta_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut contexts = Vec::new();
+    for entry in entries.flatten() {
+        let meta = match read_json(&entry.path().join("meta.json")) {
+            Some(meta) => meta,
+            None => continue,
+        };
+        let name = match meta.get("Name").and_then(|n| n.as_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let docker = &meta["Endpoints"]["docker"];
+        // TLS material is stored under the same hash directory as the metadata
+        let tls_dir = config_dir.join("contexts").join("tls").join(entry.file_name()).join("docker");
+        contexts.push(DockerContext {
+            name,
+            description: meta["Metadata"]["Description"].as_str().unwrap_or("").to_string(),
+            host: docker["Host"].as_str().unwrap_or("").to_string(),
+            tls: tls_dir.exists() || docker["SkipTLSVerify"].as_bool().unwrap_or(false),
+        });
+    }
+    contexts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(contexts)
+}
+
+fn write_current_context(config_dir: &Path, name: &str) -> Result<(), DockerError> {
+    let path = config_dir.join("config.json");
+    // Only a missing file starts from scratch; anything else would lose the user's logins
+    let mut config = match fs::read(&path) {
+        Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => Value::Object(Default::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+    };
+    let object = config
+        .as_object_mut()
+        .ok_or_else(|| format!("{} is not a JSON object", path.display()))?;
+    if name == DEFAULT_CONTEXT {
+        object.remove("currentContext");
+    } else {
+        object.insert("currentContext".to_string(), Value::String(name.to_string()));
+    }
+
+    fs::create_dir_all(config_dir).map_err(|e| format!("Failed to create {}: {}", config_dir.display(), e))?;
+    let data = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    // Write beside it and swap it in, so a failed write leaves the old file whole
+    let temp = config_dir.join(format!("config.json.{}.tmp", std::process::id()));
+    fs::write(&temp, data)
+        .and_then(|_| fs::rename(&temp, &path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            format!("Failed to write {}: {}", path.display(), e).into()
+        })
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env_value("DOCKER_CONFIG") {
+        return Some(PathBuf::from(dir));
+    }
+    env_value("HOME")
+        .or_else(|| env_value("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".docker"))
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    let data = fs::read(path).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn env_flag(name: &str) -> bool {
+    env_value(name).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dui-context-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_read_contexts_from_metadata() {
+        let dir = scratch_dir("read");
+        let meta = dir.join("contexts/meta/0a1b2c");
+        fs::create_dir_all(&meta).unwrap();
+        fs::write(
+            meta.join("meta.json"),
+            r#"{"Name":"buildbox","Metadata":{"Description":"Shared build box"},"Endpoints":{"docker":{"Host":"tcp://10.0.0.5:2376","SkipTLSVerify":false}}}"#,
+        )
+        .unwrap();
+        fs::create_dir_all(dir.join("contexts/tls/0a1b2c/docker")).unwrap();
+
+        let contexts = read_contexts(&dir).unwrap();
+        assert_eq!(
+            contexts,
+            vec![DockerContext {
+                name: "buildbox".to_string(),
+                description: "Shared build box".to_string(),
+                host: "tcp://10.0.0.5:2376".to_string(),
+                tls: true,
+            }]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_current_context_keeps_other_settings() {
+        let dir = scratch_dir("write");
+        fs::write(dir.join("config.json"), r#"{"auths":{"ghcr.io":{}}}"#).unwrap();
+
+        write_current_context(&dir, "rootless").unwrap();
+        let config = read_json(&dir.join("config.json")).unwrap();
+        assert_eq!(config["currentContext"], "rootless");
+        assert!(config["auths"]["ghcr.io"].is_object());
+
+        write_current_context(&dir, DEFAULT_CONTEXT).unwrap();
+        assert!(read_json(&dir.join("config.json")).unwrap().get("currentContext").is_none());
+
+        // A config that doesn't parse is left as it was
+        fs::write(dir.join("config.json"), r#"{"auths":{"ghcr.io":{}},"#).unwrap();
+        assert!(write_current_context(&dir, "rootless").is_err());
+        assert_eq!(fs::read_to_string(dir.join("config.json")).unwrap(), r#"{"auths":{"ghcr.io":{}},"#);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_host_flag_wins() {
+        let target = Target::resolve(Some("ignored"), Some("tcp://10.0.0.5:2375")).unwrap();
+        assert_eq!(target.label, "tcp://10.0.0.5:2375");
+        assert_eq!(target.cli_args, vec!["--host", "tcp://10.0.0.5:2375"]);
+        assert!(!target.is_local());
+    }
+}
@@ -0,0 +1,344 @@
+// `dui containers cp`: copies between the host and containers, either way.
+//
+// Backends move tar archives, like the daemon's archive endpoints and
+// `docker cp -` do; this module packs and unpacks them on the host side and
+// resolves destinations the way `docker cp` does: copying onto an existing
+// directory puts the source inside it, anything else copies to that name.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::docker::DockerClient;
+use crate::error::DockerError;
+use crate::tar::{self, Packer};
+
+/// How often a copy in progress reports.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// One end of a copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopyTarget {
+    /// `container:path`
+    Container { container: String, path: String },
+    /// A path on the host.
+    Local(String),
+    /// `-`: a tar archive on stdout or stdin.
+    Stream,
+}
+
+impl CopyTarget {
+    /// Host paths that are absolute or start with `.` may contain colons;
+    /// anything else with one names a container, as in `docker cp`.
+    pub fn parse(text: &str) -> CopyTarget {
+        if text == "-" {
+            return CopyTarget::Stream;
+        }
+        if text.starts_with('/') || text.starts_with('.') {
+            return CopyTarget::Local(text.to_string());
+        }
+        match text.split_once(':') {
+            Some((container, path)) if !container.is_empty() => {
+                CopyTarget::Container { container: container.to_string(), path: path.to_string() }
+            }
+            _ => CopyTarget::Local(text.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for CopyTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CopyTarget::Container { container, path } => write!(f, "{}:{}", container, path),
+            CopyTarget::Local(path) => write!(f, "{}", path),
+            CopyTarget::Stream => write!(f, "-"),
+        }
+    }
+}
+
+/// What a path inside a container is, according to the daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathStat {
+    pub name: String,
+    pub size: u64,
+    pub is_dir: bool,
+    /// Set when the path is a symlink.
+    pub link_target: Option<String>,
+}
+
+/// How far a copy has got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CopyProgress {
+    /// Archive bytes moved so far.
+    pub bytes: u64,
+    /// Size of the archive, when it is known up front.
+    pub total: Option<u64>,
+    /// Files, directories and links copied; when sending from the host,
+    /// everything in the archive.
+    pub entries: u64,
+}
+
+/// Copies `src` to `dest`, reporting progress every `PROGRESS_INTERVAL`.
+/// `-` streams a tar archive to stdout or from stdin. Returns the final totals.
+pub fn copy(docker: &DockerClient, src: &CopyTarget, dest: &CopyTarget, progress: impl FnMut(&CopyProgress)) -> Result<CopyProgress, DockerError> {
+    let mut reporter = Reporter::new(progress);
+    match (src, dest) {
+        (CopyTarget::Container { container, path }, CopyTarget::Local(dest)) => {
+            copy_from_container(docker, container, path, Path::new(dest), &mut reporter)?
+        }
+        (CopyTarget::Local(src), CopyTarget::Container { container, path }) => {
+            copy_to_container(docker, Path::new(src), container, path, &mut reporter)?
+        }
+        (CopyTarget::Container { container, path }, CopyTarget::Stream) => {
+            export_archive(docker, container, path, &mut io::stdout().lock(), &mut reporter)?
+        }
+        (CopyTarget::Stream, CopyTarget::Container { container, path }) => {
+            import_archive(docker, &mut io::stdin().lock(), container, path, &mut reporter)?
+        }
+        (CopyTarget::Container { .. }, CopyTarget::Container { .. }) => {
+            return Err(DockerError::Other("Copying between containers is not supported; copy to the host first".to_string()));
+        }
+        _ => {
+            return Err(DockerError::Other("One side of the copy must be a container path, e.g. web:/etc/nginx".to_string()));
+        }
+    }
+    reporter.finish();
+    Ok(reporter.progress)
+}
+
+fn copy_from_container<F: FnMut(&CopyProgress)>(
+    docker: &DockerClient,
+    container: &str,
+    path: &str,
+    dest: &Path,
+    reporter: &mut Reporter<F>,
+) -> Result<(), DockerError> {
+    let stat = docker.stat_container_path(container, path)?.ok_or_else(|| {
+        DockerError::Other(format!("Error response from daemon: Could not find the file {} in container {}", path, container))
+    })?;
+    if stat.is_dir && dest.exists() && !dest.is_dir() {
+        return Err(DockerError::Other(format!("Cannot copy a directory onto the file {}", dest.display())));
+    }
+
+    let (dir, root) = if dest.is_dir() {
+        (dest.to_path_buf(), None)
+    } else if dest.to_string_lossy().ends_with('/') {
+        if !stat.is_dir {
+            return Err(DockerError::Other(format!("Destination directory {} does not exist", dest.display())));
+        }
+        fs::create_dir_all(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+        (dest.to_path_buf(), None)
+    } else {
+        let parent = dest.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        if !parent.is_dir() {
+            return Err(DockerError::Other(format!("Destination directory {} does not exist", parent.display())));
+        }
+        let name = dest.file_name().map(|name| name.to_string_lossy().into_owned());
+        (parent.to_path_buf(), name)
+    };
+
+    // A single file's archive is its header, contents and padding
+    if !stat.is_dir {
+        reporter.progress.total = Some(512 + stat.size + tar::padding(stat.size) as u64 + 1024);
+    }
+    let archive = docker.copy_from_container(container, path)?;
+    tar::unpack(archive, &dir, root.as_deref(), |bytes, entries| reporter.update(bytes, entries)).map_err(io_error)?;
+    Ok(())
+}
+
+fn copy_to_container<F: FnMut(&CopyProgress)>(
+    docker: &DockerClient,
+    src: &Path,
+    container: &str,
+    path: &str,
+    reporter: &mut Reporter<F>,
+) -> Result<(), DockerError> {
+    let metadata = fs::symlink_metadata(src).map_err(|e| format!("{}: {}", src.display(), e))?;
+    let (dir, root) = match stat_following_link(docker, container, path)? {
+        Some(stat) if stat.is_dir => (path.to_string(), local_name(src)?),
+        Some(_) if metadata.is_dir() => {
+            return Err(DockerError::Other(format!("Cannot copy a directory onto the file {}:{}", container, path)));
+        }
+        _ => split_container_path(path),
+    };
+
+    let packer = Packer::new(src, &root).map_err(io_error)?;
+    reporter.progress.total = Some(packer.size());
+    let entries = packer.entries() as u64;
+    let mut archive = Counted { inner: packer, bytes: 0, report: |bytes| reporter.update(bytes, entries) };
+    docker.copy_to_container(container, &dir, &mut archive)
+}
+
+// Follows a symlink once, so copying onto /var/run lands inside /run
+fn stat_following_link(docker: &DockerClient, container: &str, path: &str) -> Result<Option<PathStat>, DockerError> {
+    match docker.stat_container_path(container, path)? {
+        Some(PathStat { link_target: Some(target), .. }) => {
+            let target = if target.starts_with('/') { target } else { format!("{}/{}", split_container_path(path).0, target) };
+            docker.stat_container_path(container, &target)
+        }
+        stat => Ok(stat),
+    }
+}
+
+/// Writes `path` in `container` to `out` as a tar archive.
+pub fn export_archive<F: FnMut(&CopyProgress)>(
+    docker: &DockerClient,
+    container: &str,
+    path: &str,
+    out: &mut dyn Write,
+    reporter: &mut Reporter<F>,
+) -> Result<(), DockerError> {
+    let archive = docker.copy_from_container(container, path)?;
+    let mut archive = Counted { inner: archive, bytes: 0, report: |bytes| reporter.update(bytes, 0) };
+    io::copy(&mut archive, out).map_err(io_error)?;
+    out.flush().map_err(io_error)
+}
+
+/// Extracts the tar archive read from `input` into the directory `path` in `container`.
+pub fn import_archive<F: FnMut(&CopyProgress)>(
+    docker: &DockerClient,
+    input: &mut dyn Read,
+    container: &str,
+    path: &str,
+    reporter: &mut Reporter<F>,
+) -> Result<(), DockerError> {
+    let mut archive = Counted { inner: input, bytes: 0, report: |bytes| reporter.update(bytes, 0) };
+    docker.copy_to_container(container, path, &mut archive)
+}
+
+// The name a host path is copied under; `.` and `..` are named after the directory they mean
+fn local_name(path: &Path) -> Result<String, DockerError> {
+    let name = match path.file_name() {
+        Some(name) => Some(name.to_os_string()),
+        None => fs::canonicalize(path).ok().and_then(|path| path.file_name().map(|name| name.to_os_string())),
+    };
+    name.map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| DockerError::Other(format!("Cannot copy {}: it has no name to copy it under", path.display())))
+}
+
+// "/etc/nginx/nginx.conf" -> ("/etc/nginx", "nginx.conf"). Container paths
+// are relative to its root, as in `docker cp`.
+fn split_container_path(path: &str) -> (String, String) {
+    let path = path.trim_end_matches('/');
+    match path.rsplit_once('/') {
+        Some(("", name)) => ("/".to_string(), name.to_string()),
+        Some((dir, name)) => (dir.to_string(), name.to_string()),
+        None => ("/".to_string(), path.to_string()),
+    }
+}
+
+fn io_error(error: io::Error) -> DockerError {
+    DockerError::from_message(error.to_string())
+}
+
+/// Passes progress on at most every `PROGRESS_INTERVAL`, so drawing it
+/// doesn't slow the copy down.
+pub struct Reporter<F> {
+    callback: F,
+    last: Option<Instant>,
+    progress: CopyProgress,
+}
+
+impl<F: FnMut(&CopyProgress)> Reporter<F> {
+    pub fn new(callback: F) -> Reporter<F> {
+        Reporter { callback, last: None, progress: CopyProgress::default() }
+    }
+
+    fn update(&mut self, bytes: u64, entries: u64) {
+        self.progress.bytes = bytes;
+        self.progress.entries = entries;
+        if self.last.map_or(true, |last| last.elapsed() >= PROGRESS_INTERVAL) {
+            self.last = Some(Instant::now());
+            (self.callback)(&self.progress);
+        }
+    }
+
+    fn finish(&mut self) {
+        (self.callback)(&self.progress);
+    }
+}
+
+// Counts what passes through, for progress
+struct Counted<R, F> {
+    inner: R,
+    bytes: u64,
+    report: F,
+}
+
+impl<R: Read, F: FnMut(u64)> Read for Counted<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+        (self.report)(self.bytes);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use crate::fake::FakeBackend;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dui-copy-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(
+            CopyTarget::parse("web:/etc/nginx"),
+            CopyTarget::Container { container: "web".to_string(), path: "/etc/nginx".to_string() }
+        );
+        assert_eq!(CopyTarget::parse("./backup:2024"), CopyTarget::Local("./backup:2024".to_string()));
+        assert_eq!(CopyTarget::parse("notes.txt"), CopyTarget::Local("notes.txt".to_string()));
+        assert_eq!(CopyTarget::parse("-"), CopyTarget::Stream);
+        assert_eq!(split_container_path("/etc/nginx/nginx.conf"), ("/etc/nginx".to_string(), "nginx.conf".to_string()));
+        assert_eq!(split_container_path("/srv/"), ("/".to_string(), "srv".to_string()));
+    }
+
+    #[test]
+    fn test_copy_both_ways() {
+        let dir = scratch("both-ways");
+        let docker = DockerClient::with_backend(Arc::new(FakeBackend::demo()));
+        let container = |path: &str| CopyTarget::Container { container: "web".to_string(), path: path.to_string() };
+        let local = |path: &Path| CopyTarget::Local(path.display().to_string());
+
+        // Out of the container: into an existing directory, then to a new name
+        let done = copy(&docker, &container("/etc/nginx"), &local(&dir), |_| {}).unwrap();
+        assert!(done.entries >= 3);
+        assert!(fs::read_to_string(dir.join("nginx/conf.d/default.conf")).unwrap().contains("listen 80"));
+        copy(&docker, &container("/etc/nginx/nginx.conf"), &local(&dir.join("copy.conf")), |_| {}).unwrap();
+        assert!(dir.join("copy.conf").is_file());
+
+        // Back in: onto a directory and to a new name
+        fs::create_dir(dir.join("site")).unwrap();
+        fs::write(dir.join("site/index.html"), "<h1>shop</h1>").unwrap();
+        let mut updates = Vec::new();
+        let done = copy(&docker, &local(&dir.join("site")), &container("/usr/share/nginx"), |p| updates.push(*p)).unwrap();
+        assert_eq!(done.entries, 2);
+        assert_eq!(done.bytes, done.total.unwrap());
+        assert!(!updates.is_empty());
+        copy(&docker, &local(&dir.join("copy.conf")), &container("/tmp/nginx.conf.bak"), |_| {}).unwrap();
+
+        let mut archive = Vec::new();
+        export_archive(&docker, "web", "/usr/share/nginx/site", &mut archive, &mut Reporter::new(|_| {})).unwrap();
+        let mut reader = tar::TarReader::new(archive.as_slice());
+        assert_eq!(reader.next_entry().unwrap().unwrap().path, "site/");
+        assert_eq!(reader.next_entry().unwrap().unwrap().path, "site/index.html");
+        assert!(docker.stat_container_path("web", "/tmp/nginx.conf.bak").unwrap().is_some());
+
+        assert!(copy(&docker, &container("/nope"), &local(&dir), |_| {}).is_err());
+        assert!(copy(&docker, &local(&dir), &local(&dir), |_| {}).is_err());
+        assert!(matches!(
+            copy(&docker, &CopyTarget::parse("ghost:/etc"), &local(&dir), |_| {}),
+            Err(DockerError::NoSuchContainer(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,433 @@
+// Disk usage by images, containers, volumes and build cache.
+//
+// The CLI backend parses `docker system df -v`, whose tables are aligned by
+// a tabwriter: columns are cut at the header's column starts rather than
+// split on whitespace, as CREATED and STATUS cells have spaces in them. The
+// Engine backend maps `/system/df` onto the same items. Each item says
+// whether a prune would remove it, so the reclaim plan can total the space
+// each prune would free.
+
+use serde_json::Value;
+use crate::utils::parse_size;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageKind {
+    Image,
+    Container,
+    Volume,
+    BuildCache,
+}
+
+impl UsageKind {
+    pub const ALL: [UsageKind; 4] = [UsageKind::Image, UsageKind::Container, UsageKind::Volume, UsageKind::BuildCache];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UsageKind::Image => "Images",
+            UsageKind::Container => "Containers",
+            UsageKind::Volume => "Volumes",
+            UsageKind::BuildCache => "Build cache",
+        }
+    }
+}
+
+/// Why an item could be reclaimed, which is also the prune that would do it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reclaim {
+    StoppedContainer,
+    /// Untagged and unused.
+    DanglingImage,
+    /// Tagged but no container uses it.
+    UnusedImage,
+    UnusedVolume,
+    UnusedCache,
+}
+
+impl Reclaim {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Reclaim::StoppedContainer => "stopped",
+            Reclaim::DanglingImage => "dangling",
+            Reclaim::UnusedImage | Reclaim::UnusedVolume | Reclaim::UnusedCache => "unused",
+        }
+    }
+
+    /// What it removes, e.g. "image".
+    pub fn noun(&self) -> &'static str {
+        match self {
+            Reclaim::StoppedContainer => "container",
+            Reclaim::DanglingImage | Reclaim::UnusedImage => "image",
+            Reclaim::UnusedVolume => "volume",
+            Reclaim::UnusedCache => "cache record",
+        }
+    }
+
+    pub fn command(&self) -> &'static str {
+        match self {
+            Reclaim::StoppedContainer => "docker container prune",
+            Reclaim::DanglingImage => "docker image prune",
+            Reclaim::UnusedImage => "docker image prune -a",
+            Reclaim::UnusedVolume => "docker volume prune -a",
+            Reclaim::UnusedCache => "docker builder prune",
+        }
+    }
+
+    // `image prune -a` removes dangling images as well
+    fn covers(&self, reclaim: Reclaim) -> bool {
+        *self == reclaim || (*self == Reclaim::UnusedImage && reclaim == Reclaim::DanglingImage)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageItem {
+    pub kind: UsageKind,
+    /// "nginx:1.25", a container or volume name, or a cache id.
+    pub name: String,
+    /// Bytes removing the item would free: an image's unique size, a
+    /// container's writable layer.
+    pub size: u64,
+    /// e.g. "2 containers", "Exited (0) 2 hours ago", "1 link", "regular".
+    pub detail: String,
+    pub reclaim: Option<Reclaim>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReclaimAction {
+    pub reclaim: Reclaim,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub items: Vec<UsageItem>,
+    /// Image layers shared between images, counted once.
+    pub shared_image_size: u64,
+}
+
+impl DiskUsage {
+    /// Parses `docker system df -v` (or `podman system df -v`).
+    pub fn parse_verbose(text: &str) -> DiskUsage {
+        let mut usage = DiskUsage::default();
+        let mut section = None;
+        let mut columns: Option<Vec<(String, usize)>> = None;
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                // A blank line ends a table but not the section's heading
+                if columns.is_some() {
+                    section = None;
+                    columns = None;
+                }
+                continue;
+            }
+            let heading = trimmed.to_lowercase();
+            if heading.ends_with("usage:") || heading.starts_with("build cache usage") {
+                columns = None;
+            }
+            if heading.starts_with("images space usage") {
+                section = Some(UsageKind::Image);
+            } else if heading.starts_with("containers space usage") {
+                section = Some(UsageKind::Container);
+            } else if heading.starts_with("local volumes space usage") {
+                section = Some(UsageKind::Volume);
+            } else if heading.starts_with("build cache usage") {
+                section = Some(UsageKind::BuildCache);
+            } else if let Some(kind) = section {
+                match &columns {
+                    None => columns = Some(header_columns(line)),
+                    Some(columns) => {
+                        let row = Row { columns, cells: cut_row(line, columns) };
+                        usage.items.extend(row.item(kind, &mut usage.shared_image_size));
+                    }
+                }
+            }
+        }
+        usage
+    }
+
+    /// Maps the Engine API's `/system/df` response.
+    pub fn from_engine(df: &Value) -> DiskUsage {
+        let list = |key: &str| df[key].as_array().cloned().unwrap_or_default();
+        let number = |value: &Value| value.as_i64().filter(|n| *n >= 0).unwrap_or(0) as u64;
+        let mut items = Vec::new();
+
+        let mut unique_total = 0;
+        for image in list("Images") {
+            let size = number(&image["Size"]);
+            let unique = size.saturating_sub(number(&image["SharedSize"]));
+            unique_total += unique;
+            let tags: Vec<&str> = image["RepoTags"].as_array().into_iter().flatten().filter_map(Value::as_str).filter(|tag| *tag != "<none>:<none>").collect();
+            let id = short_id(image["Id"].as_str().unwrap_or(""));
+            let containers = image["Containers"].as_i64().unwrap_or(-1);
+            items.push(UsageItem {
+                kind: UsageKind::Image,
+                name: tags.first().map_or(id, |tag| tag.to_string()),
+                size: unique,
+                detail: count(containers.max(0) as u64, "container"),
+                reclaim: image_reclaim(tags.is_empty(), containers),
+            });
+        }
+        for container in list("Containers") {
+            let state = container["State"].as_str().unwrap_or("");
+            items.push(UsageItem {
+                kind: UsageKind::Container,
+                name: container["Names"][0].as_str().unwrap_or("").trim_start_matches('/').to_string(),
+                size: number(&container["SizeRw"]),
+                detail: container["Status"].as_str().unwrap_or(state).to_string(),
+                reclaim: Some(Reclaim::StoppedContainer).filter(|_| matches!(state, "exited" | "created" | "dead")),
+            });
+        }
+        for volume in list("Volumes") {
+            // -1 when the daemon didn't count
+            let links = volume["UsageData"]["RefCount"].as_i64().unwrap_or(-1);
+            items.push(UsageItem {
+                kind: UsageKind::Volume,
+                name: volume["Name"].as_str().unwrap_or("").to_string(),
+                size: number(&volume["UsageData"]["Size"]),
+                detail: count(links.max(0) as u64, "link"),
+                reclaim: Some(Reclaim::UnusedVolume).filter(|_| links == 0),
+            });
+        }
+        for cache in list("BuildCache") {
+            let busy = cache["InUse"].as_bool().unwrap_or(false) || cache["Shared"].as_bool().unwrap_or(false);
+            items.push(UsageItem {
+                kind: UsageKind::BuildCache,
+                name: short_id(cache["ID"].as_str().unwrap_or("")),
+                size: number(&cache["Size"]),
+                detail: cache["Type"].as_str().unwrap_or("").to_string(),
+                reclaim: Some(Reclaim::UnusedCache).filter(|_| !busy),
+            });
+        }
+        DiskUsage { items, shared_image_size: number(&df["LayersSize"]).saturating_sub(unique_total) }
+    }
+
+    pub fn items_of(&self, kind: UsageKind) -> impl Iterator<Item = &UsageItem> {
+        self.items.iter().filter(move |item| item.kind == kind)
+    }
+
+    /// Space used by one kind; shared image layers are counted once.
+    pub fn total(&self, kind: UsageKind) -> u64 {
+        let shared = if kind == UsageKind::Image { self.shared_image_size } else { 0 };
+        self.items_of(kind).map(|item| item.size).sum::<u64>() + shared
+    }
+
+    /// Space in one kind that some prune would free.
+    pub fn reclaimable(&self, kind: UsageKind) -> u64 {
+        self.items_of(kind).filter(|item| item.reclaim.is_some()).map(|item| item.size).sum()
+    }
+
+    /// What each prune would free, leaving out prunes with nothing to do.
+    pub fn reclaim_plan(&self) -> Vec<ReclaimAction> {
+        let actions = [Reclaim::StoppedContainer, Reclaim::DanglingImage, Reclaim::UnusedImage, Reclaim::UnusedVolume, Reclaim::UnusedCache];
+        actions
+            .iter()
+            .map(|action| {
+                let items: Vec<&UsageItem> = self.items.iter().filter(|item| item.reclaim.is_some_and(|reclaim| action.covers(reclaim))).collect();
+                ReclaimAction { reclaim: *action, count: items.len(), bytes: items.iter().map(|item| item.size).sum() }
+            })
+            .filter(|action| action.count > 0)
+            .collect()
+    }
+}
+
+/// How an image could be reclaimed, given its container count (-1 if unknown).
+pub fn image_reclaim(untagged: bool, containers: i64) -> Option<Reclaim> {
+    match (untagged, containers) {
+        (true, 0) => Some(Reclaim::DanglingImage),
+        (false, 0) => Some(Reclaim::UnusedImage),
+        _ => None,
+    }
+}
+
+fn short_id(id: &str) -> String {
+    id.trim_start_matches("sha256:").chars().take(12).collect()
+}
+
+fn count(n: u64, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
+// Column names and the character offsets they start at; columns are
+// separated by two or more spaces, names like "IMAGE ID" by one
+fn header_columns(header: &str) -> Vec<(String, usize)> {
+    let chars: Vec<char> = header.chars().collect();
+    let mut columns = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] == ' ' {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < chars.len() && !(chars[index] == ' ' && chars.get(index + 1).map_or(true, |c| *c == ' ')) {
+            index += 1;
+        }
+        columns.push((chars[start..index].iter().collect(), start));
+    }
+    columns
+}
+
+fn cut_row(line: &str, columns: &[(String, usize)]) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    columns
+        .iter()
+        .enumerate()
+        .map(|(index, (_, start))| {
+            let end = columns.get(index + 1).map_or(chars.len(), |(_, next)| *next).min(chars.len());
+            chars[(*start).min(end)..end].iter().collect::<String>().trim().to_string()
+        })
+        .collect()
+}
+
+struct Row<'a> {
+    columns: &'a [(String, usize)],
+    cells: Vec<String>,
+}
+
+impl Row<'_> {
+    fn get(&self, name: &str) -> &str {
+        self.columns.iter().position(|(column, _)| column == name).and_then(|index| self.cells.get(index)).map_or("", String::as_str)
+    }
+
+    fn size(&self, name: &str) -> u64 {
+        parse_size(self.get(name)).unwrap_or(0)
+    }
+
+    fn item(&self, kind: UsageKind, shared_image_size: &mut u64) -> Option<UsageItem> {
+        let item = match kind {
+            UsageKind::Image => {
+                let (repository, tag) = (self.get("REPOSITORY"), self.get("TAG"));
+                let untagged = repository == "<none>" && tag == "<none>";
+                let containers: i64 = self.get("CONTAINERS").parse().unwrap_or(-1);
+                // Every image sharing a layer counts it; the largest share is the least they all use
+                *shared_image_size = (*shared_image_size).max(self.size("SHARED SIZE"));
+                UsageItem {
+                    kind,
+                    name: if untagged { self.get("IMAGE ID").to_string() } else { format!("{}:{}", repository, tag) },
+                    size: self.size("UNIQUE SIZE"),
+                    detail: count(containers.max(0) as u64, "container"),
+                    reclaim: image_reclaim(untagged, containers),
+                }
+            }
+            UsageKind::Container => {
+                let status = self.get("STATUS");
+                let stopped = ["Exited", "Created", "Dead"].iter().any(|state| status.starts_with(state));
+                UsageItem {
+                    kind,
+                    name: self.get("NAMES").to_string(),
+                    size: self.size("SIZE"),
+                    detail: status.to_string(),
+                    reclaim: Some(Reclaim::StoppedContainer).filter(|_| stopped),
+                }
+            }
+            UsageKind::Volume => {
+                let links: u64 = self.get("LINKS").parse().unwrap_or(1);
+                UsageItem {
+                    kind,
+                    name: self.get("VOLUME NAME").to_string(),
+                    size: self.size("SIZE"),
+                    detail: count(links, "link"),
+                    reclaim: Some(Reclaim::UnusedVolume).filter(|_| links == 0),
+                }
+            }
+            UsageKind::BuildCache => UsageItem {
+                kind,
+                name: self.get("CACHE ID").to_string(),
+                size: self.size("SIZE"),
+                detail: self.get("CACHE TYPE").to_string(),
+                // The table doesn't say which records are in use; shared ones can't go
+                reclaim: Some(Reclaim::UnusedCache).filter(|_| self.get("SHARED") != "true"),
+            },
+        };
+        Some(item).filter(|item| !item.name.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERBOSE: &str = "\
+Images space usage:
+
+REPOSITORY   TAG       IMAGE ID       CREATED        SIZE      SHARED SIZE   UNIQUE SIZE   CONTAINERS
+nginx        1.25      a8758716bb6a   2 weeks ago    187MB     7.38MB        179.6MB       1
+<none>       <none>    5e1d2c3b4a59   3 weeks ago    96.5MB    7.38MB        89.1MB        0
+redis        7         7f4b2c1a9e8d   4 weeks ago    138MB     0B            138MB         0
+
+Containers space usage:
+
+CONTAINER ID   IMAGE          COMMAND                  LOCAL VOLUMES   SIZE      CREATED       STATUS                   NAMES
+3f4e5d6c7b8a   nginx:1.25     \"/docker-entrypoint.…\"   0               1.09kB    3 hours ago   Up 3 hours               web
+0f9e8d7c6b5a   myapp:latest   \"./worker\"               1               12.3MB    2 days ago    Exited (0) 2 hours ago   worker
+
+Local Volumes space usage:
+
+VOLUME NAME   LINKS     SIZE
+pgdata        1         48.2MB
+old_reports   0         1.5GB
+
+Build cache usage: 210MB
+
+CACHE ID       CACHE TYPE     SIZE      CREATED        LAST USED      USAGE     SHARED
+k3j2h1g0f9e8   regular        200MB     2 days ago     2 days ago     1         false
+a1b2c3d4e5f6   source.local   10MB      2 days ago     2 days ago     1         true
+";
+
+    #[test]
+    fn test_parse_verbose_marks_reclaimable() {
+        let usage = DiskUsage::parse_verbose(VERBOSE);
+        let reclaim: Vec<(&str, Option<Reclaim>)> = usage.items.iter().map(|item| (item.name.as_str(), item.reclaim)).collect();
+        assert_eq!(
+            reclaim,
+            vec![
+                ("nginx:1.25", None),
+                ("5e1d2c3b4a59", Some(Reclaim::DanglingImage)),
+                ("redis:7", Some(Reclaim::UnusedImage)),
+                ("web", None),
+                ("worker", Some(Reclaim::StoppedContainer)),
+                ("pgdata", None),
+                ("old_reports", Some(Reclaim::UnusedVolume)),
+                ("k3j2h1g0f9e8", Some(Reclaim::UnusedCache)),
+                ("a1b2c3d4e5f6", None),
+            ]
+        );
+        assert_eq!(usage.items[4].detail, "Exited (0) 2 hours ago");
+        assert_eq!(usage.total(UsageKind::Image), 179_600_000 + 89_100_000 + 138_000_000 + 7_380_000);
+        assert_eq!(usage.reclaimable(UsageKind::Volume), 1_500_000_000);
+
+        let plan: Vec<(&str, usize, u64)> = usage.reclaim_plan().iter().map(|action| (action.reclaim.command(), action.count, action.bytes)).collect();
+        assert_eq!(
+            plan,
+            vec![
+                ("docker container prune", 1, 12_300_000),
+                ("docker image prune", 1, 89_100_000),
+                ("docker image prune -a", 2, 227_100_000),
+                ("docker volume prune -a", 1, 1_500_000_000),
+                ("docker builder prune", 1, 200_000_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_engine() {
+        let df = serde_json::json!({
+            "LayersSize": 300,
+            "Images": [
+                {"Id": "sha256:a8758716bb6a0123", "RepoTags": ["nginx:1.25"], "Size": 187, "SharedSize": 7, "Containers": 1},
+                {"Id": "sha256:5e1d2c3b4a590123", "RepoTags": ["<none>:<none>"], "Size": 96, "SharedSize": 7, "Containers": 0},
+            ],
+            "Containers": [{"Id": "0f9e", "Names": ["/worker"], "SizeRw": 12, "State": "exited", "Status": "Exited (0) 2 hours ago"}],
+            "Volumes": [{"Name": "old_reports", "UsageData": {"Size": 1500, "RefCount": 0}}],
+            "BuildCache": [{"ID": "k3j2h1g0f9e8", "Type": "regular", "Size": 200, "InUse": true, "Shared": false}],
+        });
+        let usage = DiskUsage::from_engine(&df);
+        assert_eq!(usage.items[1].name, "5e1d2c3b4a59");
+        assert_eq!(usage.items[1].reclaim, Some(Reclaim::DanglingImage));
+        assert_eq!(usage.total(UsageKind::Image), 300);
+        assert_eq!(usage.items[2].reclaim, Some(Reclaim::StoppedContainer));
+        assert_eq!(usage.items[3].detail, "0 links");
+        assert_eq!(usage.items[4].reclaim, None);
+    }
+}
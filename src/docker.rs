@@ -1,11 +1,31 @@
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader, Write};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::io::Write;
+use std::ops::{ControlFlow, Deref};
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
-use crate::utils::{validate_container_name, validate_image_name, format_size};
-
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use crate::backend::{DockerBackend, StatsStream};
+use crate::cli::CliBackend;
+use crate::context::Target;
+use crate::engine::EngineClient;
+use crate::runtime::Runtime;
+use crate::error::DockerError;
+use crate::events::{DockerEvent, EventFilter};
+use crate::utils::{
+    binary_size, decimal_size, parse_duration_nanos, parse_memory, validate_container_name, validate_image_name, validate_port_mapping,
+    validate_restart_policy,
+};
+
+/// Entry point for all Docker operations. Daemon lifecycle handling lives
+/// here; the operations themselves are delegated to a `DockerBackend`.
 #[derive(Clone)]
-pub struct DockerClient;
+pub struct DockerClient {
+    backend: Arc<dyn DockerBackend>,
+    target: Target,
+    runtime: Runtime,
+}
 
 #[derive(Debug, Clone)]
 pub struct Container {
@@ -14,6 +34,566 @@ pub struct Container {
     pub image: String,
     pub status: String,
     pub ports: String,
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Everything `dui containers create` can set on a new container.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainerSpec {
+    pub name: String,
+    pub image: String,
+    /// `[ip:]host:container[/proto]` or `container[/proto]`.
+    pub ports: Vec<String>,
+    /// `source:target[:options]`, where source is a host path or a volume name,
+    /// or just a target path for an anonymous volume.
+    pub volumes: Vec<String>,
+    /// `KEY=VALUE`, or `KEY` to pass the variable through from this shell.
+    pub env: Vec<String>,
+    pub env_files: Vec<String>,
+    /// `key=value`.
+    pub labels: Vec<String>,
+    /// The first network is used at creation, the rest are connected afterwards.
+    pub networks: Vec<String>,
+    /// PID namespace, e.g. "host" or "container:<name>".
+    pub pid: Option<String>,
+    /// no, always, unless-stopped or on-failure[:max-retries].
+    pub restart: Option<String>,
+    /// Memory limit such as 512m or 2g.
+    pub memory: Option<String>,
+    /// Number of CPUs such as 1.5.
+    pub cpus: Option<String>,
+    pub healthcheck: Option<Healthcheck>,
+    pub user: Option<String>,
+    pub workdir: Option<String>,
+    pub entrypoint: Option<String>,
+    /// Replaces the image's CMD when not empty.
+    pub command: Vec<String>,
+    /// Remove the container once it exits.
+    pub auto_remove: bool,
+}
+
+/// Settings shared by captured and interactive `exec`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecOptions {
+    /// `user[:group]`, by name or ID.
+    pub user: Option<String>,
+    pub workdir: Option<String>,
+    /// `KEY=VALUE`.
+    pub env: Vec<String>,
+    /// Key sequence that leaves an interactive session running, e.g. "ctrl-p,ctrl-q".
+    pub detach_keys: Option<String>,
+}
+
+impl ExecOptions {
+    /// Arguments for `docker exec`, placed before the container name.
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let options = [("--user", &self.user), ("--workdir", &self.workdir), ("--detach-keys", &self.detach_keys)];
+        for (flag, value) in options {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value.clone());
+            }
+        }
+        for env in &self.env {
+            args.push("--env".to_string());
+            args.push(env.clone());
+        }
+        args
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Healthcheck {
+    /// Run through the container's shell, as with `--health-cmd`.
+    pub command: String,
+    /// Durations such as 30s or 1m.
+    pub interval: Option<String>,
+    pub timeout: Option<String>,
+    pub retries: Option<u32>,
+}
+
+impl ContainerSpec {
+    pub fn new(name: &str, image: &str) -> Self {
+        ContainerSpec { name: name.to_string(), image: image.to_string(), ..Default::default() }
+    }
+
+    /// Rebuilds the spec of an existing container from the inspect JSON of it
+    /// and of the image it runs. Only what the container sets on top of the
+    /// image is kept, so a newer image's own env, command and labels apply.
+    /// Settings a spec can't express, such as capabilities, are dropped; see
+    /// `unsupported_settings`.
+    pub fn from_inspect(container: &Value, image: &Value) -> ContainerSpec {
+        let config = &container["Config"];
+        let host = &container["HostConfig"];
+        let base = &image["Config"];
+        let strings = |value: &Value| -> Vec<String> {
+            value.as_array().into_iter().flatten().filter_map(|item| item.as_str()).map(str::to_string).collect()
+        };
+        let added = |field: &str| config[field].as_str().filter(|value| !value.is_empty() && base[field] != config[field]).map(str::to_string);
+
+        let mut ports = Vec::new();
+        for (port, bindings) in host["PortBindings"].as_object().into_iter().flatten() {
+            let port = port.strip_suffix("/tcp").unwrap_or(port);
+            for binding in bindings.as_array().into_iter().flatten() {
+                let host_port = binding["HostPort"].as_str().unwrap_or("");
+                ports.push(match binding["HostIp"].as_str().unwrap_or("") {
+                    _ if host_port.is_empty() => port.to_string(),
+                    "" => format!("{}:{}", host_port, port),
+                    ip if ip.contains(':') => format!("[{}]:{}:{}", ip, host_port, port),
+                    ip => format!("{}:{}:{}", ip, host_port, port),
+                });
+            }
+        }
+
+        // Volumes go by name, anonymous ones too, so their data carries over
+        let mut volumes = Vec::new();
+        for mount in container["Mounts"].as_array().into_iter().flatten() {
+            let source = match mount["Type"].as_str() {
+                Some("bind") => mount["Source"].as_str(),
+                Some("volume") => mount["Name"].as_str(),
+                _ => None,
+            };
+            if let (Some(source), Some(target)) = (source, mount["Destination"].as_str()) {
+                let read_only = if mount["RW"].as_bool() == Some(false) { ":ro" } else { "" };
+                volumes.push(format!("{}:{}{}", source, target, read_only));
+            }
+        }
+
+        let base_env = strings(&base["Env"]);
+        let env = strings(&config["Env"]).into_iter().filter(|pair| !base_env.contains(pair)).collect();
+        let labels = config["Labels"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(key, value)| base["Labels"].get(key.as_str()) != Some(value))
+            .map(|(key, value)| format!("{}={}", key, value.as_str().unwrap_or("")))
+            .collect();
+
+        let mode = match host["NetworkMode"].as_str().unwrap_or("default") {
+            "default" | "" => "bridge",
+            mode => mode,
+        };
+        let mut networks = vec![mode.to_string()];
+        networks.extend(
+            container["NetworkSettings"]["Networks"]
+                .as_object()
+                .into_iter()
+                .flat_map(|attached| attached.keys())
+                .filter(|name| *name != mode)
+                .cloned(),
+        );
+        if networks == ["bridge"] {
+            networks.clear();
+        }
+
+        let restart = match (host["RestartPolicy"]["Name"].as_str().unwrap_or(""), host["RestartPolicy"]["MaximumRetryCount"].as_u64()) {
+            ("" | "no", _) => None,
+            ("on-failure", Some(retries)) if retries > 0 => Some(format!("on-failure:{}", retries)),
+            (policy, _) => Some(policy.to_string()),
+        };
+
+        // Healthchecks run through the shell, as `--health-cmd` does
+        let healthcheck = match strings(&config["Healthcheck"]["Test"]).split_first() {
+            Some((kind, test)) if (kind == "CMD" || kind == "CMD-SHELL") && config["Healthcheck"] != base["Healthcheck"] => {
+                let duration = |field: &str| {
+                    config["Healthcheck"][field].as_u64().filter(|nanos| *nanos > 0).map(|nanos| match nanos % 1_000_000_000 {
+                        0 => format!("{}s", nanos / 1_000_000_000),
+                        _ => format!("{}ms", nanos / 1_000_000),
+                    })
+                };
+                Some(Healthcheck {
+                    command: test.join(" "),
+                    interval: duration("Interval"),
+                    timeout: duration("Timeout"),
+                    retries: config["Healthcheck"]["Retries"].as_u64().filter(|retries| *retries > 0).map(|retries| retries as u32),
+                })
+            }
+            _ => None,
+        };
+
+        // Overriding the entrypoint clears the image's CMD, so Cmd is all the container's own
+        let (entrypoint, command) = if config["Entrypoint"] != base["Entrypoint"] {
+            let mut words = strings(&config["Entrypoint"]);
+            let entrypoint = if words.is_empty() { String::new() } else { words.remove(0) };
+            words.extend(strings(&config["Cmd"]));
+            (Some(entrypoint), words)
+        } else if config["Cmd"] != base["Cmd"] {
+            (None, strings(&config["Cmd"]))
+        } else {
+            (None, Vec::new())
+        };
+
+        ContainerSpec {
+            name: container["Name"].as_str().unwrap_or("").trim_start_matches('/').to_string(),
+            image: config["Image"].as_str().unwrap_or("").to_string(),
+            ports,
+            volumes,
+            env,
+            env_files: Vec::new(),
+            labels,
+            networks,
+            pid: host["PidMode"].as_str().filter(|mode| !mode.is_empty()).map(str::to_string),
+            restart,
+            memory: host["Memory"].as_u64().filter(|bytes| *bytes > 0).map(|bytes| format!("{}b", bytes)),
+            cpus: host["NanoCpus"].as_u64().filter(|nanos| *nanos > 0).map(|nanos| (nanos as f64 / 1e9).to_string()),
+            healthcheck,
+            user: added("User"),
+            workdir: added("WorkingDir"),
+            entrypoint,
+            command,
+            auto_remove: host["AutoRemove"].as_bool().unwrap_or(false),
+        }
+    }
+
+    /// What an inspected container sets that `from_inspect` can't carry over,
+    /// by `docker run` flag, so a recreated container would lose it.
+    pub fn unsupported_settings(container: &Value) -> Vec<String> {
+        let host = &container["HostConfig"];
+        let set = |value: &Value| match value {
+            Value::Array(items) => !items.is_empty(),
+            Value::Object(entries) => !entries.is_empty(),
+            Value::String(text) => !text.is_empty(),
+            _ => false,
+        };
+        let mut lost = Vec::new();
+        if host["Privileged"].as_bool() == Some(true) {
+            lost.push("--privileged");
+        }
+        for (field, flag) in [
+            ("CapAdd", "--cap-add"),
+            ("CapDrop", "--cap-drop"),
+            ("Devices", "--device"),
+            ("ExtraHosts", "--add-host"),
+            ("Dns", "--dns"),
+            ("DnsSearch", "--dns-search"),
+            ("DnsOptions", "--dns-option"),
+            ("Ulimits", "--ulimit"),
+            ("SecurityOpt", "--security-opt"),
+        ] {
+            if set(&host[field]) {
+                lost.push(flag);
+            }
+        }
+        let tmpfs_mount = container["Mounts"].as_array().into_iter().flatten().any(|mount| mount["Type"] == "tmpfs");
+        if set(&host["Tmpfs"]) || tmpfs_mount {
+            lost.push("--tmpfs");
+        }
+        // 64MB is what the daemon gives every container
+        if host["ShmSize"].as_u64().is_some_and(|size| size != 0 && size != 64 * 1024 * 1024) {
+            lost.push("--shm-size");
+        }
+        if !matches!(host["LogConfig"]["Type"].as_str(), None | Some("") | Some("json-file")) {
+            lost.push("--log-driver");
+        }
+        if set(&host["LogConfig"]["Config"]) {
+            lost.push("--log-opt");
+        }
+
+        // The daemon aliases every container by its short ID and name itself
+        let id = container["Id"].as_str().unwrap_or("");
+        let name = container["Name"].as_str().unwrap_or("").trim_start_matches('/');
+        let networks: Vec<&Value> = container["NetworkSettings"]["Networks"].as_object().into_iter().flat_map(|n| n.values()).collect();
+        if networks.iter().any(|network| {
+            network["Aliases"].as_array().into_iter().flatten().filter_map(Value::as_str).any(|alias| alias != name && !id.starts_with(alias))
+        }) {
+            lost.push("--network-alias");
+        }
+        if networks.iter().any(|network| set(&network["IPAMConfig"]["IPv4Address"]) || set(&network["IPAMConfig"]["IPv6Address"])) {
+            lost.push("--ip");
+        }
+        lost.into_iter().map(str::to_string).collect()
+    }
+
+    /// Rejects malformed values before anything is sent to the daemon.
+    pub fn validate(&self) -> Result<(), DockerError> {
+        validate_container_name(&self.name)?;
+        validate_image_name(&self.image)?;
+        for port in &self.ports {
+            validate_port_mapping(port)?;
+        }
+        for volume in &self.volumes {
+            // A lone target path is an anonymous volume
+            let valid = match volume.split_once(':') {
+                Some((source, rest)) => !source.is_empty() && !rest.split(':').next().unwrap_or("").is_empty(),
+                None => volume.starts_with('/'),
+            };
+            if !valid {
+                return Err(format!("Invalid volume '{}': expected source:target[:options]", volume).into());
+            }
+        }
+        for (kind, pairs) in [("environment variable", &self.env), ("label", &self.labels)] {
+            if let Some(pair) = pairs.iter().find(|pair| pair.is_empty() || pair.starts_with('=')) {
+                return Err(format!("Invalid {} '{}': expected KEY=VALUE", kind, pair).into());
+            }
+        }
+        if let Some(restart) = &self.restart {
+            validate_restart_policy(restart)?;
+        }
+        Ok(())
+    }
+
+    /// Arguments for `docker run`/`docker create`, without the subcommand.
+    /// Networks after the first are left for `docker network connect`.
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = vec!["--name".to_string(), self.name.clone()];
+        let mut push = |flag: &str, value: &str| {
+            args.push(flag.to_string());
+            args.push(value.to_string());
+        };
+        for port in &self.ports {
+            push("--publish", port);
+        }
+        for volume in &self.volumes {
+            push("--volume", volume);
+        }
+        for env in &self.env {
+            push("--env", env);
+        }
+        for env_file in &self.env_files {
+            push("--env-file", env_file);
+        }
+        for label in &self.labels {
+            push("--label", label);
+        }
+        if let Some(network) = self.networks.first() {
+            push("--network", network);
+        }
+        let options = [
+            ("--pid", &self.pid),
+            ("--restart", &self.restart),
+            ("--memory", &self.memory),
+            ("--cpus", &self.cpus),
+            ("--user", &self.user),
+            ("--workdir", &self.workdir),
+            ("--entrypoint", &self.entrypoint),
+        ];
+        for (flag, value) in options {
+            if let Some(value) = value {
+                push(flag, value);
+            }
+        }
+        if let Some(health) = &self.healthcheck {
+            push("--health-cmd", &health.command);
+            if let Some(interval) = &health.interval {
+                push("--health-interval", interval);
+            }
+            if let Some(timeout) = &health.timeout {
+                push("--health-timeout", timeout);
+            }
+            if let Some(retries) = health.retries {
+                push("--health-retries", &retries.to_string());
+            }
+        }
+        if self.auto_remove {
+            args.push("--rm".to_string());
+        }
+        args.push(self.image.clone());
+        args.extend(self.command.iter().cloned());
+        args
+    }
+
+    /// The body for the Engine API's `POST /containers/create`, doing what
+    /// the docker CLI does for `run`: env files are read and bare `KEY`s
+    /// passed through from this shell. Networks after the first are left to
+    /// be connected afterwards, as with `cli_args`.
+    pub fn api_config(&self) -> Result<Value, DockerError> {
+        let pass_through = |pair: &str| match pair.contains('=') {
+            true => Some(pair.to_string()),
+            false => std::env::var(pair).ok().map(|value| format!("{}={}", pair, value)),
+        };
+        let mut env = Vec::new();
+        for file in &self.env_files {
+            let text = std::fs::read_to_string(file).map_err(|e| format!("Failed to read env file {}: {}", file, e))?;
+            env.extend(text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).filter_map(pass_through));
+        }
+        env.extend(self.env.iter().filter_map(|pair| pass_through(pair)));
+
+        let labels: BTreeMap<&str, &str> = self.labels.iter().map(|label| label.split_once('=').unwrap_or((label, ""))).collect();
+
+        let mut exposed = serde_json::Map::new();
+        let mut bindings = serde_json::Map::new();
+        for port in &self.ports {
+            for (container_port, host_ip, host_port) in port_bindings(port) {
+                exposed.insert(container_port.clone(), json!({}));
+                if !host_ip.is_empty() || !host_port.is_empty() {
+                    let entry = bindings.entry(container_port).or_insert_with(|| json!([]));
+                    if let Some(entries) = entry.as_array_mut() {
+                        entries.push(json!({"HostIp": host_ip, "HostPort": host_port}));
+                    }
+                }
+            }
+        }
+
+        // Binds take host paths and volume names alike; a lone target is an anonymous volume
+        let mut binds = Vec::new();
+        let mut anonymous = serde_json::Map::new();
+        for volume in &self.volumes {
+            match volume.split_once(':') {
+                Some((source, rest)) if source.starts_with('.') => {
+                    let source = std::env::current_dir().map(|dir| dir.join(source)).unwrap_or_else(|_| source.into());
+                    binds.push(format!("{}:{}", source.display(), rest));
+                }
+                Some(_) => binds.push(volume.clone()),
+                None => {
+                    anonymous.insert(volume.clone(), json!({}));
+                }
+            }
+        }
+
+        let mut host = json!({"PortBindings": bindings, "Binds": binds, "AutoRemove": self.auto_remove});
+        if let Some(network) = self.networks.first() {
+            host["NetworkMode"] = json!(network);
+        }
+        if let Some(pid) = &self.pid {
+            host["PidMode"] = json!(pid);
+        }
+        if let Some(policy) = &self.restart {
+            let (name, retries) = policy.split_once(':').unwrap_or((policy, "0"));
+            host["RestartPolicy"] = json!({"Name": name, "MaximumRetryCount": retries.parse::<u64>().unwrap_or(0)});
+        }
+        if let Some(memory) = &self.memory {
+            host["Memory"] = json!(parse_memory(memory).ok_or_else(|| format!("Invalid memory limit '{}': use e.g. 512m or 2g", memory))?);
+        }
+        if let Some(cpus) = &self.cpus {
+            let cpus: f64 = cpus.parse().map_err(|_| format!("Invalid --cpus '{}': use a number such as 1.5", cpus))?;
+            host["NanoCpus"] = json!((cpus * 1e9).round() as u64);
+        }
+
+        let mut config = json!({
+            "Image": self.image,
+            "Env": env,
+            "Labels": labels,
+            "ExposedPorts": exposed,
+            "Volumes": anonymous,
+            "HostConfig": host,
+        });
+        if let Some(user) = &self.user {
+            config["User"] = json!(user);
+        }
+        if let Some(workdir) = &self.workdir {
+            config["WorkingDir"] = json!(workdir);
+        }
+        // Like `--entrypoint`, this also drops the image's CMD
+        if let Some(entrypoint) = &self.entrypoint {
+            config["Entrypoint"] = json!([entrypoint]);
+        }
+        if !self.command.is_empty() {
+            config["Cmd"] = json!(self.command);
+        }
+        if let Some(health) = &self.healthcheck {
+            let duration = |value: &Option<String>| -> Result<u64, DockerError> {
+                match value {
+                    Some(text) => parse_duration_nanos(text).ok_or_else(|| format!("Invalid healthcheck duration '{}': use e.g. 30s or 1m", text).into()),
+                    None => Ok(0),
+                }
+            };
+            config["Healthcheck"] = json!({
+                "Test": ["CMD-SHELL", health.command],
+                "Interval": duration(&health.interval)?,
+                "Timeout": duration(&health.timeout)?,
+                "Retries": health.retries.unwrap_or(0),
+            });
+        }
+        Ok(config)
+    }
+}
+
+// A `--publish` mapping as (container port/proto, host IP, host port) for
+// each port in it, ranges paired up port by port
+fn port_bindings(mapping: &str) -> Vec<(String, String, String)> {
+    let (ports, protocol) = mapping.split_once('/').unwrap_or((mapping, "tcp"));
+    let (ip, ports) = match ports.rfind(']') {
+        Some(end) if ports.starts_with('[') => (&ports[1..end], ports[end + 1..].trim_start_matches(':')),
+        _ if ports.matches(':').count() == 2 => ports.split_once(':').unwrap_or(("", ports)),
+        _ => ("", ports),
+    };
+    let (host, container) = ports.rsplit_once(':').unwrap_or(("", ports));
+    let range = |text: &str| -> Vec<u16> {
+        match text.split_once('-') {
+            Some((first, last)) => (first.parse().unwrap_or(1)..=last.parse().unwrap_or(0)).collect(),
+            None => text.parse().into_iter().collect(),
+        }
+    };
+    let containers = range(container);
+    let hosts = range(host);
+    containers
+        .iter()
+        .enumerate()
+        .map(|(i, port)| {
+            // A host range for a single port lets the daemon pick one from it
+            let host_port = if hosts.len() == containers.len() { hosts[i].to_string() } else { host.to_string() };
+            (format!("{}/{}", port, protocol), ip.to_string(), host_port)
+        })
+        .collect()
+}
+
+/// Everything `dui images build` can pass to the builder.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BuildOptions {
+    /// Build context directory.
+    pub context: String,
+    /// Dockerfile path, when not `Dockerfile` in the context.
+    pub dockerfile: Option<String>,
+    /// Every tag is applied to the built image.
+    pub tags: Vec<String>,
+    /// `KEY=VALUE`, or `KEY` to pass the variable through from this shell.
+    pub build_args: Vec<String>,
+    /// Stage of a multi-stage Dockerfile to stop at.
+    pub target: Option<String>,
+    pub no_cache: bool,
+    /// `key=value`.
+    pub labels: Vec<String>,
+    /// e.g. linux/amd64 or linux/arm64.
+    pub platform: Option<String>,
+}
+
+impl BuildOptions {
+    pub fn new(context: &str, tags: Vec<String>) -> Self {
+        BuildOptions { context: context.to_string(), tags, ..Default::default() }
+    }
+
+    /// Rejects malformed values before the builder starts.
+    pub fn validate(&self) -> Result<(), DockerError> {
+        for tag in &self.tags {
+            validate_image_name(tag)?;
+        }
+        for (kind, pairs) in [("build argument", &self.build_args), ("label", &self.labels)] {
+            if let Some(pair) = pairs.iter().find(|pair| pair.is_empty() || pair.starts_with('=')) {
+                return Err(format!("Invalid {} '{}': expected KEY=VALUE", kind, pair).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Arguments for `docker build`, without the subcommand.
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut push = |flag: &str, value: &str| {
+            args.push(flag.to_string());
+            args.push(value.to_string());
+        };
+        for tag in &self.tags {
+            push("--tag", tag);
+        }
+        for build_arg in &self.build_args {
+            push("--build-arg", build_arg);
+        }
+        for label in &self.labels {
+            push("--label", label);
+        }
+        let options = [("--file", &self.dockerfile), ("--target", &self.target), ("--platform", &self.platform)];
+        for (flag, value) in options {
+            if let Some(value) = value {
+                push(flag, value);
+            }
+        }
+        if self.no_cache {
+            args.push("--no-cache".to_string());
+        }
+        args.push(self.context.clone());
+        args
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -25,14 +605,60 @@ pub struct Image {
     pub created: String,
 }
 
-#[derive(Debug, Clone)]
+/// One resource usage sample. Byte counters are cumulative since the container started.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct ContainerStats {
     pub name: String,
-    pub cpu_percent: String,
-    pub memory_usage: String,
-    pub memory_percent: String,
-    pub network_io: String,
-    pub block_io: String,
+    /// CPU time used per unit of wall time: 1.0 is one core fully busy,
+    /// so a busy multi-threaded container can exceed 1.0.
+    pub cpu_fraction: f64,
+    /// Cores available to the container; 0 when the backend couldn't tell.
+    pub online_cpus: u32,
+    pub memory_used: u64,
+    pub memory_limit: u64,
+    pub net_rx: u64,
+    pub net_tx: u64,
+    pub block_read: u64,
+    pub block_write: u64,
+}
+
+impl ContainerStats {
+    /// CPU usage as `docker stats` reports it, where 100% is one core.
+    pub fn cpu_percent(&self) -> f64 {
+        self.cpu_fraction * 100.0
+    }
+
+    /// Share of all available cores in use, from 0.0 to 1.0.
+    pub fn cpu_share(&self) -> f64 {
+        (self.cpu_fraction / self.online_cpus.max(1) as f64).clamp(0.0, 1.0)
+    }
+
+    pub fn memory_percent(&self) -> f64 {
+        if self.memory_limit == 0 {
+            return 0.0;
+        }
+        self.memory_used as f64 / self.memory_limit as f64 * 100.0
+    }
+
+    pub fn cpu_text(&self) -> String {
+        format!("{:.2}%", self.cpu_percent())
+    }
+
+    pub fn memory_text(&self) -> String {
+        format!("{:.2}%", self.memory_percent())
+    }
+
+    pub fn memory_usage(&self) -> String {
+        format!("{} / {}", binary_size(self.memory_used as f64), binary_size(self.memory_limit as f64))
+    }
+
+    pub fn network_io(&self) -> String {
+        format!("{} / {}", decimal_size(self.net_rx as f64), decimal_size(self.net_tx as f64))
+    }
+
+    pub fn block_io(&self) -> String {
+        format!("{} / {}", decimal_size(self.block_read as f64), decimal_size(self.block_write as f64))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -67,38 +693,50 @@ pub struct ContainerProcess {
 }
 
 impl DockerClient {
-    pub fn new() -> Self {
-        DockerClient
+    pub fn new(target: Target) -> Self {
+        // Talk to the daemon socket directly when one is reachable; otherwise
+        // shell out to the docker CLI.
+        let runtime = Runtime::detect();
+        let backend: Arc<dyn DockerBackend> = match EngineClient::for_target(&target, runtime) {
+            Some(engine) => Arc::new(engine),
+            None => Arc::new(CliBackend::new(runtime, &target.cli_args)),
+        };
+        DockerClient { backend, target, runtime }
+    }
+
+    pub fn with_backend(backend: Arc<dyn DockerBackend>) -> Self {
+        DockerClient { backend, target: Target::default(), runtime: Runtime::Docker }
+    }
+
+    /// The context name (or --host value) this client is pointed at.
+    pub fn context(&self) -> &str {
+        &self.target.label
     }
 
     pub fn is_docker_available(&self) -> bool {
-        Command::new("docker")
-            .arg("--version")
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+        self.backend.is_available()
     }
 
     pub fn is_docker_daemon_running(&self) -> bool {
-        Command::new("docker")
-            .args(["info"])
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+        self.backend.ping()
     }
 
-    pub fn start_docker_daemon(&self) -> Result<(), String> {
+    pub fn start_docker_daemon(&self) -> Result<(), DockerError> {
         let os = std::env::consts::OS;
+
+        if self.runtime == Runtime::Podman {
+            return self.start_podman_service(os);
+        }
         
         match os {
             "macos" => self.start_docker_daemon_macos(),
             "linux" => self.start_docker_daemon_linux(),
             "windows" => self.start_docker_daemon_windows(),
-            _ => Err(format!("Unsupported operating system: {}", os)),
+            _ => Err(DockerError::DaemonUnreachable(format!("Unsupported operating system: {}", os))),
         }
     }
 
-    fn start_docker_daemon_macos(&self) -> Result<(), String> {
+    fn start_docker_daemon_macos(&self) -> Result<(), DockerError> {
         // Try to start Docker Desktop on macOS
         let docker_desktop_paths = [
             "/Applications/Docker.app",
@@ -115,7 +753,7 @@ impl DockerClient {
                     .arg("-a")
                     .arg(path)
                     .output()
-                    .map_err(|e| format!("Failed to start Docker Desktop: {}", e))?;
+                    .map_err(|e| DockerError::DaemonUnreachable(format!("Failed to start Docker Desktop: {}", e)))?;
                 
                 // Wait for Docker daemon to start
                 return self.wait_for_docker_daemon();
@@ -151,12 +789,12 @@ impl DockerClient {
                     }
                 }
                 
-                Err("Docker Desktop not found. Please install Docker Desktop or start Docker daemon manually.\nVisit: https://docs.docker.com/desktop/install/mac/".to_string())
+                Err(DockerError::DaemonUnreachable("Docker Desktop not found. Please install Docker Desktop or start Docker daemon manually.\nVisit: https://docs.docker.com/desktop/install/mac/".to_string()))
             },
         }
     }
 
-    fn start_docker_daemon_linux(&self) -> Result<(), String> {
+    fn start_docker_daemon_linux(&self) -> Result<(), DockerError> {
         // Try systemctl first (most common on modern Linux distros)
         let systemctl_result = Command::new("sudo")
             .args(["systemctl", "start", "docker"])
@@ -186,11 +824,11 @@ impl DockerClient {
 
         match daemon_result {
             Ok(output) if output.status.success() => self.wait_for_docker_daemon(),
-            _ => Err("Failed to start Docker daemon. Please start Docker manually or check your Docker installation.".to_string()),
+            _ => Err(DockerError::DaemonUnreachable("Failed to start Docker daemon. Please start Docker manually or check your Docker installation.".to_string())),
         }
     }
 
-    fn start_docker_daemon_windows(&self) -> Result<(), String> {
+    fn start_docker_daemon_windows(&self) -> Result<(), DockerError> {
         // Try to start Docker Desktop on Windows
         let docker_desktop_paths = [
             "C:\\Program Files\\Docker\\Docker\\Docker Desktop.exe",
@@ -202,7 +840,7 @@ impl DockerClient {
                 Command::new("cmd")
                     .args(["/C", "start", "", path])
                     .output()
-                    .map_err(|e| format!("Failed to start Docker Desktop: {}", e))?;
+                    .map_err(|e| DockerError::DaemonUnreachable(format!("Failed to start Docker Desktop: {}", e)))?;
                 
                 return self.wait_for_docker_daemon();
             }
@@ -215,11 +853,30 @@ impl DockerClient {
 
         match ps_result {
             Ok(output) if output.status.success() => self.wait_for_docker_daemon(),
-            _ => Err("Docker Desktop not found. Please install Docker Desktop or start Docker service manually.".to_string()),
+            _ => Err(DockerError::DaemonUnreachable("Docker Desktop not found. Please install Docker Desktop or start Docker service manually.".to_string())),
         }
     }
 
-    fn wait_for_docker_daemon(&self) -> Result<(), String> {
+    fn start_podman_service(&self, os: &str) -> Result<(), DockerError> {
+        println!("🦭 Podman API is not reachable. Attempting to start it...");
+
+        // Linux runs the API as a socket-activated user service; elsewhere it lives in a VM
+        let (program, args): (&str, &[&str]) = match os {
+            "linux" => ("systemctl", &["--user", "start", "podman.socket"]),
+            _ => ("podman", &["machine", "start"]),
+        };
+
+        match Command::new(program).args(args).output() {
+            Ok(output) if output.status.success() => self.wait_for_docker_daemon(),
+            _ => Err(DockerError::DaemonUnreachable(format!(
+                "Failed to start Podman. Run '{} {}' manually or check your Podman installation.",
+                program,
+                args.join(" ")
+            ))),
+        }
+    }
+
+    fn wait_for_docker_daemon(&self) -> Result<(), DockerError> {
         let max_attempts = 30;
         let delay = Duration::from_secs(2);
         
@@ -242,13 +899,16 @@ impl DockerClient {
         
         println!("\n❌ Docker daemon failed to start within expected time (60 seconds).");
         println!("   Please try starting Docker Desktop manually or check your Docker installation.");
-        Err("Docker daemon failed to start within expected time (60 seconds). Please check Docker installation.".to_string())
+        Err(DockerError::DaemonUnreachable("Docker daemon failed to start within expected time (60 seconds). Please check Docker installation.".to_string()))
     }
 
-    pub fn ensure_docker_is_running(&self) -> Result<(), String> {
+    pub fn ensure_docker_is_running(&self) -> Result<(), DockerError> {
         // First check if docker command is available
         if !self.is_docker_available() {
-            return Err("Docker is not installed. Please install Docker first.\nVisit: https://docs.docker.com/desktop/install/mac/".to_string());
+            return Err(match self.runtime {
+                Runtime::Podman => "Podman is not installed. Please install Podman first.\nVisit: https://podman.io/docs/installation".into(),
+                Runtime::Docker => "Docker is not installed. Please install Docker first.\nVisit: https://docs.docker.com/desktop/install/mac/".into(),
+            });
         }
 
         // Check if daemon is running
@@ -257,13 +917,15 @@ impl DockerClient {
         }
 
         // Print debug information
-        println!("🔍 Docker diagnosis:");
-        println!("   - Docker CLI: ✅ Available");
-        println!("   - Docker Daemon: ❌ Not running");
+        let runtime = self.runtime.name();
+        println!("🔍 {} diagnosis:", runtime);
+        println!("   - {} CLI: ✅ Available", runtime);
+        println!("   - {} Daemon: ❌ Not running", runtime);
         
         // Get more detailed error information
-        let daemon_check = Command::new("docker")
-            .args(["info"])
+        let daemon_check = Command::new(self.runtime.program())
+            .args(&self.target.cli_args)
+            .arg("info")
             .output();
             
         if let Ok(output) = daemon_check {
@@ -278,6 +940,14 @@ impl DockerClient {
             }
         }
 
+        // A remote daemon can't be started from here
+        if !self.target.is_local() {
+            return Err(DockerError::DaemonUnreachable(format!(
+                "Cannot connect to the Docker daemon for context '{}'. Is the docker daemon running?",
+                self.target.label
+            )));
+        }
+
         // Try to start the daemon
         match self.start_docker_daemon() {
             Ok(()) => Ok(()),
@@ -292,840 +962,337 @@ impl DockerClient {
         }
     }
 
-    // ===== CONTAINER COMMANDS =====
-
-    pub fn create_container(&self, name: &str, image: &str, ports: Option<&str>, volumes: Option<&str>, env: Option<&str>) -> Result<(), String> {
-        // Validate container name
-        validate_container_name(name)?;
-        
-        // Validate image name
-        validate_image_name(image)?;
-
-        let mut args = vec!["run", "-d"];
-        
-        // Add name
-        args.extend_from_slice(&["--name", name]);
-        
-        // Add port mapping if provided
-        if let Some(port_mapping) = ports {
-            args.extend_from_slice(&["-p", port_mapping]);
-        }
-        
-        // Add volume mapping if provided
-        if let Some(volume_mapping) = volumes {
-            args.extend_from_slice(&["-v", volume_mapping]);
-        }
-        
-        // Add environment variables if provided
-        if let Some(env_vars) = env {
-            args.extend_from_slice(&["-e", env_vars]);
-        }
-        
-        // Add image
-        args.push(image);
-
-        let output = Command::new("docker")
-            .args(&args)
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn attach_container(&self, name: &str) -> Result<(), String> {
-        let mut child = Command::new("docker")
-            .args(["attach", name])
-            .spawn()
-            .map_err(|e| format!("Failed to attach to container: {}", e))?;
-
-        child.wait()
-            .map_err(|e| format!("Failed to wait for attach process: {}", e))?;
-
-        Ok(())
-    }
-
-    pub fn commit_container(&self, container: &str, repository: &str, tag: Option<&str>) -> Result<(), String> {
-        let mut args = vec!["commit"];
-        
-        if let Some(tag_value) = tag {
-            args.extend_from_slice(&[repository, tag_value]);
-        } else {
-            args.push(repository);
-        }
-        
-        args.push(container);
-
-        let output = Command::new("docker")
-            .args(&args)
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn copy_from_container(&self, container: &str, src_path: &str, dest_path: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["cp", &format!("{}:{}", container, src_path), dest_path])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    #[allow(dead_code)]
-    pub fn copy_to_container(&self, src_path: &str, container: &str, dest_path: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["cp", src_path, &format!("{}:{}", container, dest_path)])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn diff_container(&self, container: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["diff", container])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    }
-
-    pub fn export_container(&self, container: &str, output_file: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["export", "-o", output_file, container])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn get_container_history(&self, image: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["history", image])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    }
-
-    pub fn import_image(&self, file: &str, repository: &str, tag: Option<&str>) -> Result<(), String> {
-        let mut args = vec!["import"];
-        
-        if let Some(tag_value) = tag {
-            args.extend_from_slice(&[repository, tag_value]);
-        } else {
-            args.push(repository);
-        }
-        
-        args.push(file);
-
-        let output = Command::new("docker")
-            .args(&args)
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+    // ===== COMMANDS THAT NEED A RUNNING DAEMON =====
 
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn kill_container(&self, container: &str, signal: Option<&str>) -> Result<(), String> {
-        let mut args = vec!["kill"];
-        
-        if let Some(sig) = signal {
-            args.extend_from_slice(&["-s", sig]);
-        }
-        
-        args.push(container);
-
-        let output = Command::new("docker")
-            .args(&args)
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn load_image(&self, file: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["load", "-i", file])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn get_container_ports(&self, container: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["port", container])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    }
-
-    pub fn rename_container(&self, old_name: &str, new_name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["rename", old_name, new_name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn save_image(&self, image: &str, output_file: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["save", "-o", output_file, image])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn get_container_processes(&self, container: &str) -> Result<Vec<ContainerProcess>, String> {
-        let output = Command::new("docker")
-            .args(["top", container])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut processes = Vec::new();
-
-        // Skip header line
-        for line in output_str.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 11 {
-                processes.push(ContainerProcess {
-                    user: parts[0].to_string(),
-                    pid: parts[1].to_string(),
-                    ppid: parts[2].to_string(),
-                    cpu: parts[3].to_string(),
-                    mem: parts[4].to_string(),
-                    vsz: parts[5].to_string(),
-                    rss: parts[6].to_string(),
-                    tty: parts[7].to_string(),
-                    stat: parts[8].to_string(),
-                    start: parts[9].to_string(),
-                    time: parts[10].to_string(),
-                    command: parts[11..].join(" "),
-                });
-            }
-        }
-
-        Ok(processes)
-    }
-
-    pub fn update_container(&self, container: &str, cpu_period: Option<&str>, cpu_quota: Option<&str>, 
-                          memory: Option<&str>, memory_swap: Option<&str>) -> Result<(), String> {
-        let mut args = vec!["update"];
-        
-        if let Some(period) = cpu_period {
-            args.extend_from_slice(&["--cpu-period", period]);
-        }
-        
-        if let Some(quota) = cpu_quota {
-            args.extend_from_slice(&["--cpu-quota", quota]);
-        }
-        
-        if let Some(mem) = memory {
-            args.extend_from_slice(&["--memory", mem]);
-        }
-        
-        if let Some(mem_swap) = memory_swap {
-            args.extend_from_slice(&["--memory-swap", mem_swap]);
-        }
-        
-        args.push(container);
-
-        let output = Command::new("docker")
-            .args(&args)
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn wait_for_container(&self, container: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["wait", container])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    }
-
-    // ===== EXISTING CONTAINER COMMANDS =====
-
-    pub fn get_container_info(&self, name: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["inspect", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    }
-
-    pub fn get_container_size(&self, name: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["ps", "-s", "--format", "json", "--filter", &format!("name={}", name)])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        for line in output_str.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(json) => {
-                    if let Some(size) = json.get("Size").and_then(|v| v.as_str()) {
-                        // Parse size and format it
-                        if let Ok(size_bytes) = size.parse::<u64>() {
-                            return Ok(format_size(size_bytes));
-                        }
-                        return Ok(size.to_string());
-                    }
-                }
-                Err(_) => continue,
-            }
-        }
-
-        Err("Container not found or size information unavailable".to_string())
-    }
-
-    pub fn list_containers(&self) -> Result<Vec<Container>, String> {
+    pub fn list_containers(&self) -> Result<Vec<Container>, DockerError> {
         // Ensure Docker is running before attempting command
         self.ensure_docker_is_running()?;
-        
-        let output = Command::new("docker")
-            .args(["ps", "-a", "--format", "json"])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Docker command failed: {}", stderr));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut containers = Vec::new();
-
-        // Parse JSON output - each line is a separate JSON object
-        for line in output_str.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(json) => {
-                    if let (Some(id), Some(names), Some(image), Some(status), ports) = (
-                        json.get("ID").and_then(|v| v.as_str()),
-                        json.get("Names").and_then(|v| v.as_str()),
-                        json.get("Image").and_then(|v| v.as_str()),
-                        json.get("Status").and_then(|v| v.as_str()),
-                        json.get("Ports").and_then(|v| v.as_str()).unwrap_or("")
-                    ) {
-                        containers.push(Container {
-                            id: id.to_string(),
-                            name: names.to_string(),
-                            image: image.to_string(),
-                            status: status.to_string(),
-                            ports: ports.to_string(),
-                        });
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to parse container JSON: {} for line: {}", e, line);
-                }
-            }
-        }
-
-        Ok(containers)
-    }
-
-    pub fn start_container(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["start", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
+        self.backend.list_containers()
     }
 
-    pub fn stop_container(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["stop", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
+    pub fn get_container_stats(&self) -> Result<Vec<ContainerStats>, DockerError> {
+        // Ensure Docker is running before attempting command
+        self.ensure_docker_is_running()?;
+        self.backend.get_container_stats()
     }
 
-    pub fn remove_container(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["rm", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
+    pub fn stream_stats(&self) -> Result<StatsStream, DockerError> {
+        // Ensure Docker is running before attempting command
+        self.ensure_docker_is_running()?;
+        self.backend.stream_stats()
     }
 
-    pub fn get_container_logs(&self, name: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["logs", "--tail", "50", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    pub fn list_images(&self) -> Result<Vec<Image>, DockerError> {
+        // Ensure Docker is running before attempting command
+        self.ensure_docker_is_running()?;
+        self.backend.list_images()
     }
 
-    pub fn get_container_stats(&self) -> Result<Vec<ContainerStats>, String> {
+    pub fn get_system_info(&self) -> Result<String, DockerError> {
         // Ensure Docker is running before attempting command
         self.ensure_docker_is_running()?;
-        
-        let output = Command::new("docker")
-            .args(["stats", "--no-stream", "--format", "json"])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Docker command failed: {}", stderr));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut stats = Vec::new();
-
-        // Parse JSON output - each line is a separate JSON object
-        for line in output_str.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(json) => {
-                    if let (Some(name), Some(cpu), Some(mem_usage), Some(mem_perc), Some(net_io), Some(block_io)) = (
-                        json.get("Name").and_then(|v| v.as_str()),
-                        json.get("CPUPerc").and_then(|v| v.as_str()),
-                        json.get("MemUsage").and_then(|v| v.as_str()),
-                        json.get("MemPerc").and_then(|v| v.as_str()),
-                        json.get("NetIO").and_then(|v| v.as_str()),
-                        json.get("BlockIO").and_then(|v| v.as_str())
-                    ) {
-                        stats.push(ContainerStats {
-                            name: name.to_string(),
-                            cpu_percent: cpu.to_string(),
-                            memory_usage: mem_usage.to_string(),
-                            memory_percent: mem_perc.to_string(),
-                            network_io: net_io.to_string(),
-                            block_io: block_io.to_string(),
-                        });
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to parse stats JSON: {} for line: {}", e, line);
-                }
-            }
-        }
-
-        Ok(stats)
+        self.backend.get_system_info()
     }
 
-    pub fn restart_container(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["restart", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
+    /// Daemon events that pass `filter`, as an iterator that blocks until the
+    /// next one arrives. Dropping it closes the stream.
+    pub fn subscribe(&self, filter: EventFilter) -> Result<impl Iterator<Item = DockerEvent> + Send, DockerError> {
+        self.ensure_docker_is_running()?;
+        let events = self.backend.events(&filter)?;
+        Ok(events.filter(move |event| filter.matches(event)))
     }
 
-    pub fn pause_container(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["pause", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    /// Calls `handler` for each event that passes `filter` until it breaks
+    /// or the daemon closes the stream.
+    #[cfg_attr(feature = "async", allow(dead_code))]
+    pub fn on_event(
+        &self,
+        filter: EventFilter,
+        mut handler: impl FnMut(&DockerEvent) -> ControlFlow<()>,
+    ) -> Result<(), DockerError> {
+        for event in self.subscribe(filter)? {
+            if handler(&event).is_break() {
+                break;
+            }
         }
-
         Ok(())
     }
+}
 
-    pub fn unpause_container(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["unpause", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
+/// Yields `fetch()` right away and then once per `interval`, forever. Used
+/// for live views such as system info; stats come from `StatsUpdates`.
+pub struct Updates<F> {
+    fetch: F,
+    interval: Duration,
+    next_tick: Option<Instant>,
+}
 
-        Ok(())
+impl<F> Updates<F> {
+    pub fn new(interval: Duration, fetch: F) -> Self {
+        Updates { fetch, interval, next_tick: None }
     }
+}
 
-    pub fn exec_container(&self, name: &str, command: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["exec", name, "sh", "-c", command])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
+impl<T, F> Iterator for Updates<F>
+where
+    F: FnMut() -> Result<T, DockerError>,
+{
+    type Item = Result<T, DockerError>;
 
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(tick) = self.next_tick {
+            thread::sleep(tick.saturating_duration_since(Instant::now()));
         }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        // Schedule from the start of the fetch so slow daemons don't stretch the interval
+        self.next_tick = Some(Instant::now() + self.interval);
+        Some((self.fetch)())
     }
+}
 
-    pub fn inspect_container(&self, name: &str) -> Result<String, String> {
-        let output = Command::new("docker")
-            .args(["inspect", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
+/// How long the first update waits for the stats stream's first sample.
+const FIRST_SAMPLE_WAIT: Duration = Duration::from_secs(5);
+
+/// Yields the latest stats for every running container once the first
+/// sample is in and then once per `interval`, read from one long-lived stats
+/// stream in the background. The interval doesn't wait on the daemon's
+/// sampling, so it can be shorter than a sample takes.
+pub struct StatsUpdates {
+    samples: mpsc::Receiver<Vec<ContainerStats>>,
+    latest: Vec<ContainerStats>,
+    interval: Duration,
+    next_tick: Option<Instant>,
+    ended: bool,
+}
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+impl StatsUpdates {
+    pub fn new(interval: Duration, stream: StatsStream) -> Self {
+        let (sender, samples) = mpsc::channel();
+        thread::spawn(move || {
+            for sample in stream {
+                if sender.send(sample).is_err() {
+                    break;
+                }
+            }
+        });
+        StatsUpdates { samples, latest: Vec::new(), interval, next_tick: None, ended: false }
     }
+}
 
-    // ===== IMAGE COMMANDS =====
-
-    pub fn list_images(&self) -> Result<Vec<Image>, String> {
-        // Ensure Docker is running before attempting command
-        self.ensure_docker_is_running()?;
-        
-        let output = Command::new("docker")
-            .args(["images", "--format", "json"])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Docker command failed: {}", stderr));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut images = Vec::new();
-
-        // Parse JSON output - each line is a separate JSON object
-        for line in output_str.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(json) => {
-                    if let (Some(id), Some(repo), Some(tag), Some(size), Some(created)) = (
-                        json.get("ID").and_then(|v| v.as_str()),
-                        json.get("Repository").and_then(|v| v.as_str()),
-                        json.get("Tag").and_then(|v| v.as_str()),
-                        json.get("Size").and_then(|v| v.as_str()),
-                        json.get("CreatedAt").and_then(|v| v.as_str())
-                    ) {
-                        images.push(Image {
-                            id: id.to_string(),
-                            repository: repo.to_string(),
-                            tag: tag.to_string(),
-                            size: size.to_string(),
-                            created: created.to_string(),
-                        });
+impl Iterator for StatsUpdates {
+    type Item = Result<Vec<ContainerStats>, DockerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        let first = self.next_tick.is_none();
+        let deadline = self.next_tick.unwrap_or_else(|| Instant::now() + FIRST_SAMPLE_WAIT);
+        let mut received = false;
+        // Keep only the newest sample that arrives before the tick
+        loop {
+            match self.samples.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(sample) => {
+                    self.latest = sample;
+                    received = true;
+                    if first {
+                        break;
                     }
                 }
-                Err(e) => {
-                    eprintln!("Failed to parse image JSON: {} for line: {}", e, line);
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                // What arrived before the end is still shown
+                Err(mpsc::RecvTimeoutError::Disconnected) if received => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    self.ended = true;
+                    return Some(Err("The stats stream ended; is the daemon still running?".into()));
                 }
             }
         }
-
-        Ok(images)
+        self.next_tick = Some(Instant::now() + self.interval);
+        Some(Ok(self.latest.clone()))
     }
+}
 
-    pub fn pull_image(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["pull", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
-    }
-
-    pub fn remove_image(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["rmi", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
+impl Deref for DockerClient {
+    type Target = dyn DockerBackend;
 
-        Ok(())
+    fn deref(&self) -> &Self::Target {
+        self.backend.as_ref()
     }
+}
 
-    pub fn build_image(&self, path: &str, tag: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["build", "-t", tag, path])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_fetch_on_every_tick() {
+        let mut calls = 0;
+        let updates: Vec<u32> = Updates::new(Duration::from_millis(1), || {
+            calls += 1;
+            Ok(calls)
+        })
+        .take(3)
+        .map(|update| update.unwrap())
+        .collect();
+        assert_eq!(updates, vec![1, 2, 3]);
     }
 
-    pub fn tag_image(&self, source: &str, target: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["tag", source, target])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
+    #[test]
+    fn test_stats_updates_keep_the_latest_sample() {
+        let sample = |cpu: f64| vec![ContainerStats { name: "web".to_string(), cpu_fraction: cpu, ..Default::default() }];
+        let stream: StatsStream = Box::new(vec![sample(0.1), sample(0.2), sample(0.3)].into_iter());
+        let updates: Vec<Result<Vec<ContainerStats>, DockerError>> = StatsUpdates::new(Duration::from_millis(50), stream).collect();
+        // The first sample right away, the rest by the next tick, then the end of the stream
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].as_ref().unwrap()[0].cpu_fraction, 0.1);
+        assert_eq!(updates[1].as_ref().unwrap()[0].cpu_fraction, 0.3);
+        assert!(updates[2].is_err());
     }
 
-    pub fn push_image(&self, name: &str) -> Result<(), String> {
-        let output = Command::new("docker")
-            .args(["push", name])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(())
+    #[test]
+    fn test_container_spec_cli_args() {
+        let spec = ContainerSpec {
+            ports: vec!["8080:80".to_string()],
+            networks: vec!["backend".to_string(), "metrics".to_string()],
+            restart: Some("unless-stopped".to_string()),
+            healthcheck: Some(Healthcheck { command: "pg_isready".to_string(), retries: Some(3), ..Default::default() }),
+            command: vec!["postgres".to_string(), "-c".to_string(), "fsync=off".to_string()],
+            ..ContainerSpec::new("db", "postgres:16")
+        };
+        assert!(spec.validate().is_ok());
+        assert_eq!(
+            spec.cli_args().join(" "),
+            "--name db --publish 8080:80 --network backend --restart unless-stopped \
+             --health-cmd pg_isready --health-retries 3 postgres:16 postgres -c fsync=off"
+        );
+
+        let bad = ContainerSpec { volumes: vec![":/data".to_string()], ..ContainerSpec::new("db", "postgres:16") };
+        assert!(bad.validate().is_err());
     }
 
-    // ===== SYSTEM COMMANDS =====
-
-    pub fn get_system_info(&self) -> Result<String, String> {
-        // Ensure Docker is running before attempting command
-        self.ensure_docker_is_running()?;
-        
-        let output = Command::new("docker")
-            .args(["system", "info"])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    #[test]
+    fn test_container_spec_from_inspect() {
+        let image = serde_json::json!({"Config": {
+            "Env": ["PATH=/usr/local/bin:/usr/bin", "NGINX_VERSION=1.25.3"],
+            "Cmd": ["nginx", "-g", "daemon off;"],
+            "Labels": {"maintainer": "NGINX Docker Maintainers"},
+            "WorkingDir": ""
+        }});
+        let container = serde_json::json!({
+            "Name": "/web",
+            "Config": {
+                "Image": "nginx:1.25",
+                "Env": ["PATH=/usr/local/bin:/usr/bin", "NGINX_VERSION=1.25.3", "TZ=UTC"],
+                "Cmd": ["nginx", "-g", "daemon off;"],
+                "Labels": {"maintainer": "NGINX Docker Maintainers", "app": "shop"},
+                "WorkingDir": "",
+                "Healthcheck": {"Test": ["CMD-SHELL", "curl -f localhost"], "Interval": 30_000_000_000u64, "Retries": 3}
+            },
+            "HostConfig": {
+                "PortBindings": {"80/tcp": [{"HostIp": "", "HostPort": "8080"}], "53/udp": [{"HostIp": "127.0.0.1", "HostPort": "5353"}]},
+                "RestartPolicy": {"Name": "on-failure", "MaximumRetryCount": 3},
+                "NetworkMode": "shop",
+                "Memory": 536_870_912,
+                "NanoCpus": 1_500_000_000u64
+            },
+            "Mounts": [
+                {"Type": "volume", "Name": "static", "Source": "/var/lib/docker/volumes/static/_data", "Destination": "/usr/share/nginx/html", "RW": false},
+                {"Type": "bind", "Source": "/srv/conf", "Destination": "/etc/nginx/conf.d", "RW": true}
+            ],
+            "NetworkSettings": {"Networks": {"metrics": {}, "shop": {}}}
+        });
+
+        let spec = ContainerSpec::from_inspect(&container, &image);
+        assert!(spec.validate().is_ok());
+        assert_eq!(
+            spec.cli_args().join(" "),
+            "--name web --publish 127.0.0.1:5353:53/udp --publish 8080:80 --volume static:/usr/share/nginx/html:ro \
+             --volume /srv/conf:/etc/nginx/conf.d --env TZ=UTC --label app=shop --network shop --restart on-failure:3 \
+             --memory 536870912b --cpus 1.5 --health-cmd curl -f localhost --health-interval 30s --health-retries 3 nginx:1.25"
+        );
+        assert_eq!(spec.networks, vec!["shop", "metrics"]);
+        assert!(ContainerSpec::unsupported_settings(&container).is_empty());
+
+        let mut privileged = container.clone();
+        privileged["Id"] = "3f2a1b9c8d7e6f5a".into();
+        privileged["HostConfig"]["Privileged"] = true.into();
+        privileged["HostConfig"]["CapAdd"] = serde_json::json!(["NET_ADMIN"]);
+        privileged["HostConfig"]["ShmSize"] = 67_108_864.into();
+        privileged["NetworkSettings"]["Networks"]["shop"] = serde_json::json!({"Aliases": ["web", "3f2a1b9c8d7e", "shop-web"]});
+        assert_eq!(ContainerSpec::unsupported_settings(&privileged), vec!["--privileged", "--cap-add", "--network-alias"]);
+        assert_eq!(ContainerSpec::from_inspect(&privileged, &image), spec);
     }
 
-    pub fn monitor_events(&self) -> Result<(), String> {
-        let mut child = Command::new("docker")
-            .args(["events"])
-            .stdout(Stdio::piped())
-            .spawn()
-            .map_err(|e| format!("Failed to start docker events: {}", e))?;
-
-        if let Some(stdout) = child.stdout.take() {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines() {
-                match line {
-                    Ok(event) => println!("{}", event),
-                    Err(e) => eprintln!("Error reading event: {}", e),
-                }
-            }
-        }
-
-        Ok(())
+    #[test]
+    fn test_container_spec_api_config() {
+        let spec = ContainerSpec {
+            ports: vec!["127.0.0.1:8080:80".to_string(), "9000-9001:9000-9001/udp".to_string(), "443".to_string()],
+            volumes: vec!["static:/usr/share/nginx/html:ro".to_string(), "/cache".to_string()],
+            env: vec!["TZ=UTC".to_string()],
+            labels: vec!["app=shop".to_string()],
+            networks: vec!["shop".to_string(), "metrics".to_string()],
+            restart: Some("on-failure:3".to_string()),
+            memory: Some("512m".to_string()),
+            cpus: Some("1.5".to_string()),
+            healthcheck: Some(Healthcheck { command: "curl -f localhost".to_string(), interval: Some("30s".to_string()), ..Default::default() }),
+            entrypoint: Some("nginx".to_string()),
+            command: vec!["-g".to_string(), "daemon off;".to_string()],
+            ..ContainerSpec::new("web", "nginx:1.25")
+        };
+        let config = spec.api_config().unwrap();
+        let host = &config["HostConfig"];
+        assert_eq!(host["PortBindings"]["80/tcp"], json!([{"HostIp": "127.0.0.1", "HostPort": "8080"}]));
+        assert_eq!(host["PortBindings"]["9001/udp"], json!([{"HostIp": "", "HostPort": "9001"}]));
+        assert!(host["PortBindings"].get("443/tcp").is_none());
+        assert!(config["ExposedPorts"].get("443/tcp").is_some());
+        assert_eq!(host["Binds"], json!(["static:/usr/share/nginx/html:ro"]));
+        assert_eq!(config["Volumes"], json!({"/cache": {}}));
+        assert_eq!(host["NetworkMode"], "shop");
+        assert_eq!(host["RestartPolicy"], json!({"Name": "on-failure", "MaximumRetryCount": 3}));
+        assert_eq!((host["Memory"].as_u64(), host["NanoCpus"].as_u64()), (Some(536_870_912), Some(1_500_000_000)));
+        assert_eq!(config["Labels"], json!({"app": "shop"}));
+        assert_eq!(config["Entrypoint"], json!(["nginx"]));
+        assert_eq!(config["Cmd"], json!(["-g", "daemon off;"]));
+        assert_eq!(config["Healthcheck"]["Interval"], 30_000_000_000u64);
+
+        let bad = ContainerSpec { memory: Some("lots".to_string()), ..ContainerSpec::new("web", "nginx") };
+        assert!(bad.api_config().is_err());
     }
 
-    // ===== NETWORK COMMANDS =====
-
-    pub fn list_networks(&self) -> Result<Vec<Network>, String> {
-        let output = Command::new("docker")
-            .args(["network", "ls", "--format", "json"])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Docker command failed: {}", stderr));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut networks = Vec::new();
-
-        for line in output_str.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(json) => {
-                    if let (Some(id), Some(name), Some(driver), Some(scope)) = (
-                        json.get("ID").and_then(|v| v.as_str()),
-                        json.get("Name").and_then(|v| v.as_str()),
-                        json.get("Driver").and_then(|v| v.as_str()),
-                        json.get("Scope").and_then(|v| v.as_str())
-                    ) {
-                        networks.push(Network {
-                            id: id.to_string(),
-                            name: name.to_string(),
-                            driver: driver.to_string(),
-                            scope: scope.to_string(),
-                        });
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to parse network JSON: {} for line: {}", e, line);
-                }
-            }
-        }
-
-        Ok(networks)
+    #[test]
+    fn test_build_options_cli_args() {
+        let options = BuildOptions {
+            dockerfile: Some("docker/Dockerfile.prod".to_string()),
+            build_args: vec!["VERSION=1.2".to_string()],
+            target: Some("runtime".to_string()),
+            no_cache: true,
+            ..BuildOptions::new(".", vec!["app:1.2".to_string(), "app:latest".to_string()])
+        };
+        assert!(options.validate().is_ok());
+        assert_eq!(
+            options.cli_args().join(" "),
+            "--tag app:1.2 --tag app:latest --build-arg VERSION=1.2 --file docker/Dockerfile.prod --target runtime --no-cache ."
+        );
+
+        let bad = BuildOptions { labels: vec!["=team".to_string()], ..BuildOptions::new(".", Vec::new()) };
+        assert!(bad.validate().is_err());
     }
 
-    // ===== VOLUME COMMANDS =====
-
-    pub fn list_volumes(&self) -> Result<Vec<Volume>, String> {
-        let output = Command::new("docker")
-            .args(["volume", "ls", "--format", "json"])
-            .output()
-            .map_err(|e| format!("Failed to execute docker command: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Docker command failed: {}", stderr));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut volumes = Vec::new();
-
-        for line in output_str.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            match serde_json::from_str::<serde_json::Value>(line) {
-                Ok(json) => {
-                    if let (Some(name), Some(driver), Some(mountpoint)) = (
-                        json.get("Name").and_then(|v| v.as_str()),
-                        json.get("Driver").and_then(|v| v.as_str()),
-                        json.get("Mountpoint").and_then(|v| v.as_str())
-                    ) {
-                        volumes.push(Volume {
-                            name: name.to_string(),
-                            driver: driver.to_string(),
-                            mountpoint: mountpoint.to_string(),
-                        });
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to parse volume JSON: {} for line: {}", e, line);
-                }
-            }
-        }
-
-        Ok(volumes)
+    #[test]
+    fn test_on_event_filters_and_stops() {
+        let docker = DockerClient::with_backend(Arc::new(crate::fake::FakeBackend::demo()));
+        let mut seen = Vec::new();
+        docker
+            .on_event(EventFilter { types: vec!["container".to_string()], ..Default::default() }, |event| {
+                seen.push(event.action.clone());
+                ControlFlow::Break(())
+            })
+            .unwrap();
+        assert_eq!(seen, vec!["start"]);
+
+        let dies: Vec<DockerEvent> =
+            docker.subscribe(EventFilter { actions: vec!["die".to_string()], ..Default::default() }).unwrap().collect();
+        assert_eq!(dies.len(), 1);
+        assert_eq!(dies[0].actor.attributes["name"], "worker");
     }
 }
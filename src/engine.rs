@@ -0,0 +1,1447 @@
+// Native Docker Engine API client.
+//
+// Speaks HTTP/1.1 directly to the daemon socket (or a plain tcp:// DOCKER_HOST)
+// so the common read paths don't need to spawn the docker binary.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use crate::backend::{Archive, BuildOutput, DockerBackend, EventStream, LogLines, ProgressStream, StatsStream};
+use crate::cli::CliBackend;
+use crate::context::{self, Target};
+use crate::copy::PathStat;
+use crate::disk::DiskUsage;
+use crate::docker::{BuildOptions, Container, ContainerProcess, ContainerSpec, ContainerStats, ExecOptions, Image, Network, Volume};
+use crate::error::DockerError;
+use crate::events::{DockerEvent, EventFilter};
+use crate::logs::{LogLine, LogOptions, LogStream};
+use crate::progress::ProgressMessage;
+use crate::registry::{Credentials, ImageRef};
+use crate::runtime::{container_id, container_name, record_labels, Runtime};
+use crate::utils::{base64_decode, decimal_size, format_size, format_timestamp, parse_memory, truncate_string};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl Endpoint {
+    /// Parses a DOCKER_HOST style address. Schemes we can't speak directly
+    /// (ssh://, npipe://) return None so callers fall back to the CLI.
+    pub fn parse(host: &str) -> Option<Endpoint> {
+        if let Some(path) = host.strip_prefix("unix://") {
+            Some(Endpoint::Unix(PathBuf::from(path)))
+        } else {
+            host.strip_prefix("tcp://")
+                .or_else(|| host.strip_prefix("http://"))
+                .map(|addr| Endpoint::Tcp(addr.trim_end_matches('/').to_string()))
+        }
+    }
+}
+
+trait Connection: Read + Write + Send {}
+impl<T: Read + Write + Send> Connection for T {}
+
+pub struct Response {
+    pub status: u16,
+    headers: Vec<(String, String)>,
+    body: Box<dyn Read + Send>,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status) || self.status == 304
+    }
+
+    pub fn bytes(mut self) -> Result<Vec<u8>, DockerError> {
+        let mut data = Vec::new();
+        self.body
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read response body: {}", e))?;
+        Ok(data)
+    }
+
+    pub fn json(self) -> Result<Value, DockerError> {
+        let data = self.bytes()?;
+        serde_json::from_slice(&data).map_err(|e| format!("Failed to parse API response: {}", e).into())
+    }
+
+    pub fn into_reader(self) -> Box<dyn Read + Send> {
+        self.body
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body.
+struct ChunkedReader<R> {
+    inner: R,
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        ChunkedReader { inner, remaining: 0, done: false }
+    }
+
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let mut line = String::new();
+        self.inner.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or("");
+        self.remaining = usize::from_str_radix(size, 16)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid chunk size: {:?}", size)))?;
+        if self.remaining == 0 {
+            self.done = true;
+            // Drain optional trailers up to the terminating blank line
+            loop {
+                line.clear();
+                if self.inner.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            self.next_chunk()?;
+            if self.done {
+                return Ok(0);
+            }
+        }
+        let max = buf.len().min(self.remaining);
+        let read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed mid-chunk"));
+        }
+        self.remaining -= read;
+        if self.remaining == 0 {
+            let mut crlf = [0u8; 2];
+            self.inner.read_exact(&mut crlf)?;
+        }
+        Ok(read)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineClient {
+    endpoint: Endpoint,
+    cli: CliBackend,
+}
+
+impl EngineClient {
+    pub fn new(endpoint: Endpoint, cli: CliBackend) -> Self {
+        EngineClient { endpoint, cli }
+    }
+
+    /// Builds a client for the selected daemon, using the runtime's default
+    /// socket when no host is set. TLS-protected and ssh:// hosts are left to
+    /// the CLI, as is a daemon that doesn't answer a ping, e.g. behind a stale
+    /// socket file, so the CLI can report it or start the daemon.
+    pub fn for_target(target: &Target, runtime: Runtime) -> Option<Self> {
+        if target.tls {
+            return None;
+        }
+        let cli = CliBackend::new(runtime, &target.cli_args);
+        let client = match &target.host {
+            Some(host) => Endpoint::parse(host).map(|endpoint| EngineClient::new(endpoint, cli)),
+            None => runtime.default_socket().map(|path| EngineClient::new(Endpoint::Unix(path), cli)),
+        }?;
+        client.ping().then_some(client)
+    }
+
+    fn connect(&self) -> Result<Box<dyn Connection>, DockerError> {
+        match &self.endpoint {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => UnixStream::connect(path)
+                .map(|stream| Box::new(stream) as Box<dyn Connection>)
+                .map_err(|e| format!("Cannot connect to the Docker daemon at unix://{}: {}", path.display(), e).into()),
+            #[cfg(not(unix))]
+            Endpoint::Unix(path) => Err(format!("Unix sockets are not supported on this platform: {}", path.display()).into()),
+            Endpoint::Tcp(addr) => TcpStream::connect(addr)
+                .map(|stream| Box::new(stream) as Box<dyn Connection>)
+                .map_err(|e| format!("Cannot connect to the Docker daemon at tcp://{}: {}", addr, e).into()),
+        }
+    }
+
+    pub fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Response, DockerError> {
+        self.request_with_headers(method, path, &[], body)
+    }
+
+    fn request_with_headers(&self, method: &str, path: &str, headers: &[(&str, &str)], body: Option<&Value>) -> Result<Response, DockerError> {
+        let mut conn = self.connect()?;
+        let payload = body.map(|b| b.to_string());
+
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: docker\r\nUser-Agent: dui/{}\r\nConnection: close\r\n",
+            method,
+            path,
+            env!("CARGO_PKG_VERSION")
+        );
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        match &payload {
+            Some(data) => head.push_str(&format!("Content-Type: application/json\r\nContent-Length: {}\r\n", data.len())),
+            None if method == "POST" || method == "PUT" => head.push_str("Content-Length: 0\r\n"),
+            None => {}
+        }
+        head.push_str("\r\n");
+
+        conn.write_all(head.as_bytes())
+            .and_then(|_| payload.as_ref().map_or(Ok(()), |data| conn.write_all(data.as_bytes())))
+            .and_then(|_| conn.flush())
+            .map_err(|e| format!("Failed to send request to Docker daemon: {}", e))?;
+
+        read_response(BufReader::new(conn), method == "HEAD")
+    }
+
+    /// Sends a request and turns non-2xx replies into the daemon's error message.
+    fn call(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Response, DockerError> {
+        check(self.request(method, path, body)?)
+    }
+
+    /// Streams `body` as a chunked upload, e.g. a tar archive.
+    fn upload(&self, method: &str, path: &str, content_type: &str, body: &mut dyn Read) -> Result<Response, DockerError> {
+        let mut conn = self.connect()?;
+        let head = format!(
+            "{} {} HTTP/1.1\r\nHost: docker\r\nUser-Agent: dui/{}\r\nConnection: close\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\n\r\n",
+            method,
+            path,
+            env!("CARGO_PKG_VERSION"),
+            content_type
+        );
+
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut sent = conn.write_all(head.as_bytes());
+        while sent.is_ok() {
+            let read = body.read(&mut buffer).map_err(|e| format!("Failed to read upload: {}", e))?;
+            sent = conn
+                .write_all(format!("{:x}\r\n", read).as_bytes())
+                .and_then(|_| conn.write_all(&buffer[..read]))
+                .and_then(|_| conn.write_all(b"\r\n"));
+            if read == 0 {
+                break;
+            }
+        }
+        let sent = sent.and_then(|_| conn.flush());
+
+        // The daemon may reject the upload before reading all of it; its
+        // reply says why better than the broken pipe does
+        match (sent, read_response(BufReader::new(conn), false)) {
+            (Ok(()), response) => check(response?),
+            (Err(_), Ok(response)) if !response.is_success() => check(response),
+            (Err(e), _) => Err(format!("Failed to send request to Docker daemon: {}", e).into()),
+        }
+    }
+
+    fn get_json(&self, path: &str) -> Result<Value, DockerError> {
+        self.call("GET", path, None)?.json()
+    }
+
+    fn post(&self, path: &str) -> Result<(), DockerError> {
+        self.call("POST", path, None)?.bytes().map(|_| ())
+    }
+
+    fn download(&self, path: &str, output_file: &str) -> Result<(), DockerError> {
+        let mut reader = self.call("GET", path, None)?.into_reader();
+        let mut file = File::create(output_file).map_err(|e| format!("Failed to create {}: {}", output_file, e))?;
+        io::copy(&mut reader, &mut file).map_err(|e| format!("Failed to write {}: {}", output_file, e))?;
+        Ok(())
+    }
+
+    fn list_running(&self) -> Result<Vec<(String, String)>, DockerError> {
+        let json = self.get_json("/containers/json")?;
+        Ok(json
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|c| {
+                let container = container_from_json(c);
+                (container.id, container.name)
+            })
+            .collect())
+    }
+
+    // Sends every sample of one container's `stream=true` stats as (id, stats),
+    // then (id, None) once the stream ends or can't be opened
+    fn follow_stats(&self, id: String, name: String, sender: Sender<(String, Option<ContainerStats>)>) {
+        let client = self.clone();
+        thread::spawn(move || {
+            if let Ok(response) = client.call("GET", &format!("/containers/{}/stats?stream=true", encode(&id)), None) {
+                for (index, line) in BufReader::new(response.into_reader()).lines().map_while(Result::ok).enumerate() {
+                    let json = match serde_json::from_str::<Value>(&line) {
+                        Ok(json) => json,
+                        Err(_) => continue,
+                    };
+                    // The first sample has nothing to measure CPU against yet
+                    if index == 0 && json.pointer("/precpu_stats/system_cpu_usage").and_then(|v| v.as_u64()).unwrap_or(0) == 0 {
+                        continue;
+                    }
+                    if sender.send((id.clone(), Some(stats_from_json(&name, &json)))).is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = sender.send((id, None));
+        });
+    }
+
+    fn container_stats(&self, id: &str, name: &str) -> Result<ContainerStats, DockerError> {
+        let json = self.get_json(&format!("/containers/{}/stats?stream=false", encode(id)))?;
+        Ok(stats_from_json(name, &json))
+    }
+}
+
+impl DockerBackend for EngineClient {
+    // ===== SYSTEM =====
+
+    // Only chosen once the daemon answered, see for_target
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn ping(&self) -> bool {
+        self.request("GET", "/_ping", None)
+            .map(|response| response.is_success())
+            .unwrap_or(false)
+    }
+
+    fn get_system_info(&self) -> Result<String, DockerError> {
+        let info = self.get_json("/info")?;
+        let mut lines = Vec::new();
+        if let Some(fields) = info.as_object() {
+            for (key, value) in fields {
+                match value {
+                    Value::String(s) => lines.push(format!("{}: {}", key, s)),
+                    Value::Number(n) => lines.push(format!("{}: {}", key, n)),
+                    Value::Bool(b) => lines.push(format!("{}: {}", key, b)),
+                    _ => {}
+                }
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn disk_usage(&self) -> Result<DiskUsage, DockerError> {
+        Ok(DiskUsage::from_engine(&self.get_json("/system/df")?))
+    }
+
+    fn events(&self, filter: &EventFilter) -> Result<EventStream, DockerError> {
+        let mut query = Vec::new();
+        if let Some(since) = filter.since {
+            query.push(format!("since={}", since));
+        }
+        if let Some(filters) = filter.api_filters() {
+            query.push(format!("filters={}", encode(&filters.to_string())));
+        }
+        let path = if query.is_empty() { "/events".to_string() } else { format!("/events?{}", query.join("&")) };
+
+        let reader = BufReader::new(self.call("GET", &path, None)?.into_reader());
+        Ok(Box::new(
+            reader
+                .lines()
+                .map_while(|line| line.ok())
+                .filter_map(|line| DockerEvent::from_json(&serde_json::from_str(&line).ok()?)),
+        ))
+    }
+
+    // ===== CONTAINERS =====
+
+    fn list_containers(&self) -> Result<Vec<Container>, DockerError> {
+        let json = self.get_json("/containers/json?all=1")?;
+        Ok(json.as_array().map(|items| items.iter().map(container_from_json).collect()).unwrap_or_default())
+    }
+
+    fn inspect_container(&self, name: &str) -> Result<String, DockerError> {
+        let json = self.get_json(&format!("/containers/{}/json", encode(name)))?;
+        serde_json::to_string_pretty(&Value::Array(vec![json])).map_err(|e| e.to_string().into())
+    }
+
+    fn start_container(&self, name: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/start", encode(name)))
+    }
+
+    fn stop_container(&self, name: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/stop", encode(name)))
+    }
+
+    fn restart_container(&self, name: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/restart", encode(name)))
+    }
+
+    fn pause_container(&self, name: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/pause", encode(name)))
+    }
+
+    fn unpause_container(&self, name: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/unpause", encode(name)))
+    }
+
+    fn kill_container(&self, name: &str, signal: Option<&str>) -> Result<(), DockerError> {
+        let mut path = format!("/containers/{}/kill", encode(name));
+        if let Some(sig) = signal {
+            path.push_str(&format!("?signal={}", encode(sig)));
+        }
+        self.post(&path)
+    }
+
+    fn remove_container(&self, name: &str) -> Result<(), DockerError> {
+        self.call("DELETE", &format!("/containers/{}", encode(name)), None)?.bytes().map(|_| ())
+    }
+
+    fn rename_container(&self, old_name: &str, new_name: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/rename?name={}", encode(old_name), encode(new_name)))
+    }
+
+    fn wait_for_container(&self, name: &str) -> Result<String, DockerError> {
+        let json = self.call("POST", &format!("/containers/{}/wait", encode(name)), None)?.json()?;
+        Ok(json.get("StatusCode").map(|code| code.to_string()).unwrap_or_default())
+    }
+
+    fn commit_container(&self, container: &str, repository: &str, tag: Option<&str>) -> Result<(), DockerError> {
+        let mut path = format!("/commit?container={}&repo={}", encode(container), encode(repository));
+        if let Some(tag_value) = tag {
+            path.push_str(&format!("&tag={}", encode(tag_value)));
+        }
+        self.post(&path)
+    }
+
+    fn export_container(&self, name: &str, output_file: &str) -> Result<(), DockerError> {
+        self.download(&format!("/containers/{}/export", encode(name)), output_file)
+    }
+
+    fn diff_container(&self, name: &str) -> Result<String, DockerError> {
+        let json = self.get_json(&format!("/containers/{}/changes", encode(name)))?;
+        let changes = json.as_array().cloned().unwrap_or_default();
+        Ok(changes
+            .iter()
+            .map(|change| {
+                let kind = match change.get("Kind").and_then(|k| k.as_u64()) {
+                    Some(1) => "A",
+                    Some(2) => "D",
+                    _ => "C",
+                };
+                format!("{} {}", kind, str_field(change, "Path"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn get_container_ports(&self, name: &str) -> Result<String, DockerError> {
+        let json = self.get_json(&format!("/containers/{}/json", encode(name)))?;
+        let mut lines = Vec::new();
+        if let Some(ports) = json.pointer("/NetworkSettings/Ports").and_then(|p| p.as_object()) {
+            for (container_port, bindings) in ports {
+                for binding in bindings.as_array().into_iter().flatten() {
+                    lines.push(format!(
+                        "{} -> {}:{}",
+                        container_port,
+                        str_field(binding, "HostIp"),
+                        str_field(binding, "HostPort")
+                    ));
+                }
+            }
+        }
+        lines.sort();
+        Ok(lines.join("\n"))
+    }
+
+    fn get_container_size(&self, name: &str) -> Result<String, DockerError> {
+        let filters = json!({ "name": [name] }).to_string();
+        let json = self.get_json(&format!("/containers/json?all=1&size=1&filters={}", encode(&filters)))?;
+        let items = json.as_array().cloned().unwrap_or_default();
+        let container = items
+            .iter()
+            .find(|c| container_from_json(c).name == name)
+            .or_else(|| items.first())
+            .ok_or_else(|| "Container not found or size information unavailable".to_string())?;
+        let rw = container.get("SizeRw").and_then(|v| v.as_u64()).unwrap_or(0);
+        let root = container.get("SizeRootFs").and_then(|v| v.as_u64()).unwrap_or(0);
+        Ok(format!("{} (virtual {})", format_size(rw), format_size(root)))
+    }
+
+    fn get_container_logs(&self, name: &str, options: &LogOptions) -> Result<LogLines, DockerError> {
+        let response = self.call("GET", &format!("/containers/{}/logs?{}", encode(name), options.api_query()), None)?;
+        Ok(Box::new(LogFrames::new(response.into_reader(), options.timestamps)))
+    }
+
+    fn copy_from_container(&self, container: &str, path: &str) -> Result<Archive, DockerError> {
+        let path = format!("/containers/{}/archive?path={}", encode(container), encode(path));
+        Ok(self.call("GET", &path, None)?.into_reader())
+    }
+
+    fn copy_to_container(&self, container: &str, dir: &str, archive: &mut dyn Read) -> Result<(), DockerError> {
+        let path = format!("/containers/{}/archive?path={}", encode(container), encode(dir));
+        self.upload("PUT", &path, "application/x-tar", archive)?.bytes().map(|_| ())
+    }
+
+    fn stat_container_path(&self, container: &str, path: &str) -> Result<Option<PathStat>, DockerError> {
+        let response = self.request("HEAD", &format!("/containers/{}/archive?path={}", encode(container), encode(path)), None)?;
+        if response.status == 404 {
+            // Missing container or missing path; only the first is an error
+            self.get_json(&format!("/containers/{}/json", encode(container)))?;
+            return Ok(None);
+        }
+        let response = check(response)?;
+        let stat = response
+            .header("X-Docker-Container-Path-Stat")
+            .and_then(base64_decode)
+            .and_then(|data| serde_json::from_slice::<Value>(&data).ok())
+            .ok_or_else(|| "Docker daemon sent no path information".to_string())?;
+        Ok(Some(path_stat_from_json(&stat)))
+    }
+
+    fn get_container_processes(&self, name: &str) -> Result<Vec<ContainerProcess>, DockerError> {
+        let json = self.get_json(&format!("/containers/{}/top", encode(name)))?;
+        let titles: Vec<String> = json
+            .get("Titles")
+            .and_then(|t| t.as_array())
+            .map(|t| t.iter().map(|v| v.as_str().unwrap_or("").to_string()).collect())
+            .unwrap_or_default();
+        let column = |row: &[Value], names: &[&str]| -> String {
+            titles
+                .iter()
+                .position(|t| names.contains(&t.as_str()))
+                .and_then(|i| row.get(i))
+                .and_then(|v| v.as_str())
+                .unwrap_or("-")
+                .to_string()
+        };
+
+        let mut processes = Vec::new();
+        for row in json.get("Processes").and_then(|p| p.as_array()).into_iter().flatten() {
+            let row = row.as_array().map(|r| r.as_slice()).unwrap_or(&[]);
+            processes.push(ContainerProcess {
+                user: column(row, &["USER", "UID"]),
+                pid: column(row, &["PID"]),
+                ppid: column(row, &["PPID"]),
+                cpu: column(row, &["%CPU", "C"]),
+                mem: column(row, &["%MEM"]),
+                vsz: column(row, &["VSZ"]),
+                rss: column(row, &["RSS"]),
+                tty: column(row, &["TTY", "TT"]),
+                stat: column(row, &["STAT"]),
+                start: column(row, &["START", "STIME"]),
+                time: column(row, &["TIME"]),
+                command: column(row, &["COMMAND", "CMD"]),
+            });
+        }
+        Ok(processes)
+    }
+
+    fn exec_container(&self, name: &str, command: &str, options: &ExecOptions) -> Result<String, DockerError> {
+        let mut body = json!({
+            "AttachStdout": true,
+            "AttachStderr": true,
+            "Cmd": ["sh", "-c", command],
+            "Env": options.env,
+        });
+        if let Some(user) = &options.user {
+            body["User"] = json!(user);
+        }
+        if let Some(workdir) = &options.workdir {
+            body["WorkingDir"] = json!(workdir);
+        }
+        let created = self.call("POST", &format!("/containers/{}/exec", encode(name)), Some(&body))?.json()?;
+        let exec_id = str_field(&created, "Id");
+
+        let data = self
+            .call("POST", &format!("/exec/{}/start", encode(&exec_id)), Some(&json!({ "Detach": false, "Tty": false })))?
+            .bytes()?;
+        let (stdout, stderr) = demux_stream(&data);
+
+        let inspect = self.get_json(&format!("/exec/{}/json", encode(&exec_id)))?;
+        if inspect.get("ExitCode").and_then(|c| c.as_i64()).unwrap_or(0) != 0 {
+            return Err(DockerError::from_message(String::from_utf8_lossy(&stderr)));
+        }
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+
+    fn get_container_stats(&self) -> Result<Vec<ContainerStats>, DockerError> {
+        let running = self.list_running()?;
+        // Each one-shot stats call blocks for a sampling interval, so fan out
+        let results: Vec<(String, Result<ContainerStats, DockerError>)> = thread::scope(|scope| {
+            let handles: Vec<_> = running
+                .iter()
+                .map(|(id, name)| (name, scope.spawn(move || self.container_stats(id, name))))
+                .collect();
+            handles
+                .into_iter()
+                .map(|(name, handle)| {
+                    let result = handle.join().unwrap_or_else(|_| Err("stats worker panicked".into()));
+                    (name.clone(), result)
+                })
+                .collect()
+        });
+
+        let mut stats = Vec::new();
+        for (name, result) in results {
+            match result {
+                Ok(stat) => stats.push(stat),
+                Err(e) => eprintln!("Failed to get stats for {}: {}", name, e),
+            }
+        }
+        Ok(stats)
+    }
+
+    fn samples_stats_per_container(&self) -> bool {
+        true
+    }
+
+    fn get_stats_for_container(&self, container: &str) -> Result<ContainerStats, DockerError> {
+        self.container_stats(container, container)
+    }
+
+    fn stream_stats(&self) -> Result<StatsStream, DockerError> {
+        let (sender, updates) = mpsc::channel();
+        let mut stats = EngineStats {
+            client: self.clone(),
+            sender,
+            updates,
+            streaming: HashSet::new(),
+            latest: Vec::new(),
+            next_listing: Instant::now(),
+        };
+        // Fail here rather than mid-stream when the daemon can't be reached
+        stats.follow_running()?;
+        Ok(Box::new(stats))
+    }
+
+    // ===== IMAGES =====
+
+    fn list_images(&self) -> Result<Vec<Image>, DockerError> {
+        let json = self.get_json("/images/json")?;
+        let mut images = Vec::new();
+        for item in json.as_array().into_iter().flatten() {
+            let id = str_field(item, "Id");
+            let id = id.strip_prefix("sha256:").unwrap_or(&id).chars().take(12).collect::<String>();
+            let size = decimal_size(item.get("Size").and_then(|s| s.as_f64()).unwrap_or(0.0));
+            let created = format_timestamp(item.get("Created").and_then(|c| c.as_i64()).unwrap_or(0));
+
+            let mut tags: Vec<String> = item
+                .get("RepoTags")
+                .and_then(|t| t.as_array())
+                .map(|t| t.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default();
+            if tags.is_empty() {
+                tags.push("<none>:<none>".to_string());
+            }
+
+            for reference in tags {
+                let (repository, tag) = split_reference(&reference);
+                images.push(Image {
+                    id: id.clone(),
+                    repository,
+                    tag,
+                    size: size.clone(),
+                    created: created.clone(),
+                });
+            }
+        }
+        Ok(images)
+    }
+
+    fn image_exists(&self, name: &str) -> Result<bool, DockerError> {
+        let response = self.request("GET", &format!("/images/{}/json", encode(name)), None)?;
+        if response.status == 404 {
+            return Ok(false);
+        }
+        check(response)?.bytes().map(|_| true)
+    }
+
+    fn image_repo_digests(&self, name: &str) -> Result<Vec<String>, DockerError> {
+        let json = self.get_json(&format!("/images/{}/json", encode(name)))?;
+        Ok(json["RepoDigests"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|digest| digest.as_str())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn inspect_image(&self, name: &str) -> Result<String, DockerError> {
+        let json = self.get_json(&format!("/images/{}/json", encode(name)))?;
+        serde_json::to_string_pretty(&Value::Array(vec![json])).map_err(|e| e.to_string().into())
+    }
+
+    fn pull_image(&self, name: &str) -> Result<ProgressStream, DockerError> {
+        let path = if name.contains('@') {
+            format!("/images/create?fromImage={}", encode(name))
+        } else {
+            let (repository, tag) = split_reference(name);
+            format!("/images/create?fromImage={}&tag={}", encode(&repository), encode(&tag))
+        };
+        // Private registries need what `docker login` stored, as the CLI would send it
+        let auth = ImageRef::parse(name).ok().and_then(|image| {
+            let credentials = Credentials::from_config(&context::config_dir()?, &image.registry).ok()??;
+            Some(credentials.engine_auth(&image.registry))
+        });
+        let headers: Vec<(&str, &str)> = auth.iter().map(|auth| ("X-Registry-Auth", auth.as_str())).collect();
+        let reader = BufReader::new(check(self.request_with_headers("POST", &path, &headers, None)?)?.into_reader());
+        // A dropped connection ends the stream with a failure, not silently
+        let mut failed = false;
+        Ok(Box::new(
+            reader
+                .lines()
+                .map_while(move |line| match line {
+                    _ if failed => None,
+                    Ok(line) => Some(ProgressMessage::parse(&line)),
+                    Err(e) => {
+                        failed = true;
+                        Some(Some(ProgressMessage::failure(format!("Failed to read pull progress: {}", e))))
+                    }
+                })
+                .flatten(),
+        ))
+    }
+
+    fn remove_image(&self, name: &str) -> Result<(), DockerError> {
+        self.call("DELETE", &format!("/images/{}", encode(name)), None)?.bytes().map(|_| ())
+    }
+
+    fn tag_image(&self, source: &str, target: &str) -> Result<(), DockerError> {
+        let (repository, tag) = split_reference(target);
+        self.post(&format!("/images/{}/tag?repo={}&tag={}", encode(source), encode(&repository), encode(&tag)))
+    }
+
+    fn get_image_history(&self, image: &str) -> Result<String, DockerError> {
+        let json = self.get_json(&format!("/images/{}/history", encode(image)))?;
+        let mut lines = vec![format!("{:<14} {:<30} {:<47} {:<10} {}", "IMAGE", "CREATED", "CREATED BY", "SIZE", "COMMENT")];
+        for layer in json.as_array().into_iter().flatten() {
+            let id = str_field(layer, "Id");
+            let id = match id.strip_prefix("sha256:") {
+                Some(hex) => hex.chars().take(12).collect(),
+                None => id,
+            };
+            lines.push(format!(
+                "{:<14} {:<30} {:<47} {:<10} {}",
+                id,
+                format_timestamp(layer.get("Created").and_then(|c| c.as_i64()).unwrap_or(0)),
+                truncate_string(&str_field(layer, "CreatedBy"), 45),
+                decimal_size(layer.get("Size").and_then(|s| s.as_f64()).unwrap_or(0.0)),
+                str_field(layer, "Comment")
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn save_image(&self, image: &str, output_file: &str) -> Result<(), DockerError> {
+        self.download(&format!("/images/get?names={}", encode(image)), output_file)
+    }
+
+    fn export_image(&self, image: &str) -> Result<Archive, DockerError> {
+        Ok(self.call("GET", &format!("/images/get?names={}", encode(image)), None)?.into_reader())
+    }
+
+    // ===== NETWORKS & VOLUMES =====
+
+    fn list_networks(&self) -> Result<Vec<Network>, DockerError> {
+        let json = self.get_json("/networks")?;
+        Ok(json
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|n| Network {
+                id: str_field(n, "Id"),
+                name: str_field(n, "Name"),
+                driver: str_field(n, "Driver"),
+                scope: str_field(n, "Scope"),
+            })
+            .collect())
+    }
+
+    fn list_volumes(&self) -> Result<Vec<Volume>, DockerError> {
+        let json = self.get_json("/volumes")?;
+        Ok(json
+            .get("Volumes")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .map(|v| Volume {
+                name: str_field(v, "Name"),
+                driver: str_field(v, "Driver"),
+                mountpoint: str_field(v, "Mountpoint"),
+            })
+            .collect())
+    }
+
+    fn remove_network(&self, name: &str) -> Result<(), DockerError> {
+        self.call("DELETE", &format!("/networks/{}", encode(name)), None)?.bytes().map(|_| ())
+    }
+
+    fn remove_volume(&self, name: &str) -> Result<(), DockerError> {
+        self.call("DELETE", &format!("/volumes/{}", encode(name)), None)?.bytes().map(|_| ())
+    }
+
+    fn network_containers(&self, network: &str) -> Result<usize, DockerError> {
+        // The network's own Containers only has running endpoints
+        let filters = json!({"network": [network]}).to_string();
+        let json = self.get_json(&format!("/containers/json?all=1&filters={}", encode(&filters)))?;
+        Ok(json.as_array().map_or(0, Vec::len))
+    }
+
+    fn create_container(&self, spec: &ContainerSpec) -> Result<(), DockerError> {
+        spec.validate()?;
+        let path = format!("/containers/create?name={}", encode(&spec.name));
+        let created = self.call("POST", &path, Some(&spec.api_config()?))?.json()?;
+        let id = str_field(&created, "Id");
+        // Joined before starting, so the container comes up on all of them
+        for network in spec.networks.iter().skip(1) {
+            let body = json!({ "Container": id });
+            self.call("POST", &format!("/networks/{}/connect", encode(network)), Some(&body))?.bytes()?;
+        }
+        self.post(&format!("/containers/{}/start", encode(&id)))
+    }
+
+    fn update_container(&self, container: &str, cpu_period: Option<&str>, cpu_quota: Option<&str>,
+                        memory: Option<&str>, memory_swap: Option<&str>) -> Result<(), DockerError> {
+        let mut body = json!({});
+        for (field, value) in [("CpuPeriod", cpu_period), ("CpuQuota", cpu_quota)] {
+            if let Some(value) = value {
+                let number: i64 = value.parse().map_err(|_| format!("Invalid {} '{}': expected a whole number", field, value))?;
+                body[field] = json!(number);
+            }
+        }
+        // -1 is unlimited swap
+        for (field, value) in [("Memory", memory), ("MemorySwap", memory_swap)] {
+            if let Some(value) = value {
+                let bytes = match value.trim() {
+                    "-1" => -1,
+                    text => parse_memory(text).ok_or_else(|| format!("Invalid {} '{}': use e.g. 512m or 2g", field, value))? as i64,
+                };
+                body[field] = json!(bytes);
+            }
+        }
+        self.call("POST", &format!("/containers/{}/update", encode(container)), Some(&body))?.bytes().map(|_| ())
+    }
+
+    // ===== CLI FALLBACKS =====
+    // Operations that need a build context, registry credentials or a
+    // terminal are still delegated to the docker CLI.
+
+    fn attach_container(&self, name: &str) -> Result<(), DockerError> {
+        self.cli.attach_container(name)
+    }
+
+    fn exec_interactive(&self, name: &str, command: &[String], options: &ExecOptions) -> Result<i32, DockerError> {
+        self.cli.exec_interactive(name, command, options)
+    }
+
+    fn push_image(&self, name: &str) -> Result<ProgressStream, DockerError> {
+        self.cli.push_image(name)
+    }
+
+    fn build_image(&self, options: &BuildOptions) -> Result<BuildOutput, DockerError> {
+        self.cli.build_image(options)
+    }
+
+    fn import_image(&self, file: &str, repository: &str, tag: Option<&str>) -> Result<(), DockerError> {
+        self.cli.import_image(file, repository, tag)
+    }
+
+    fn load_image(&self, file: &str) -> Result<(), DockerError> {
+        self.cli.load_image(file)
+    }
+}
+
+/// How often `stream_stats` looks for containers that started since.
+const STATS_LISTING_INTERVAL: Duration = Duration::from_secs(2);
+
+// `stream_stats` over the API: one `stream=true` connection per running
+// container, each read on its own thread, and a fresh listing every few
+// seconds to pick up containers that started since. A snapshot goes out with
+// each sample once every container has reported.
+struct EngineStats {
+    client: EngineClient,
+    sender: Sender<(String, Option<ContainerStats>)>,
+    updates: Receiver<(String, Option<ContainerStats>)>,
+    // IDs with an open stream
+    streaming: HashSet<String>,
+    // By ID, in the order first reported
+    latest: Vec<(String, ContainerStats)>,
+    next_listing: Instant,
+}
+
+impl EngineStats {
+    fn follow_running(&mut self) -> Result<(), DockerError> {
+        for (id, name) in self.client.list_running()? {
+            if self.streaming.insert(id.clone()) {
+                self.client.follow_stats(id, name, self.sender.clone());
+            }
+        }
+        self.next_listing = Instant::now() + STATS_LISTING_INTERVAL;
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Vec<ContainerStats> {
+        self.latest.iter().map(|(_, stats)| stats.clone()).collect()
+    }
+}
+
+impl Iterator for EngineStats {
+    type Item = Vec<ContainerStats>;
+
+    fn next(&mut self) -> Option<Vec<ContainerStats>> {
+        loop {
+            if Instant::now() >= self.next_listing {
+                // A daemon that stopped answering ends the stream
+                self.follow_running().ok()?;
+                if self.streaming.is_empty() {
+                    return Some(Vec::new());
+                }
+            }
+            match self.updates.recv_timeout(self.next_listing.saturating_duration_since(Instant::now())) {
+                Ok((id, Some(stats))) => {
+                    match self.latest.iter_mut().find(|(seen, _)| *seen == id) {
+                        Some((_, latest)) => *latest = stats,
+                        None => self.latest.push((id, stats)),
+                    }
+                    if self.streaming.iter().all(|id| self.latest.iter().any(|(seen, _)| seen == id)) {
+                        return Some(self.snapshot());
+                    }
+                }
+                Ok((id, None)) => {
+                    self.streaming.remove(&id);
+                    self.latest.retain(|(seen, _)| *seen != id);
+                    return Some(self.snapshot());
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+// Go's os.FileMode bits in the archive stat header
+fn path_stat_from_json(json: &Value) -> PathStat {
+    const MODE_DIR: u64 = 1 << 31;
+    const MODE_SYMLINK: u64 = 1 << 27;
+    let mode = json["mode"].as_u64().unwrap_or(0);
+    let link_target = json["linkTarget"].as_str().filter(|target| !target.is_empty() && mode & MODE_SYMLINK != 0);
+    PathStat {
+        name: json["name"].as_str().unwrap_or("").to_string(),
+        size: json["size"].as_u64().unwrap_or(0),
+        is_dir: mode & MODE_DIR != 0,
+        link_target: link_target.map(str::to_string),
+    }
+}
+
+// Passes 2xx replies through and turns the rest into the daemon's error message
+fn check(response: Response) -> Result<Response, DockerError> {
+    if response.is_success() {
+        return Ok(response);
+    }
+    let status = response.status;
+    let data = response.bytes().unwrap_or_default();
+    let message = serde_json::from_slice::<Value>(&data)
+        .ok()
+        .and_then(|v| v.get("message").and_then(|m| m.as_str()).map(|m| m.to_string()))
+        .unwrap_or_else(|| String::from_utf8_lossy(&data).trim().to_string());
+    if message.is_empty() {
+        Err(format!("Docker API request failed with status {}", status).into())
+    } else {
+        Err(format!("Error response from daemon: {}", message).into())
+    }
+}
+
+/// Parses an HTTP/1.1 response head and frames the body that follows.
+pub fn read_response<R: BufRead + Send + 'static>(mut reader: R, head_only: bool) -> Result<Response, DockerError> {
+    let mut status_line = String::new();
+    reader
+        .read_line(&mut status_line)
+        .map_err(|e| format!("Failed to read response from Docker daemon: {}", e))?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| format!("Malformed response from Docker daemon: {:?}", status_line.trim()))?;
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read response headers: {}", e))?;
+        let line = line.trim_end();
+        if read == 0 || line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+
+    let body: Box<dyn Read + Send> = if head_only || status == 204 || status == 304 {
+        Box::new(io::empty())
+    } else if header("Transfer-Encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        Box::new(ChunkedReader::new(reader))
+    } else if let Some(length) = header("Content-Length").and_then(|v| v.parse::<u64>().ok()) {
+        Box::new(reader.take(length))
+    } else {
+        Box::new(reader)
+    };
+
+    Ok(Response { status, headers, body })
+}
+
+/// Splits Docker's multiplexed stdout/stderr stream (8-byte frame headers).
+/// Streams from TTY containers are raw and returned as stdout unchanged.
+pub fn demux_stream(data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    if !is_multiplexed(data) {
+        stdout.extend_from_slice(data);
+        return (stdout, stderr);
+    }
+
+    let mut offset = 0;
+    while offset + 8 <= data.len() {
+        let stream = data[offset];
+        let size = u32::from_be_bytes([data[offset + 4], data[offset + 5], data[offset + 6], data[offset + 7]]) as usize;
+        let start = offset + 8;
+        let end = (start + size).min(data.len());
+        if stream == 2 {
+            stderr.extend_from_slice(&data[start..end]);
+        } else {
+            stdout.extend_from_slice(&data[start..end]);
+        }
+        offset = end;
+    }
+    (stdout, stderr)
+}
+
+/// Reads a logs response frame by frame, so followed logs arrive as they are
+/// written. As with `demux_stream`, output from TTY containers is all stdout.
+struct LogFrames {
+    reader: BufReader<Box<dyn Read + Send>>,
+    multiplexed: Option<bool>,
+    timestamps: bool,
+    // Output per stream that hasn't reached a newline yet
+    partial: [Vec<u8>; 2],
+    ready: VecDeque<LogLine>,
+    done: bool,
+}
+
+impl LogFrames {
+    fn new(reader: Box<dyn Read + Send>, timestamps: bool) -> Self {
+        LogFrames {
+            reader: BufReader::new(reader),
+            multiplexed: None,
+            timestamps,
+            partial: [Vec::new(), Vec::new()],
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    // Appends the next frame to `partial`; false once the stream has ended
+    fn read_frame(&mut self) -> bool {
+        let multiplexed = match self.multiplexed {
+            Some(multiplexed) => multiplexed,
+            None => match self.reader.fill_buf() {
+                Ok(data) => *self.multiplexed.insert(is_multiplexed(data)),
+                Err(_) => return false,
+            },
+        };
+
+        if !multiplexed {
+            let mut buffer = [0; 8192];
+            return match self.reader.read(&mut buffer) {
+                Ok(0) | Err(_) => false,
+                Ok(read) => {
+                    self.partial[0].extend_from_slice(&buffer[..read]);
+                    true
+                }
+            };
+        }
+
+        let mut header = [0; 8];
+        if self.reader.read_exact(&mut header).is_err() {
+            return false;
+        }
+        let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let mut payload = vec![0; size];
+        if self.reader.read_exact(&mut payload).is_err() {
+            return false;
+        }
+        self.partial[usize::from(header[0] == 2)].extend_from_slice(&payload);
+        true
+    }
+
+    // Moves complete lines into `ready`, and at the end whatever is left
+    fn split_lines(&mut self) {
+        for (index, stream) in [LogStream::Stdout, LogStream::Stderr].into_iter().enumerate() {
+            let buffer = &mut self.partial[index];
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                self.ready.push_back(LogLine::parse(stream, &String::from_utf8_lossy(&line[..end]), self.timestamps));
+            }
+            if self.done && !buffer.is_empty() {
+                let line: Vec<u8> = std::mem::take(buffer);
+                self.ready.push_back(LogLine::parse(stream, &String::from_utf8_lossy(&line), self.timestamps));
+            }
+        }
+    }
+}
+
+impl Iterator for LogFrames {
+    type Item = LogLine;
+
+    fn next(&mut self) -> Option<LogLine> {
+        loop {
+            if let Some(line) = self.ready.pop_front() {
+                return Some(line);
+            }
+            if self.done {
+                return None;
+            }
+            self.done = !self.read_frame();
+            self.split_lines();
+        }
+    }
+}
+
+fn is_multiplexed(data: &[u8]) -> bool {
+    data.len() >= 8 && data[0] <= 2 && data[1..4] == [0, 0, 0]
+}
+
+fn container_from_json(json: &Value) -> Container {
+    Container {
+        id: container_id(json).unwrap_or_default(),
+        name: container_name(json).unwrap_or_default(),
+        image: str_field(json, "Image"),
+        status: str_field(json, "Status"),
+        ports: format_ports(json.get("Ports")),
+        labels: record_labels(json.get("Labels")),
+    }
+}
+
+// Renders the API port list the way `docker ps` does
+fn format_ports(ports: Option<&Value>) -> String {
+    let mut rendered: Vec<String> = ports
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .map(|port| {
+            let private = port.get("PrivatePort").and_then(|p| p.as_u64()).unwrap_or(0);
+            let protocol = port.get("Type").and_then(|t| t.as_str()).unwrap_or("tcp");
+            match port.get("PublicPort").and_then(|p| p.as_u64()) {
+                Some(public) => format!(
+                    "{}:{}->{}/{}",
+                    port.get("IP").and_then(|ip| ip.as_str()).unwrap_or("0.0.0.0"),
+                    public,
+                    private,
+                    protocol
+                ),
+                None => format!("{}/{}", private, protocol),
+            }
+        })
+        .collect();
+    rendered.sort();
+    rendered.dedup();
+    rendered.join(", ")
+}
+
+fn stats_from_json(name: &str, json: &Value) -> ContainerStats {
+    let number = |pointer: &str| json.pointer(pointer).and_then(|v| v.as_f64()).unwrap_or(0.0);
+
+    let cpu_delta = number("/cpu_stats/cpu_usage/total_usage") - number("/precpu_stats/cpu_usage/total_usage");
+    let system_delta = number("/cpu_stats/system_cpu_usage") - number("/precpu_stats/system_cpu_usage");
+    let online_cpus = match number("/cpu_stats/online_cpus") {
+        n if n > 0.0 => n,
+        _ => json
+            .pointer("/cpu_stats/cpu_usage/percpu_usage")
+            .and_then(|p| p.as_array())
+            .map(|p| p.len() as f64)
+            .unwrap_or(1.0),
+    };
+    // 1.0 means one core fully busy, matching the CPU % that `docker stats` prints
+    let cpu_fraction = if cpu_delta > 0.0 && system_delta > 0.0 {
+        cpu_delta / system_delta * online_cpus
+    } else {
+        0.0
+    };
+
+    // Page cache isn't counted as usage; the key differs between cgroup v1 and v2
+    let cache = json
+        .pointer("/memory_stats/stats/inactive_file")
+        .or_else(|| json.pointer("/memory_stats/stats/total_inactive_file"))
+        .or_else(|| json.pointer("/memory_stats/stats/cache"))
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+    let memory_used = (number("/memory_stats/usage") - cache).max(0.0);
+    let memory_limit = number("/memory_stats/limit");
+
+    let (mut rx, mut tx) = (0.0, 0.0);
+    if let Some(networks) = json.get("networks").and_then(|n| n.as_object()) {
+        for network in networks.values() {
+            rx += network.get("rx_bytes").and_then(|v| v.as_f64()).unwrap_or(0.0);
+            tx += network.get("tx_bytes").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        }
+    }
+
+    let (mut read, mut write) = (0.0, 0.0);
+    for entry in json
+        .pointer("/blkio_stats/io_service_bytes_recursive")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let value = entry.get("value").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        match entry.get("op").and_then(|o| o.as_str()).map(|o| o.to_ascii_lowercase()).as_deref() {
+            Some("read") => read += value,
+            Some("write") => write += value,
+            _ => {}
+        }
+    }
+
+    ContainerStats {
+        name: name.to_string(),
+        cpu_fraction,
+        online_cpus: online_cpus as u32,
+        memory_used: memory_used as u64,
+        memory_limit: memory_limit as u64,
+        net_rx: rx as u64,
+        net_tx: tx as u64,
+        block_read: read as u64,
+        block_write: write as u64,
+    }
+}
+
+fn str_field(json: &Value, key: &str) -> String {
+    json.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string()
+}
+
+/// Splits "repo:tag" into its parts, defaulting the tag to "latest".
+/// A colon belonging to a registry port ("host:5000/app") is not a tag.
+pub fn split_reference(reference: &str) -> (String, String) {
+    match reference.rfind(':') {
+        Some(i) if !reference[i..].contains('/') => (reference[..i].to_string(), reference[i + 1..].to_string()),
+        _ => (reference.to_string(), "latest".to_string()),
+    }
+}
+
+fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static SOCKET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    // Serves one canned HTTP response per connection and returns the request lines seen
+    fn serve(responses: Vec<String>) -> (EngineClient, thread::JoinHandle<Vec<String>>) {
+        let path = std::env::temp_dir().join(format!(
+            "dui-engine-{}-{}.sock",
+            std::process::id(),
+            SOCKET_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let client = EngineClient::new(Endpoint::Unix(path.clone()), CliBackend::default());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                requests.push(request_line.trim().to_string());
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            let _ = std::fs::remove_file(&path);
+            requests
+        });
+
+        (client, handle)
+    }
+
+    fn json_response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    #[test]
+    fn test_list_containers() {
+        let body = r#"[{"Id":"abc123","Names":["/web"],"Image":"nginx:latest","Status":"Up 2 hours",
+            "Ports":[{"IP":"0.0.0.0","PrivatePort":80,"PublicPort":8080,"Type":"tcp"},{"PrivatePort":443,"Type":"tcp"}]}]"#;
+        let (client, server) = serve(vec![json_response("200 OK", body)]);
+
+        let containers = client.list_containers().unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].name, "web");
+        assert_eq!(containers[0].image, "nginx:latest");
+        assert_eq!(containers[0].ports, "0.0.0.0:8080->80/tcp, 443/tcp");
+        assert_eq!(server.join().unwrap(), vec!["GET /containers/json?all=1 HTTP/1.1"]);
+    }
+
+    #[test]
+    fn test_chunked_response() {
+        let response = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n[{\"N\r\nf\r\name\":\"bridge\"}]\r\n0\r\n\r\n";
+        let (client, server) = serve(vec![response.to_string()]);
+
+        let networks = client.list_networks().unwrap();
+        assert_eq!(networks.len(), 1);
+        assert_eq!(networks[0].name, "bridge");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_error_message_from_daemon() {
+        let (client, server) = serve(vec![json_response("404 Not Found", r#"{"message":"No such container: ghost"}"#)]);
+
+        let err = client.start_container("ghost").unwrap_err();
+        assert_eq!(err, DockerError::NoSuchContainer("Error response from daemon: No such container: ghost".to_string()));
+        assert_eq!(server.join().unwrap(), vec!["POST /containers/ghost/start HTTP/1.1"]);
+    }
+
+    #[test]
+    fn test_create_container_over_the_api() {
+        let (client, server) = serve(vec![
+            json_response("201 Created", r#"{"Id":"4f1c2e","Warnings":[]}"#),
+            json_response("200 OK", ""),
+            "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+        ]);
+        let spec = ContainerSpec { networks: vec!["shop".to_string(), "metrics".to_string()], ..ContainerSpec::new("web", "nginx:1.25") };
+
+        client.create_container(&spec).unwrap();
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "POST /containers/create?name=web HTTP/1.1",
+                "POST /networks/metrics/connect HTTP/1.1",
+                "POST /containers/4f1c2e/start HTTP/1.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats_calculation() {
+        let stats = json!({
+            "cpu_stats": { "cpu_usage": { "total_usage": 400 }, "system_cpu_usage": 2000, "online_cpus": 2 },
+            "precpu_stats": { "cpu_usage": { "total_usage": 200 }, "system_cpu_usage": 1000 },
+            "memory_stats": { "usage": 2_097_152, "limit": 8_388_608, "stats": { "inactive_file": 1_048_576 } },
+            "networks": { "eth0": { "rx_bytes": 1500, "tx_bytes": 648 } },
+            "blkio_stats": { "io_service_bytes_recursive": [
+                { "op": "read", "value": 4096 }, { "op": "write", "value": 0 }
+            ] }
+        });
+
+        let stat = stats_from_json("web", &stats);
+        assert_eq!(stat.cpu_fraction, 0.4);
+        assert_eq!(stat.online_cpus, 2);
+        assert_eq!((stat.memory_used, stat.memory_limit), (1_048_576, 8_388_608));
+        assert_eq!((stat.net_rx, stat.net_tx), (1500, 648));
+        assert_eq!((stat.block_read, stat.block_write), (4096, 0));
+        assert_eq!(stat.cpu_text(), "40.00%");
+        assert_eq!(stat.memory_usage(), "1MiB / 8MiB");
+        assert_eq!(stat.memory_text(), "12.50%");
+        assert_eq!(stat.network_io(), "1.5kB / 648B");
+        assert_eq!(stat.block_io(), "4.1kB / 0B");
+    }
+
+    #[test]
+    fn test_demux_stream() {
+        let mut data = vec![1, 0, 0, 0, 0, 0, 0, 3];
+        data.extend_from_slice(b"out");
+        data.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 3]);
+        data.extend_from_slice(b"err");
+
+        let (stdout, stderr) = demux_stream(&data);
+        assert_eq!(stdout, b"out");
+        assert_eq!(stderr, b"err");
+        assert_eq!(demux_stream(b"plain tty output").0, b"plain tty output");
+    }
+
+    #[test]
+    fn test_log_frames_split_streams_and_lines() {
+        let mut data = vec![1, 0, 0, 0, 0, 0, 0, 9];
+        data.extend_from_slice(b"GET / 200");
+        data.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 8]);
+        data.extend_from_slice(b"timeout\n");
+        data.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 5]);
+        data.extend_from_slice(b"\nbye\n");
+
+        let lines: Vec<(LogStream, String)> = LogFrames::new(Box::new(io::Cursor::new(data)), false)
+            .map(|line| (line.stream, line.text))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (LogStream::Stderr, "timeout".to_string()),
+                (LogStream::Stdout, "GET / 200".to_string()),
+                (LogStream::Stdout, "bye".to_string()),
+            ]
+        );
+
+        let tty: Vec<String> = LogFrames::new(Box::new(io::Cursor::new(b"one\ntwo".to_vec())), false)
+            .map(|line| line.text)
+            .collect();
+        assert_eq!(tty, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn test_split_reference() {
+        assert_eq!(split_reference("nginx"), ("nginx".to_string(), "latest".to_string()));
+        assert_eq!(split_reference("nginx:1.25"), ("nginx".to_string(), "1.25".to_string()));
+        assert_eq!(split_reference("localhost:5000/app"), ("localhost:5000/app".to_string(), "latest".to_string()));
+    }
+}
@@ -0,0 +1,147 @@
+// Typed failures returned by every Docker backend.
+//
+// Each variant keeps the original message from the daemon or the docker CLI,
+// so nothing is lost when printing, and maps to a stable process exit code
+// that scripts can branch on.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DockerError {
+    NoSuchContainer(String),
+    DaemonUnreachable(String),
+    PermissionDenied(String),
+    NameConflict(String),
+    ImageNotFound(String),
+    Other(String),
+}
+
+impl DockerError {
+    /// Classifies a raw error message from the daemon or the docker CLI.
+    pub fn from_message(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let lower = message.to_lowercase();
+
+        if lower.contains("permission denied")
+            && (lower.contains("docker.sock") || lower.contains("docker daemon") || lower.contains("os error 13"))
+        {
+            DockerError::PermissionDenied(message)
+        } else if lower.contains("cannot connect to the docker daemon")
+            || lower.contains("is the docker daemon running")
+            || lower.contains("error during connect")
+            || lower.contains("docker daemon failed to start")
+            || lower.contains("failed to start docker daemon")
+        {
+            DockerError::DaemonUnreachable(message)
+        } else if lower.contains("no such container") {
+            DockerError::NoSuchContainer(message)
+        } else if lower.contains("is already in use") || lower.contains("conflict. the container name") {
+            DockerError::NameConflict(message)
+        } else if lower.contains("no such image")
+            || lower.contains("unable to find image")
+            || lower.contains("manifest unknown")
+            || lower.contains("repository does not exist")
+            || (lower.contains("manifest for") && lower.contains("not found"))
+        {
+            DockerError::ImageNotFound(message)
+        } else {
+            DockerError::Other(message)
+        }
+    }
+
+    /// The original stderr or daemon message.
+    pub fn message(&self) -> &str {
+        match self {
+            DockerError::NoSuchContainer(message)
+            | DockerError::DaemonUnreachable(message)
+            | DockerError::PermissionDenied(message)
+            | DockerError::NameConflict(message)
+            | DockerError::ImageNotFound(message)
+            | DockerError::Other(message) => message,
+        }
+    }
+
+    /// Process exit code for this kind of failure. 2 is left to clap's usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DockerError::Other(_) => 1,
+            DockerError::NoSuchContainer(_) => 3,
+            DockerError::DaemonUnreachable(_) => 4,
+            DockerError::PermissionDenied(_) => 5,
+            DockerError::NameConflict(_) => 6,
+            DockerError::ImageNotFound(_) => 7,
+        }
+    }
+
+    /// A targeted hint for fixing the failure, if there is one.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        match self {
+            DockerError::NoSuchContainer(_) => {
+                Some("Run 'dui containers list' to see the available container names and IDs")
+            }
+            DockerError::DaemonUnreachable(_) => {
+                Some("Start Docker Desktop or the docker service, or check DOCKER_HOST points at a running daemon")
+            }
+            DockerError::PermissionDenied(_) => {
+                Some("Add your user to the 'docker' group (sudo usermod -aG docker $USER) and log in again")
+            }
+            DockerError::NameConflict(_) => {
+                Some("Pick another name, or remove the existing container with 'dui containers remove <name>'")
+            }
+            DockerError::ImageNotFound(_) => {
+                Some("Check the image name and tag, or run 'dui images pull <image>' and log in for private registries")
+            }
+            DockerError::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for DockerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message().trim_end())
+    }
+}
+
+impl std::error::Error for DockerError {}
+
+impl From<String> for DockerError {
+    fn from(message: String) -> Self {
+        DockerError::from_message(message)
+    }
+}
+
+impl From<&str> for DockerError {
+    fn from(message: &str) -> Self {
+        DockerError::from_message(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_cli_and_daemon_messages() {
+        let cases = [
+            ("Error response from daemon: No such container: web", 3),
+            ("Cannot connect to the Docker daemon at unix:///var/run/docker.sock. Is the docker daemon running?", 4),
+            ("permission denied while trying to connect to the Docker daemon socket at unix:///var/run/docker.sock", 5),
+            ("Cannot connect to the Docker daemon at unix:///var/run/docker.sock: Permission denied (os error 13)", 5),
+            ("Error response from daemon: Conflict. The container name \"/web\" is already in use by container \"3f4e\".", 6),
+            ("Error response from daemon: pull access denied for nope, repository does not exist or may require 'docker login'", 7),
+            ("Error response from daemon: No such image: nginx:nope", 7),
+            ("OCI runtime exec failed: exec: \"bash\": permission denied", 1),
+        ];
+
+        for (message, code) in cases {
+            assert_eq!(DockerError::from(message).exit_code(), code, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_display_keeps_original_message() {
+        let error = DockerError::from("Error response from daemon: No such container: web\n".to_string());
+        assert_eq!(error.to_string(), "Error response from daemon: No such container: web");
+        assert!(error.suggestion().is_some());
+    }
+}
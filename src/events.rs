@@ -0,0 +1,256 @@
+// Typed daemon events and the filters applied to them.
+//
+// Every backend asks the daemon for JSON events (`/events`, `docker events
+// --format '{{json .}}'`, `podman events --format json`) and parses them into
+// `DockerEvent`. Filters are sent to the daemon where it supports them and are
+// checked again on the client, so every backend behaves the same.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use serde_json::Value;
+use crate::runtime::text_field;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DockerEvent {
+    /// Object type: container, image, network, volume, daemon, ...
+    pub kind: String,
+    /// What happened, e.g. "start", "die" or "health_status: healthy".
+    pub action: String,
+    pub actor: Actor,
+    /// Unix seconds.
+    pub time: i64,
+}
+
+/// The object an event is about.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Actor {
+    pub id: String,
+    /// Name, image, exit code and the container's labels.
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl DockerEvent {
+    /// Parses an event from the Engine API or the docker/podman CLI.
+    pub fn from_json(json: &Value) -> Option<DockerEvent> {
+        let kind = text_field(json, &["Type", "type"])?.to_lowercase();
+        let action = text_field(json, &["Action", "Status", "status"])?;
+
+        let mut attributes: BTreeMap<String, String> = json
+            .pointer("/Actor/Attributes")
+            .or_else(|| json.get("Attributes"))
+            .and_then(|attributes| attributes.as_object())
+            .map(|attributes| {
+                attributes
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Podman keeps the name and image at the top level
+        for (key, field) in [("name", "Name"), ("image", "Image")] {
+            if let Some(value) = text_field(json, &[field]).filter(|value| !value.is_empty()) {
+                attributes.entry(key.to_string()).or_insert(value);
+            }
+        }
+
+        let id = json
+            .pointer("/Actor/ID")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string())
+            .or_else(|| text_field(json, &["ID", "id"]))
+            .unwrap_or_default();
+        let time = match json.get("timeNano").and_then(|nanos| nanos.as_i64()) {
+            Some(nanos) => nanos / 1_000_000_000,
+            None => json.get("time").and_then(|secs| secs.as_i64()).unwrap_or(0),
+        };
+
+        Some(DockerEvent { kind, action, actor: Actor { id, attributes }, time })
+    }
+
+    /// The action without its detail, so "exec_start: sh -c ls" is "exec_start".
+    pub fn action_name(&self) -> &str {
+        self.action.split(':').next().unwrap_or("").trim()
+    }
+}
+
+// Like `docker events`: the short ID, then the attributes in brackets
+impl fmt::Display for Actor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(short_id(&self.id))?;
+        if !self.attributes.is_empty() {
+            let attributes: Vec<String> =
+                self.attributes.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+            write!(f, " ({})", attributes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Matches the `docker events` line format, minus the nanosecond timestamp
+impl fmt::Display for DockerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.kind, self.action, self.actor)
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    if id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        &id[..12]
+    } else {
+        id
+    }
+}
+
+/// Which events to deliver. Values of one kind are alternatives; different
+/// kinds must all match, as with `docker events --filter`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub types: Vec<String>,
+    pub actions: Vec<String>,
+    /// `key` or `key=value`.
+    pub labels: Vec<String>,
+    /// Replay events from this Unix time before following new ones.
+    pub since: Option<i64>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &DockerEvent) -> bool {
+        (self.types.is_empty() || self.types.iter().any(|kind| kind.eq_ignore_ascii_case(&event.kind)))
+            && (self.actions.is_empty() || self.actions.iter().any(|action| action == event.action_name()))
+            && self.labels.iter().all(|label| label_matches(label, &event.actor.attributes))
+            && self.since.map_or(true, |since| event.time >= since)
+    }
+
+    /// `filters` for the Engine API, or None when nothing is filtered server-side.
+    pub fn api_filters(&self) -> Option<Value> {
+        let mut filters = serde_json::Map::new();
+        for (key, values) in [("type", &self.types), ("event", &self.actions), ("label", &self.labels)] {
+            if !values.is_empty() {
+                filters.insert(key.to_string(), Value::from(values.clone()));
+            }
+        }
+        if filters.is_empty() {
+            None
+        } else {
+            Some(Value::Object(filters))
+        }
+    }
+
+    /// `--since` and `--filter` arguments for `docker events`.
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(since) = self.since {
+            args.push("--since".to_string());
+            args.push(since.to_string());
+        }
+        for (key, values) in [("type", &self.types), ("event", &self.actions), ("label", &self.labels)] {
+            for value in values {
+                args.push("--filter".to_string());
+                args.push(format!("{}={}", key, value));
+            }
+        }
+        args
+    }
+}
+
+/// Whether `labels` satisfy a `key` or `key=value` selector.
+pub fn label_matches(selector: &str, labels: &BTreeMap<String, String>) -> bool {
+    match selector.split_once('=') {
+        Some((key, value)) => labels.get(key).is_some_and(|actual| actual == value),
+        None => labels.contains_key(selector),
+    }
+}
+
+/// Resolves `--since` (or `logs --until`) to Unix seconds: either a timestamp or a duration
+/// before `now` such as "45s", "10m" or "1h30m".
+pub fn parse_since(text: &str, now: i64) -> Result<i64, String> {
+    let text = text.trim();
+    let invalid = || format!("Invalid time '{}': use a Unix timestamp or a duration like 10m or 1h30m", text);
+    if let Ok(timestamp) = text.parse::<i64>() {
+        return Ok(timestamp);
+    }
+
+    let mut seconds = 0i64;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            _ => return Err(invalid()),
+        };
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        seconds += value * unit;
+        number.clear();
+    }
+    if !number.is_empty() || text.is_empty() {
+        return Err(invalid());
+    }
+    Ok(now - seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn die_event() -> DockerEvent {
+        DockerEvent::from_json(&json!({
+            "Type": "container",
+            "Action": "die",
+            "Actor": {
+                "ID": "3f4e5d6c7b8a9f0e1d2c3b4a5f6e7d8c9b0a1f2e3d4c5b6a7f8e9d0c1b2a3f4e",
+                "Attributes": {"app": "api", "exitCode": "137", "image": "nginx:1.25", "name": "web"}
+            },
+            "time": 1_704_164_645,
+            "timeNano": 1_704_164_645_123_456_789i64
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_engine_and_podman_events() {
+        let event = die_event();
+        assert_eq!(event.time, 1_704_164_645);
+        assert_eq!(event.to_string(), "container die 3f4e5d6c7b8a (app=api, exitCode=137, image=nginx:1.25, name=web)");
+
+        let podman = DockerEvent::from_json(&json!({
+            "ID": "3f4e5d6c7b8a", "Image": "nginx:1.25", "Name": "web", "Status": "start",
+            "Time": "2024-01-02T03:04:05Z", "Type": "container", "Attributes": {"app": "api"}
+        }))
+        .unwrap();
+        assert_eq!(podman.action, "start");
+        assert_eq!(podman.actor.attributes.get("image").map(String::as_str), Some("nginx:1.25"));
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let event = die_event();
+        let filter = |types: &[&str], actions: &[&str], labels: &[&str]| EventFilter {
+            types: types.iter().map(|s| s.to_string()).collect(),
+            actions: actions.iter().map(|s| s.to_string()).collect(),
+            labels: labels.iter().map(|s| s.to_string()).collect(),
+            since: None,
+        };
+        assert!(filter(&[], &[], &[]).matches(&event));
+        assert!(filter(&["container"], &["start", "die"], &["app=api", "exitCode"]).matches(&event));
+        assert!(!filter(&["image"], &[], &[]).matches(&event));
+        assert!(!filter(&[], &[], &["app=web"]).matches(&event));
+        assert!(!EventFilter { since: Some(1_704_164_646), ..Default::default() }.matches(&event));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("10m", 1_000), Ok(400));
+        assert_eq!(parse_since("1h30m", 10_000), Ok(4_600));
+        assert_eq!(parse_since("1704164645", 0), Ok(1_704_164_645));
+        assert!(parse_since("10", 0).is_ok());
+        assert!(parse_since("ten minutes", 0).is_err());
+        assert!(parse_since("5m3", 0).is_err());
+    }
+}
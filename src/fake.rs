@@ -0,0 +1,918 @@
+// In-memory backend for tests and demos.
+//
+// Seed it with containers, images and stats, then drive menus and charts
+// without a daemon. State changes (start, stop, rename, ...) are applied to
+// the seeded data and every call is recorded for assertions.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::backend::{Archive, BuildOutput, DockerBackend, EventStream, LogLines, ProgressStream, StatsStream};
+use crate::copy::PathStat;
+use crate::disk::{self, DiskUsage, Reclaim, UsageItem, UsageKind};
+use crate::docker::{BuildOptions, Container, ContainerProcess, ContainerSpec, ContainerStats, ExecOptions, Image, Network, Volume};
+use crate::error::DockerError;
+use crate::events::{Actor, DockerEvent, EventFilter};
+use crate::logs::{LogLine, LogOptions, LogStream};
+use crate::progress::ProgressMessage;
+use crate::tar::{self, Entry, EntryKind, TarReader};
+use crate::utils::parse_size;
+
+/// Size of the layer a fake pull or push transfers.
+const FAKE_LAYER_SIZE: u64 = 3 * 1024 * 1024;
+
+#[derive(Default)]
+struct FakeState {
+    containers: Vec<Container>,
+    images: Vec<Image>,
+    stats: Vec<ContainerStats>,
+    networks: Vec<Network>,
+    volumes: Vec<Volume>,
+    events: Vec<DockerEvent>,
+    logs: HashMap<String, Vec<LogLine>>,
+    // Per container, absolute path -> contents; None for directories
+    files: HashMap<String, BTreeMap<String, Option<Vec<u8>>>>,
+    exec_results: HashMap<String, Result<String, String>>,
+    // "repository:tag" -> "repository@digest" entries
+    repo_digests: HashMap<String, Vec<String>>,
+    calls: Vec<String>,
+}
+
+impl FakeState {
+    // Adds `path`, with None for a directory, and any missing parents
+    fn put_file(&mut self, container: &str, path: &str, contents: Option<Vec<u8>>) {
+        let files = self.files.entry(container.to_string()).or_default();
+        let path = normalize_path(path);
+        let mut dir = path.as_str();
+        while let Some((parent, _)) = dir.rsplit_once('/') {
+            if parent.is_empty() {
+                break;
+            }
+            files.entry(parent.to_string()).or_insert(None);
+            dir = parent;
+        }
+        files.insert(path, contents);
+    }
+
+    // By "repository:tag" (":latest" implied) or ID
+    fn image(&self, name: &str) -> Option<&Image> {
+        let (repository, tag) = split_name(name);
+        self.images.iter().find(|i| (i.repository == repository && i.tag == tag) || i.id == name)
+    }
+
+    // None when nothing is at `path`; Some(None) for a directory
+    fn file(&self, container: &str, path: &str) -> Option<Option<&Vec<u8>>> {
+        if path == "/" {
+            return Some(None);
+        }
+        self.files.get(container)?.get(path).map(Option::as_ref)
+    }
+}
+
+// A tar of regular files, for fake image archives
+pub fn tar_of(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (path, contents) in files {
+        let entry = Entry { path: path.to_string(), kind: EntryKind::File, size: contents.len() as u64, mode: 0o644, mtime: 0, link: None };
+        archive.extend(entry.header());
+        archive.extend_from_slice(contents);
+        archive.extend(vec![0; tar::padding(entry.size)]);
+    }
+    archive.extend(tar::end_of_archive());
+    archive
+}
+
+// "nginx:1.25" -> ("nginx", "1.25"); a registry port isn't a tag
+fn split_name(name: &str) -> (String, String) {
+    match name.rsplit_once(':') {
+        Some((repo, tag)) if !tag.contains('/') => (repo.to_string(), tag.to_string()),
+        _ => (name.to_string(), "latest".to_string()),
+    }
+}
+
+// "/etc/nginx/" and "etc/nginx" are both "/etc/nginx"
+fn normalize_path(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+#[derive(Default)]
+pub struct FakeBackend {
+    state: Mutex<FakeState>,
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        FakeBackend::default()
+    }
+
+    /// A small, stable data set for `dui --demo`.
+    pub fn demo() -> Self {
+        FakeBackend::new()
+            .with_container(Container {
+                id: "3f4e5d6c7b8a9f0e1d2c3b4a".to_string(),
+                name: "web".to_string(),
+                image: "nginx:1.25".to_string(),
+                status: "Up 3 hours".to_string(),
+                ports: "0.0.0.0:8080->80/tcp".to_string(),
+                labels: demo_labels(&[("app", "shop"), ("tier", "front")]),
+            })
+            .with_container(Container {
+                id: "a1b2c3d4e5f6a7b8c9d0e1f2".to_string(),
+                name: "db".to_string(),
+                image: "postgres:16".to_string(),
+                status: "Up 3 hours".to_string(),
+                ports: "5432/tcp".to_string(),
+                labels: demo_labels(&[("app", "shop"), ("tier", "data")]),
+            })
+            .with_container(Container {
+                id: "0f9e8d7c6b5a4f3e2d1c0b9a".to_string(),
+                name: "worker".to_string(),
+                image: "myapp:latest".to_string(),
+                status: "Exited (0) 2 hours ago".to_string(),
+                ports: String::new(),
+                labels: demo_labels(&[("app", "reports")]),
+            })
+            .with_image(Image {
+                id: "a8758716bb6a".to_string(),
+                repository: "nginx".to_string(),
+                tag: "1.25".to_string(),
+                size: "187MB".to_string(),
+                created: "2024-01-02 03:04:05 +0000 UTC".to_string(),
+            })
+            .with_image(Image {
+                id: "d2c94e258dcb".to_string(),
+                repository: "postgres".to_string(),
+                tag: "16".to_string(),
+                size: "431MB".to_string(),
+                created: "2024-01-10 12:00:00 +0000 UTC".to_string(),
+            })
+            // Left behind by a rebuild of myapp
+            .with_image(Image {
+                id: "5e1d2c3b4a59".to_string(),
+                repository: "<none>".to_string(),
+                tag: "<none>".to_string(),
+                size: "96.5MB".to_string(),
+                created: "2023-12-20 09:30:00 +0000 UTC".to_string(),
+            })
+            .with_repo_digest("nginx:1.25", "nginx@sha256:a484819eb60211f5299034ac80f6a681b06f89e65866ce91f356ed7c72af059c")
+            .with_repo_digest("postgres:16", "postgres@sha256:d2c94e258dcb3c5ac2798d32e1249e42ef01cba4841c2234249495f87264ac5a")
+            .with_stats(ContainerStats {
+                name: "web".to_string(),
+                cpu_fraction: 0.125,
+                online_cpus: 4,
+                memory_used: 25_690_112,
+                memory_limit: 2_087_354_368,
+                net_rx: 1_200_000,
+                net_tx: 3_400_000,
+                block_read: 8_192,
+                block_write: 0,
+            })
+            // Busier than one core, as a multi-threaded database often is
+            .with_stats(ContainerStats {
+                name: "db".to_string(),
+                cpu_fraction: 1.631,
+                online_cpus: 4,
+                memory_used: 536_870_912,
+                memory_limit: 2_087_354_368,
+                net_rx: 640_000,
+                net_tx: 1_100_000,
+                block_read: 120_000_000,
+                block_write: 48_000_000,
+            })
+            .with_network(Network {
+                id: "9c1f0c2b7e6d".to_string(),
+                name: "bridge".to_string(),
+                driver: "bridge".to_string(),
+                scope: "local".to_string(),
+            })
+            .with_network(Network {
+                id: "4d3c2b1a0f9e".to_string(),
+                name: "legacy_net".to_string(),
+                driver: "bridge".to_string(),
+                scope: "local".to_string(),
+            })
+            .with_volume(Volume {
+                name: "pgdata".to_string(),
+                driver: "local".to_string(),
+                mountpoint: "/var/lib/docker/volumes/pgdata/_data".to_string(),
+            })
+            .with_volume(Volume {
+                name: "old_reports".to_string(),
+                driver: "local".to_string(),
+                mountpoint: "/var/lib/docker/volumes/old_reports/_data".to_string(),
+            })
+            .with_logs("web", LogStream::Stdout, "2024-01-02T03:04:05.1Z GET / 200\n2024-01-02T03:04:07.25Z GET /health 200\n")
+            .with_logs("web", LogStream::Stderr, "2024-01-02T03:04:08.5Z upstream timed out while reading response header\n")
+            .with_logs("db", LogStream::Stdout, "2024-01-02T03:04:06.3Z database system is ready to accept connections\n")
+            .with_logs(
+                "worker",
+                LogStream::Stdout,
+                concat!(
+                    "2024-01-02T01:00:00Z starting report worker\n",
+                    "2024-01-02T01:00:01Z {\"level\":\"info\",\"msg\":\"report generated\",\"report_id\":17,\"user_id\":42}\n",
+                    "2024-01-02T01:00:02Z {\"level\":\"error\",\"msg\":\"smtp send failed\",\"report_id\":18,\"user_id\":42}\n",
+                ),
+            )
+            .with_logs("db", LogStream::Stderr, "2024-01-02T03:04:07.9Z FATAL:  password authentication failed for user \"shop\"\n")
+            .with_file("web", "/etc/nginx/nginx.conf", "user nginx;\nworker_processes auto;\ninclude /etc/nginx/conf.d/*.conf;\n")
+            .with_file("web", "/etc/nginx/conf.d/default.conf", "server {\n    listen 80;\n    root /usr/share/nginx/html;\n}\n")
+            .with_file("web", "/usr/share/nginx/html/index.html", "<h1>Welcome to nginx!</h1>\n")
+            .with_dir("web", "/tmp")
+            .with_event(demo_event("start", "3f4e5d6c7b8a", &[("app", "shop"), ("image", "nginx:1.25"), ("name", "web")], 10_800))
+            .with_event(demo_event("die", "0f9e8d7c6b5a", &[("exitCode", "0"), ("image", "myapp:latest"), ("name", "worker")], 7_200))
+    }
+
+    pub fn with_container(self, container: Container) -> Self {
+        self.lock().containers.push(container);
+        self
+    }
+
+    pub fn with_image(self, image: Image) -> Self {
+        self.lock().images.push(image);
+        self
+    }
+
+    pub fn with_stats(self, stats: ContainerStats) -> Self {
+        self.lock().stats.push(stats);
+        self
+    }
+
+    pub fn with_network(self, network: Network) -> Self {
+        self.lock().networks.push(network);
+        self
+    }
+
+    pub fn with_volume(self, volume: Volume) -> Self {
+        self.lock().volumes.push(volume);
+        self
+    }
+
+    /// Appends `logs` to what `container` has written to `stream`. Each line
+    /// starts with its timestamp, as `docker logs --timestamps` prints them.
+    pub fn with_logs(self, container: &str, stream: LogStream, logs: &str) -> Self {
+        self.lock()
+            .logs
+            .entry(container.to_string())
+            .or_default()
+            .extend(logs.lines().map(|line| LogLine::parse(stream, line, true)));
+        self
+    }
+
+    /// Puts a file in `container`'s filesystem, creating its parent directories.
+    pub fn with_file(self, container: &str, path: &str, contents: &str) -> Self {
+        self.lock().put_file(container, path, Some(contents.as_bytes().to_vec()));
+        self
+    }
+
+    /// Creates a directory, and its parents, in `container`'s filesystem.
+    pub fn with_dir(self, container: &str, path: &str) -> Self {
+        self.lock().put_file(container, path, None);
+        self
+    }
+
+    /// What `exec_container` prints in `container`; empty by default.
+    #[cfg(test)]
+    pub fn with_exec_output(self, container: &str, output: &str) -> Self {
+        self.lock().exec_results.insert(container.to_string(), Ok(output.to_string()));
+        self
+    }
+
+    /// Makes `exec_container` in `container` fail with the runtime's `message`.
+    #[cfg(test)]
+    pub fn with_exec_error(self, container: &str, message: &str) -> Self {
+        self.lock().exec_results.insert(container.to_string(), Err(message.to_string()));
+        self
+    }
+
+    /// Records that `image` ("repository:tag") was pulled as `repo_digest`.
+    pub fn with_repo_digest(self, image: &str, repo_digest: &str) -> Self {
+        self.lock().repo_digests.entry(image.to_string()).or_default().push(repo_digest.to_string());
+        self
+    }
+
+    pub fn with_event(self, event: DockerEvent) -> Self {
+        self.lock().events.push(event);
+        self
+    }
+
+    /// Every operation performed so far, e.g. "start web".
+    #[cfg(test)]
+    pub fn calls(&self) -> Vec<String> {
+        self.lock().calls.clone()
+    }
+
+    #[cfg(test)]
+    pub fn containers(&self) -> Vec<Container> {
+        self.lock().containers.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn record(&self, call: String) {
+        self.lock().calls.push(call);
+    }
+
+    // Applies `update` to the named container, mirroring the daemon's error otherwise
+    fn with_container_mut<T>(&self, name: &str, update: impl FnOnce(&mut Container) -> T) -> Result<T, DockerError> {
+        let mut state = self.lock();
+        match state.containers.iter_mut().find(|c| c.name == name || c.id.starts_with(name)) {
+            Some(container) => Ok(update(container)),
+            None => Err(format!("Error response from daemon: No such container: {}", name).into()),
+        }
+    }
+
+    fn set_status(&self, action: &str, name: &str, status: &str) -> Result<(), DockerError> {
+        self.record(format!("{} {}", action, name));
+        self.with_container_mut(name, |c| c.status = status.to_string())
+    }
+}
+
+impl DockerBackend for FakeBackend {
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn ping(&self) -> bool {
+        true
+    }
+
+    fn list_containers(&self) -> Result<Vec<Container>, DockerError> {
+        Ok(self.lock().containers.clone())
+    }
+
+    fn create_container(&self, spec: &ContainerSpec) -> Result<(), DockerError> {
+        self.record(format!("create {} {}", spec.name, spec.image));
+        spec.validate()?;
+        let mut state = self.lock();
+        if state.containers.iter().any(|c| c.name == spec.name) {
+            return Err(format!(
+                "Error response from daemon: Conflict. The container name \"/{}\" is already in use",
+                spec.name
+            )
+            .into());
+        }
+        let id = format!("{:024x}", state.containers.len() + 1);
+        state.containers.push(Container {
+            id,
+            name: spec.name.clone(),
+            image: spec.image.clone(),
+            status: "Up Less than a second".to_string(),
+            ports: spec.ports.join(", "),
+            labels: spec
+                .labels
+                .iter()
+                .map(|label| match label.split_once('=') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => (label.clone(), String::new()),
+                })
+                .collect(),
+        });
+        Ok(())
+    }
+
+    fn start_container(&self, name: &str) -> Result<(), DockerError> {
+        self.set_status("start", name, "Up Less than a second")
+    }
+
+    fn stop_container(&self, name: &str) -> Result<(), DockerError> {
+        self.set_status("stop", name, "Exited (0) Less than a second ago")
+    }
+
+    fn restart_container(&self, name: &str) -> Result<(), DockerError> {
+        self.set_status("restart", name, "Up Less than a second")
+    }
+
+    fn pause_container(&self, name: &str) -> Result<(), DockerError> {
+        self.set_status("pause", name, "Up Less than a second (Paused)")
+    }
+
+    fn unpause_container(&self, name: &str) -> Result<(), DockerError> {
+        self.set_status("unpause", name, "Up Less than a second")
+    }
+
+    fn remove_container(&self, name: &str) -> Result<(), DockerError> {
+        self.record(format!("remove {}", name));
+        self.with_container_mut(name, |_| ())?;
+        self.lock().containers.retain(|c| c.name != name && !c.id.starts_with(name));
+        Ok(())
+    }
+
+    fn kill_container(&self, container: &str, signal: Option<&str>) -> Result<(), DockerError> {
+        self.record(format!("kill {} {}", container, signal.unwrap_or("SIGKILL")));
+        self.with_container_mut(container, |c| c.status = "Exited (137) Less than a second ago".to_string())
+    }
+
+    fn rename_container(&self, old_name: &str, new_name: &str) -> Result<(), DockerError> {
+        self.record(format!("rename {} {}", old_name, new_name));
+        self.with_container_mut(old_name, |c| c.name = new_name.to_string())
+    }
+
+    fn attach_container(&self, name: &str) -> Result<(), DockerError> {
+        self.record(format!("attach {}", name));
+        self.with_container_mut(name, |_| ())
+    }
+
+    fn exec_container(&self, name: &str, command: &str, _options: &ExecOptions) -> Result<String, DockerError> {
+        self.record(format!("exec {} {}", name, command));
+        self.with_container_mut(name, |_| ())?;
+        match self.lock().exec_results.get(name) {
+            Some(Ok(output)) => Ok(output.clone()),
+            Some(Err(message)) => Err(message.as_str().into()),
+            None => Ok(String::new()),
+        }
+    }
+
+    // `false` fails like it would in a real container
+    fn exec_interactive(&self, name: &str, command: &[String], _options: &ExecOptions) -> Result<i32, DockerError> {
+        self.record(format!("exec -it {} {}", name, command.join(" ")));
+        self.with_container_mut(name, |_| ())?;
+        Ok(if command.first().map(String::as_str) == Some("false") { 1 } else { 0 })
+    }
+
+    fn commit_container(&self, container: &str, repository: &str, tag: Option<&str>) -> Result<(), DockerError> {
+        self.record(format!("commit {} {}", container, repository));
+        self.with_container_mut(container, |_| ())?;
+        self.lock().images.push(Image {
+            id: format!("{:012x}", repository.len()),
+            repository: repository.to_string(),
+            tag: tag.unwrap_or("latest").to_string(),
+            size: "0B".to_string(),
+            created: String::new(),
+        });
+        Ok(())
+    }
+
+    fn copy_from_container(&self, container: &str, path: &str) -> Result<Archive, DockerError> {
+        self.record(format!("cp {}:{} -", container, path));
+        let name = self.with_container_mut(container, |c| c.name.clone())?;
+        let path = normalize_path(path);
+        let state = self.lock();
+        if state.file(&name, &path).is_none() {
+            return Err(format!("Error response from daemon: Could not find the file {} in container {}", path, container).into());
+        }
+
+        let root = path.rsplit('/').next().filter(|root| !root.is_empty()).unwrap_or(".");
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let mut archive = Vec::new();
+        for (file, contents) in state.files.get(&name).into_iter().flatten() {
+            let name = match file.strip_prefix(&prefix) {
+                Some(rest) => format!("{}/{}", root, rest),
+                None if *file == path => root.to_string(),
+                None => continue,
+            };
+            let entry = match contents {
+                Some(data) => Entry { path: name, kind: EntryKind::File, size: data.len() as u64, mode: 0o644, mtime: 0, link: None },
+                None => Entry { path: format!("{}/", name), kind: EntryKind::Directory, size: 0, mode: 0o755, mtime: 0, link: None },
+            };
+            archive.extend(entry.header());
+            if let Some(data) = contents {
+                archive.extend_from_slice(data);
+                archive.extend(vec![0; tar::padding(entry.size)]);
+            }
+        }
+        archive.extend(tar::end_of_archive());
+        Ok(Box::new(io::Cursor::new(archive)))
+    }
+
+    fn copy_to_container(&self, container: &str, dir: &str, archive: &mut dyn Read) -> Result<(), DockerError> {
+        self.record(format!("cp - {}:{}", container, dir));
+        let name = self.with_container_mut(container, |c| c.name.clone())?;
+        let dir = normalize_path(dir);
+        if !matches!(self.lock().file(&name, &dir), Some(None)) {
+            return Err(format!("Error response from daemon: Could not find the file {} in container {}", dir, container).into());
+        }
+
+        let mut reader = TarReader::new(&mut *archive);
+        while let Some(entry) = reader.next_entry().map_err(|e| e.to_string())? {
+            let path = format!("{}/{}", dir.trim_end_matches('/'), entry.path.trim_end_matches('/'));
+            match entry.kind {
+                EntryKind::Directory => self.lock().put_file(&name, &path, None),
+                EntryKind::File => {
+                    let mut data = Vec::new();
+                    reader.read_to_end(&mut data).map_err(|e| e.to_string())?;
+                    self.lock().put_file(&name, &path, Some(data));
+                }
+                _ => {}
+            }
+        }
+        io::copy(archive, &mut io::sink()).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn stat_container_path(&self, container: &str, path: &str) -> Result<Option<PathStat>, DockerError> {
+        let name = self.with_container_mut(container, |c| c.name.clone())?;
+        let path = normalize_path(path);
+        Ok(self.lock().file(&name, &path).map(|contents| PathStat {
+            name: path.rsplit('/').next().unwrap_or("").to_string(),
+            size: contents.map_or(4096, |data| data.len() as u64),
+            is_dir: contents.is_none(),
+            link_target: None,
+        }))
+    }
+
+    fn diff_container(&self, container: &str) -> Result<String, DockerError> {
+        self.with_container_mut(container, |_| String::new())
+    }
+
+    fn export_container(&self, container: &str, output_file: &str) -> Result<(), DockerError> {
+        self.record(format!("export {} {}", container, output_file));
+        self.with_container_mut(container, |_| ())
+    }
+
+    fn get_container_ports(&self, container: &str) -> Result<String, DockerError> {
+        self.with_container_mut(container, |c| c.ports.clone())
+    }
+
+    fn get_container_processes(&self, container: &str) -> Result<Vec<ContainerProcess>, DockerError> {
+        self.with_container_mut(container, |_| Vec::new())
+    }
+
+    fn update_container(&self, container: &str, _cpu_period: Option<&str>, _cpu_quota: Option<&str>,
+                        _memory: Option<&str>, _memory_swap: Option<&str>) -> Result<(), DockerError> {
+        self.record(format!("update {}", container));
+        self.with_container_mut(container, |_| ())
+    }
+
+    fn wait_for_container(&self, container: &str) -> Result<String, DockerError> {
+        self.with_container_mut(container, |_| "0".to_string())
+    }
+
+    fn inspect_container(&self, name: &str) -> Result<String, DockerError> {
+        let container = self.with_container_mut(name, |c| c.clone())?;
+        // Image is the ID the container runs, whatever its tag points to now
+        let image = self.lock().image(&container.image).map_or(container.image.clone(), |image| image.id.clone());
+        Ok(serde_json::json!([{
+            "Id": container.id,
+            "Name": format!("/{}", container.name),
+            "Image": image,
+            "Config": { "Image": container.image, "Labels": container.labels },
+        }])
+        .to_string())
+    }
+
+    fn get_container_size(&self, name: &str) -> Result<String, DockerError> {
+        self.with_container_mut(name, |_| "0 B".to_string())
+    }
+
+    fn get_container_logs(&self, name: &str, options: &LogOptions) -> Result<LogLines, DockerError> {
+        let container = self.with_container_mut(name, |c| c.name.clone())?;
+        let mut lines = self.lock().logs.get(&container).cloned().unwrap_or_default();
+        lines.sort_by(|a, b| a.time_key().cmp(&b.time_key()));
+        let skip = options.tail.map_or(0, |tail| lines.len().saturating_sub(tail));
+        let timestamps = options.timestamps;
+        Ok(Box::new(lines.into_iter().skip(skip).map(move |line| LogLine {
+            timestamp: line.timestamp.filter(|_| timestamps),
+            ..line
+        })))
+    }
+
+    fn get_container_stats(&self) -> Result<Vec<ContainerStats>, DockerError> {
+        Ok(self.lock().stats.clone())
+    }
+
+    // The same sample again every second, like a daemon that samples that often
+    fn stream_stats(&self) -> Result<StatsStream, DockerError> {
+        let stats = self.lock().stats.clone();
+        let again = stats.clone();
+        Ok(Box::new(std::iter::once(stats).chain(std::iter::repeat_with(move || {
+            thread::sleep(Duration::from_secs(1));
+            again.clone()
+        }))))
+    }
+
+    fn list_images(&self) -> Result<Vec<Image>, DockerError> {
+        Ok(self.lock().images.clone())
+    }
+
+    fn image_exists(&self, name: &str) -> Result<bool, DockerError> {
+        Ok(self.lock().image(name).is_some())
+    }
+
+    fn image_repo_digests(&self, name: &str) -> Result<Vec<String>, DockerError> {
+        let state = self.lock();
+        match state.image(name) {
+            Some(image) => Ok(state.repo_digests.get(&format!("{}:{}", image.repository, image.tag)).cloned().unwrap_or_default()),
+            None => Err(format!("Error response from daemon: No such image: {}", name).into()),
+        }
+    }
+
+    fn inspect_image(&self, name: &str) -> Result<String, DockerError> {
+        let digests = self.image_repo_digests(name)?;
+        let state = self.lock();
+        let image = state.image(name).expect("checked above");
+        Ok(serde_json::json!([{
+            "Id": image.id,
+            "RepoTags": [format!("{}:{}", image.repository, image.tag)],
+            "RepoDigests": digests,
+            "Config": {},
+        }])
+        .to_string())
+    }
+
+    fn pull_image(&self, name: &str) -> Result<ProgressStream, DockerError> {
+        self.record(format!("pull {}", name));
+        let (repository, tag) = split_name(name);
+        let mut state = self.lock();
+        if !state.images.iter().any(|i| i.repository == repository && i.tag == tag) {
+            let id = format!("{:012x}", state.images.len() + 1);
+            state.images.push(Image {
+                id,
+                repository,
+                tag: tag.clone(),
+                size: "0B".to_string(),
+                created: String::new(),
+            });
+        }
+
+        // One shared base layer and one that has to be fetched
+        let sized = |id: &str, status: &str, current: u64| ProgressMessage { current: Some(current), total: Some(FAKE_LAYER_SIZE), ..ProgressMessage::layer(id, status) };
+        let messages = vec![
+            ProgressMessage::layer(&tag, "Pulling from fake"),
+            ProgressMessage::layer("a1b2c3d4e5f6", "Already exists"),
+            ProgressMessage::layer("0f9e8d7c6b5a", "Pulling fs layer"),
+            sized("0f9e8d7c6b5a", "Downloading", FAKE_LAYER_SIZE / 2),
+            sized("0f9e8d7c6b5a", "Downloading", FAKE_LAYER_SIZE),
+            ProgressMessage::layer("0f9e8d7c6b5a", "Download complete"),
+            sized("0f9e8d7c6b5a", "Extracting", FAKE_LAYER_SIZE),
+            ProgressMessage::layer("0f9e8d7c6b5a", "Pull complete"),
+            ProgressMessage::note(&format!("Status: Downloaded newer image for {}", name)),
+        ];
+        Ok(Box::new(messages.into_iter()))
+    }
+
+    fn push_image(&self, name: &str) -> Result<ProgressStream, DockerError> {
+        self.record(format!("push {}", name));
+        let sized = |status: &str, current: u64| ProgressMessage { current: Some(current), total: Some(FAKE_LAYER_SIZE), ..ProgressMessage::layer("0f9e8d7c6b5a", status) };
+        let messages = vec![
+            ProgressMessage::layer("a1b2c3d4e5f6", "Preparing"),
+            ProgressMessage::layer("0f9e8d7c6b5a", "Preparing"),
+            ProgressMessage::layer("a1b2c3d4e5f6", "Layer already exists"),
+            sized("Pushing", FAKE_LAYER_SIZE / 2),
+            sized("Pushing", FAKE_LAYER_SIZE),
+            ProgressMessage::layer("0f9e8d7c6b5a", "Pushed"),
+        ];
+        Ok(Box::new(messages.into_iter()))
+    }
+
+    fn build_image(&self, options: &BuildOptions) -> Result<BuildOutput, DockerError> {
+        self.record(format!("build {} {}", options.context, options.tags.join(" ")));
+        options.validate()?;
+        let mut state = self.lock();
+        for tag in &options.tags {
+            let (repository, tag) = split_name(tag);
+            if !state.images.iter().any(|i| i.repository == repository && i.tag == tag) {
+                let id = format!("{:012x}", state.images.len() + 1);
+                state.images.push(Image { id, repository, tag, size: "0B".to_string(), created: String::new() });
+            }
+        }
+
+        // Dependencies come from the cache, the changed sources are rebuilt
+        let cache = if options.no_cache { "#5 DONE 2.1s" } else { "#5 CACHED" };
+        let lines = [
+            "#1 [internal] load build definition from Dockerfile",
+            "#1 DONE 0.0s",
+            "#4 [1/4] FROM docker.io/library/alpine:3.19",
+            "#4 DONE 0.0s",
+            "#5 [2/4] RUN apk add --no-cache curl",
+            cache,
+            "#6 [3/4] COPY . /app",
+            "#6 DONE 0.1s",
+            "#7 [4/4] RUN make -C /app",
+            "#7 0.214 make: Nothing to be done for 'all'.",
+            "#7 DONE 0.3s",
+            "#8 exporting to image",
+            "#8 DONE 0.1s",
+        ];
+        Ok(Box::new(lines.into_iter().map(|line| Ok(line.to_string()))))
+    }
+
+    fn tag_image(&self, source: &str, target: &str) -> Result<(), DockerError> {
+        self.record(format!("tag {} {}", source, target));
+        Ok(())
+    }
+
+    fn remove_image(&self, name: &str) -> Result<(), DockerError> {
+        self.record(format!("rmi {}", name));
+        let mut state = self.lock();
+        let before = state.images.len();
+        state.images.retain(|i| format!("{}:{}", i.repository, i.tag) != name && i.id != name);
+        if state.images.len() == before {
+            return Err(format!("Error response from daemon: No such image: {}", name).into());
+        }
+        Ok(())
+    }
+
+    fn get_image_history(&self, image: &str) -> Result<String, DockerError> {
+        Ok(format!("IMAGE          CREATED BY\n{}   (fake history)", image))
+    }
+
+    fn import_image(&self, file: &str, repository: &str, _tag: Option<&str>) -> Result<(), DockerError> {
+        self.record(format!("import {} {}", file, repository));
+        Ok(())
+    }
+
+    fn load_image(&self, file: &str) -> Result<(), DockerError> {
+        self.record(format!("load {}", file));
+        Ok(())
+    }
+
+    fn save_image(&self, image: &str, output_file: &str) -> Result<(), DockerError> {
+        self.record(format!("save {} {}", image, output_file));
+        Ok(())
+    }
+
+    fn export_image(&self, image: &str) -> Result<Archive, DockerError> {
+        self.record(format!("save {}", image));
+        if !self.image_exists(image)? {
+            return Err(format!("Error response from daemon: No such image: {}", image).into());
+        }
+        // An apk cache that a later layer deletes, so there is waste to find,
+        // and an apk database that the nginx layer adds to
+        let base_packages = "P:musl\nV:1.2.4_git20230717-r4\nA:x86_64\nL:MIT\n\nP:busybox\nV:1.36.1-r15\nA:x86_64\nL:GPL-2.0-only\n";
+        let nginx_packages = format!("{}\nP:pcre\nV:8.45-r3\nA:x86_64\nL:BSD-3-Clause\n\nP:nginx\nV:1.25.3-r0\nA:x86_64\nL:BSD-2-Clause\n", base_packages);
+        let layers = [
+            tar_of(&[
+                ("etc/os-release", b"NAME=\"Alpine Linux\"\nID=alpine\nVERSION_ID=3.19.1\n".to_vec()),
+                ("bin/busybox", vec![0; 800 * 1024]),
+                ("lib/apk/db/installed", base_packages.as_bytes().to_vec()),
+                ("var/cache/apk/APKINDEX.tar.gz", vec![0; 300 * 1024]),
+            ]),
+            tar_of(&[
+                ("usr/sbin/nginx", vec![0; 1200 * 1024]),
+                ("lib/apk/db/installed", nginx_packages.into_bytes()),
+                ("var/cache/apk/APKINDEX.tar.gz", vec![0; 320 * 1024]),
+            ]),
+            tar_of(&[("var/cache/apk/.wh.APKINDEX.tar.gz", Vec::new())]),
+            tar_of(&[("usr/share/nginx/html/index.html", b"<h1>Welcome to nginx!</h1>".to_vec())]),
+        ];
+        let config = serde_json::json!({"history": [
+            {"created_by": "/bin/sh -c #(nop) ADD file:7e3b4c5d in / "},
+            {"created_by": "/bin/sh -c apk add --no-cache nginx"},
+            {"created_by": "/bin/sh -c rm -rf /var/cache/apk/*"},
+            {"created_by": "/bin/sh -c #(nop)  EXPOSE 80", "empty_layer": true},
+            {"created_by": "COPY html /usr/share/nginx/html # buildkit"},
+        ]});
+        let paths: Vec<String> = (1..=layers.len()).map(|index| format!("blobs/sha256/{:064x}", index)).collect();
+        let manifest = serde_json::json!([{"Config": "blobs/sha256/config", "RepoTags": [image], "Layers": paths}]);
+        let mut files: Vec<(&str, Vec<u8>)> = paths.iter().map(String::as_str).zip(layers).collect();
+        files.push(("blobs/sha256/config", config.to_string().into_bytes()));
+        files.push(("manifest.json", manifest.to_string().into_bytes()));
+        Ok(Box::new(io::Cursor::new(tar_of(&files))))
+    }
+
+    fn list_networks(&self) -> Result<Vec<Network>, DockerError> {
+        Ok(self.lock().networks.clone())
+    }
+
+    fn list_volumes(&self) -> Result<Vec<Volume>, DockerError> {
+        Ok(self.lock().volumes.clone())
+    }
+
+    fn remove_network(&self, name: &str) -> Result<(), DockerError> {
+        self.record(format!("network rm {}", name));
+        let mut state = self.lock();
+        let before = state.networks.len();
+        state.networks.retain(|n| n.name != name && n.id != name);
+        if state.networks.len() == before {
+            return Err(format!("Error response from daemon: network {} not found", name).into());
+        }
+        Ok(())
+    }
+
+    fn remove_volume(&self, name: &str) -> Result<(), DockerError> {
+        self.record(format!("volume rm {}", name));
+        let mut state = self.lock();
+        let before = state.volumes.len();
+        state.volumes.retain(|v| v.name != name);
+        if state.volumes.len() == before {
+            return Err(format!("Error response from daemon: get {}: no such volume", name).into());
+        }
+        Ok(())
+    }
+
+    fn network_containers(&self, network: &str) -> Result<usize, DockerError> {
+        // The demo containers are all on the default bridge
+        let state = self.lock();
+        Ok(if network == "bridge" { state.containers.len() } else { 0 })
+    }
+
+    fn get_system_info(&self) -> Result<String, DockerError> {
+        let state = self.lock();
+        Ok(format!(
+            "Containers: {}\nImages: {}\nServer Version: fake\nOperating System: dui demo",
+            state.containers.len(),
+            state.images.len()
+        ))
+    }
+
+    fn disk_usage(&self) -> Result<DiskUsage, DockerError> {
+        let state = self.lock();
+        let mut items = Vec::new();
+        for image in &state.images {
+            let name = format!("{}:{}", image.repository, image.tag);
+            let containers = state.containers.iter().filter(|c| c.image == name || c.image == image.id).count();
+            let untagged = image.repository == "<none>";
+            items.push(UsageItem {
+                kind: UsageKind::Image,
+                name: if untagged { image.id.clone() } else { name },
+                size: parse_size(&image.size).unwrap_or(0),
+                detail: format!("{} container{}", containers, if containers == 1 { "" } else { "s" }),
+                reclaim: disk::image_reclaim(untagged, containers as i64),
+            });
+        }
+        for container in &state.containers {
+            let stopped = !container.status.starts_with("Up");
+            items.push(UsageItem {
+                kind: UsageKind::Container,
+                name: container.name.clone(),
+                // A stopped worker leaves its scratch files in the writable layer
+                size: if stopped { 12_300_000 } else { 1_090 },
+                detail: container.status.clone(),
+                reclaim: Some(Reclaim::StoppedContainer).filter(|_| stopped),
+            });
+        }
+        for volume in &state.volumes {
+            // The demo's db container mounts pgdata
+            let (links, size) = if volume.name == "pgdata" { (1, 48_200_000) } else { (0, 1_500_000_000) };
+            items.push(UsageItem {
+                kind: UsageKind::Volume,
+                name: volume.name.clone(),
+                size,
+                detail: format!("{} link{}", links, if links == 1 { "" } else { "s" }),
+                reclaim: Some(Reclaim::UnusedVolume).filter(|_| links == 0),
+            });
+        }
+        items.push(UsageItem {
+            kind: UsageKind::BuildCache,
+            name: "k3j2h1g0f9e8".to_string(),
+            size: 210_000_000,
+            detail: "regular".to_string(),
+            reclaim: Some(Reclaim::UnusedCache),
+        });
+        Ok(DiskUsage { items, shared_image_size: 7_380_000 })
+    }
+
+    fn events(&self, _filter: &EventFilter) -> Result<EventStream, DockerError> {
+        Ok(Box::new(self.lock().events.clone().into_iter()))
+    }
+}
+
+fn demo_labels(labels: &[(&str, &str)]) -> BTreeMap<String, String> {
+    labels.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+}
+
+// A container event from `seconds_ago`, so `--since` works against the demo data
+fn demo_event(action: &str, id: &str, attributes: &[(&str, &str)], seconds_ago: i64) -> DockerEvent {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    DockerEvent {
+        kind: "container".to_string(),
+        action: action.to_string(),
+        actor: Actor {
+            id: id.to_string(),
+            attributes: attributes.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect::<BTreeMap<_, _>>(),
+        },
+        time: now - seconds_ago,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_updates_state() {
+        let fake = FakeBackend::demo();
+
+        fake.stop_container("web").unwrap();
+        assert!(fake.containers()[0].status.starts_with("Exited"));
+
+        fake.rename_container("web", "frontend").unwrap();
+        assert_eq!(fake.containers()[0].name, "frontend");
+
+        fake.remove_container("frontend").unwrap();
+        assert!(fake.containers().iter().all(|c| c.name != "frontend"));
+        assert_eq!(fake.calls(), vec!["stop web", "rename web frontend", "remove frontend"]);
+    }
+
+    #[test]
+    fn test_unknown_container_mirrors_daemon_error() {
+        let fake = FakeBackend::new();
+        assert_eq!(
+            fake.start_container("ghost").unwrap_err(),
+            DockerError::NoSuchContainer("Error response from daemon: No such container: ghost".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_rejects_duplicate_names() {
+        let fake = FakeBackend::demo();
+        assert!(fake.create_container(&ContainerSpec::new("web", "nginx")).is_err());
+        let api = ContainerSpec { ports: vec!["3000:3000".to_string()], ..ContainerSpec::new("api", "myapp") };
+        assert!(fake.create_container(&api).is_ok());
+        assert_eq!(fake.containers().last().unwrap().ports, "3000:3000");
+    }
+}
@@ -0,0 +1,396 @@
+// Image layer analysis from a `docker save` archive.
+//
+// The archive holds manifest.json, the image config (whose history has the
+// instruction behind each layer) and one tar per layer, possibly gzipped.
+// Layers are read in a single streaming pass, keeping only their file lists,
+// then replayed in order to see what each one added, modified or deleted.
+// A file overwritten or deleted by a later layer still costs its size in the
+// earlier one: that is the wasted space the efficiency score is based on.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Cursor, Read};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use crate::error::DockerError;
+use crate::tar::{EntryKind, TarReader};
+
+/// JSON files (manifest, config) larger than this are not layers either way.
+const MAX_METADATA: u64 = 16 * 1024 * 1024;
+/// Larger files aren't read even when asked for, e.g. a huge rpm database.
+const MAX_KEPT: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Size of the file in this layer; for deletions, the size it had.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerInfo {
+    /// Digest or layer directory, as named in manifest.json.
+    pub id: String,
+    /// The Dockerfile instruction that created the layer, when recorded.
+    pub instruction: String,
+    /// Bytes of file contents in the layer.
+    pub size: u64,
+    pub changes: Vec<FileChange>,
+}
+
+impl LayerInfo {
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|change| change.kind == kind).count()
+    }
+}
+
+/// A path whose earlier copies were overwritten or deleted by later layers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WastedFile {
+    pub path: String,
+    /// Layers that stored a copy.
+    pub copies: usize,
+    /// Bytes of the copies that can no longer be seen.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageAnalysis {
+    pub layers: Vec<LayerInfo>,
+    /// Largest first.
+    pub wasted: Vec<WastedFile>,
+}
+
+impl ImageAnalysis {
+    /// Bytes stored across all layers.
+    pub fn total_size(&self) -> u64 {
+        self.layers.iter().map(|layer| layer.size).sum()
+    }
+
+    pub fn wasted_size(&self) -> u64 {
+        self.wasted.iter().map(|file| file.size).sum()
+    }
+
+    /// Share of the stored bytes that are still visible, from 0 to 1.
+    pub fn efficiency(&self) -> f64 {
+        match self.total_size() {
+            0 => 1.0,
+            total => 1.0 - self.wasted_size() as f64 / total as f64,
+        }
+    }
+}
+
+const WHITEOUT: &str = ".wh.";
+const OPAQUE: &str = ".wh..wh..opq";
+
+// One file of a layer tar, before comparing with the layers below
+#[derive(Debug, Clone)]
+struct LayerFile {
+    path: String,
+    size: u64,
+    is_dir: bool,
+    // Only for files `read_saved_image` was asked to keep
+    contents: Option<Vec<u8>>,
+}
+
+impl LayerFile {
+    // "etc/.wh.motd" hides etc/motd below; "etc/.wh..wh..opq" all of etc/
+    fn is_whiteout(&self) -> bool {
+        self.split().1.starts_with(WHITEOUT)
+    }
+
+    // ("etc/", "motd")
+    fn split(&self) -> (String, &str) {
+        match self.path.rsplit_once('/') {
+            Some((dir, name)) => (format!("{}/", dir), name),
+            None => (String::new(), self.path.as_str()),
+        }
+    }
+}
+
+/// One layer of a saved image.
+#[derive(Debug, Clone)]
+pub struct SavedLayer {
+    /// Digest or layer directory, shortened.
+    pub id: String,
+    pub instruction: String,
+    files: Vec<LayerFile>,
+}
+
+/// Reads a `docker save` archive and analyses its layers.
+pub fn analyze(archive: impl Read) -> Result<ImageAnalysis, DockerError> {
+    Ok(replay(read_saved_image(archive, |_| false)?))
+}
+
+/// The layers of a `docker save` archive, bottom first. The contents of
+/// files whose path `keep` accepts are read too, for `flatten`.
+pub fn read_saved_image(archive: impl Read, keep: impl Fn(&str) -> bool) -> Result<Vec<SavedLayer>, DockerError> {
+    let mut reader = TarReader::new(archive);
+    let mut metadata: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut layer_files: HashMap<String, Vec<LayerFile>> = HashMap::new();
+
+    while let Some(entry) = reader.next_entry().map_err(read_error)? {
+        if entry.kind != EntryKind::File {
+            continue;
+        }
+        let mut head = vec![0; 512.min(entry.size as usize)];
+        reader.read_exact(&mut head).map_err(read_error)?;
+        if matches!(head.first(), Some(b'{') | Some(b'[')) {
+            if entry.size <= MAX_METADATA {
+                reader.read_to_end(&mut head).map_err(read_error)?;
+                metadata.insert(entry.path, head);
+            }
+            continue;
+        }
+        let gzipped = head.starts_with(&[0x1f, 0x8b]);
+        let contents = Cursor::new(head).chain(&mut reader);
+        let files = if gzipped { list_layer(GzDecoder::new(contents), &keep) } else { list_layer(contents, &keep) };
+        // VERSION files and the like aren't tars; anything the manifest
+        // needs but couldn't be read is reported below
+        if let Ok(files) = files {
+            layer_files.insert(entry.path, files);
+        }
+    }
+
+    let manifest = metadata
+        .get("manifest.json")
+        .and_then(|data| serde_json::from_slice::<Value>(data).ok())
+        .ok_or_else(|| "Not an image archive: manifest.json is missing".to_string())?;
+    let manifest = &manifest[0];
+    let config = manifest["Config"]
+        .as_str()
+        .and_then(|path| metadata.get(path))
+        .and_then(|data| serde_json::from_slice::<Value>(data).ok())
+        .unwrap_or(Value::Null);
+    // History has an entry per instruction; only some of them made a layer
+    let instructions: Vec<String> = config["history"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entry| !entry["empty_layer"].as_bool().unwrap_or(false))
+        .map(|entry| instruction(entry["created_by"].as_str().unwrap_or("")))
+        .collect();
+
+    let mut layers = Vec::new();
+    for (index, path) in manifest["Layers"].as_array().into_iter().flatten().filter_map(Value::as_str).enumerate() {
+        let files = layer_files
+            .remove(path)
+            .ok_or_else(|| format!("Layer {} is missing from the image archive or isn't a tar", path))?;
+        layers.push(SavedLayer { id: layer_id(path), instruction: instructions.get(index).cloned().unwrap_or_default(), files });
+    }
+    Ok(layers)
+}
+
+/// The kept files (see `read_saved_image`) that the final image shows, by path.
+pub fn flatten(layers: &[SavedLayer]) -> BTreeMap<String, Vec<u8>> {
+    let mut visible: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    for layer in layers {
+        for file in layer.files.iter().filter(|file| file.is_whiteout()) {
+            let (dir, name) = file.split();
+            let hidden = if name == OPAQUE { dir.clone() } else { format!("{}{}/", dir, &name[WHITEOUT.len()..]) };
+            visible.retain(|path, _| !path.starts_with(&hidden));
+            if name != OPAQUE {
+                visible.remove(&hidden[..hidden.len() - 1]);
+            }
+        }
+        for file in &layer.files {
+            if let Some(contents) = &file.contents {
+                visible.insert(file.path.clone(), contents.clone());
+            }
+        }
+    }
+    visible
+}
+
+// The files of one layer tar, whiteouts included
+fn list_layer(contents: impl Read, keep: &impl Fn(&str) -> bool) -> io::Result<Vec<LayerFile>> {
+    let mut reader = TarReader::new(contents);
+    let mut files = Vec::new();
+    while let Some(entry) = reader.next_entry()? {
+        let path = entry.path.trim_start_matches("./").trim_end_matches('/').to_string();
+        if path.is_empty() || path == "." {
+            continue;
+        }
+        let is_file = entry.kind == EntryKind::File;
+        let contents = if is_file && entry.size <= MAX_KEPT && keep(&path) {
+            let mut data = Vec::with_capacity(entry.size as usize);
+            reader.read_to_end(&mut data)?;
+            Some(data)
+        } else {
+            None
+        };
+        let size = if is_file { entry.size } else { 0 };
+        files.push(LayerFile { path, size, is_dir: entry.kind == EntryKind::Directory, contents });
+    }
+    Ok(files)
+}
+
+// Applies each layer's files over the ones below, in order
+fn replay(layers: Vec<SavedLayer>) -> ImageAnalysis {
+    // What is visible so far: path -> (size, is_dir)
+    let mut visible: BTreeMap<String, (u64, bool)> = BTreeMap::new();
+    // Copies of each file stored by any layer, and the bytes no longer visible
+    let mut copies: HashMap<String, usize> = HashMap::new();
+    let mut wasted: BTreeMap<String, u64> = BTreeMap::new();
+
+    let mut infos = Vec::new();
+    for SavedLayer { id, instruction, files } in layers {
+        let mut changes = Vec::new();
+        // Whiteouts only hide lower layers, wherever they sit in the tar
+        let (whiteouts, files): (Vec<&LayerFile>, Vec<&LayerFile>) = files.iter().partition(|file| file.is_whiteout());
+        for whiteout in whiteouts {
+            let (dir, name) = whiteout.split();
+            if name == OPAQUE {
+                // Opaque directory: nothing below it shows through
+                for (path, (size, is_dir)) in remove_under(&mut visible, &dir) {
+                    if !is_dir {
+                        *wasted.entry(path.clone()).or_default() += size;
+                        changes.push(FileChange { path, kind: ChangeKind::Deleted, size });
+                    }
+                }
+                continue;
+            }
+            let path = format!("{}{}", dir, &name[WHITEOUT.len()..]);
+            let mut removed = remove_under(&mut visible, &format!("{}/", path));
+            removed.extend(visible.remove(&path).map(|value| (path.clone(), value)));
+            if removed.is_empty() {
+                continue;
+            }
+            let mut size = 0;
+            for (path, (file_size, is_dir)) in removed {
+                if !is_dir {
+                    *wasted.entry(path).or_default() += file_size;
+                    size += file_size;
+                }
+            }
+            changes.push(FileChange { path, kind: ChangeKind::Deleted, size });
+        }
+        for file in &files {
+            if file.is_dir {
+                visible.entry(file.path.clone()).or_insert((0, true));
+                continue;
+            }
+            *copies.entry(file.path.clone()).or_default() += 1;
+            let kind = match visible.insert(file.path.clone(), (file.size, false)) {
+                Some((size, false)) => {
+                    *wasted.entry(file.path.clone()).or_default() += size;
+                    ChangeKind::Modified
+                }
+                _ => ChangeKind::Added,
+            };
+            changes.push(FileChange { path: file.path.clone(), kind, size: file.size });
+        }
+        let size = files.iter().map(|file| file.size).sum();
+        infos.push(LayerInfo { id, instruction, size, changes });
+    }
+
+    let mut wasted: Vec<WastedFile> = wasted
+        .into_iter()
+        .filter(|(_, size)| *size > 0)
+        .map(|(path, size)| WastedFile { copies: copies.get(&path).copied().unwrap_or(1), path, size })
+        .collect();
+    wasted.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    ImageAnalysis { layers: infos, wasted }
+}
+
+// Removes and returns everything under `prefix` ("dir/")
+fn remove_under(visible: &mut BTreeMap<String, (u64, bool)>, prefix: &str) -> Vec<(String, (u64, bool))> {
+    let paths: Vec<String> = visible.range(prefix.to_string()..).map(|(path, _)| path).take_while(|path| path.starts_with(prefix)).cloned().collect();
+    paths.into_iter().filter_map(|path| visible.remove(&path).map(|value| (path, value))).collect()
+}
+
+/// The Dockerfile instruction in a history entry's `created_by`.
+pub fn instruction(created_by: &str) -> String {
+    let text = created_by.trim().trim_end_matches("# buildkit").trim();
+    if let Some(nop) = text.strip_prefix("/bin/sh -c #(nop)") {
+        return nop.trim().to_string();
+    }
+    // The legacy builder records the shell command, BuildKit "RUN /bin/sh -c ..."
+    if let Some(command) = text.strip_prefix("/bin/sh -c ").or_else(|| text.strip_prefix("RUN /bin/sh -c ")) {
+        return format!("RUN {}", command.trim());
+    }
+    text.to_string()
+}
+
+// "blobs/sha256/abc..." and "abc.../layer.tar" both become "abc..." shortened
+fn layer_id(path: &str) -> String {
+    let id = path.trim_end_matches("/layer.tar").rsplit('/').next().unwrap_or(path);
+    id.chars().take(12).collect()
+}
+
+fn read_error(e: io::Error) -> DockerError {
+    DockerError::from_message(format!("Failed to read image archive: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tar::{self, Entry};
+
+    fn tar_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (path, contents) in files {
+            let kind = if path.ends_with('/') { EntryKind::Directory } else { EntryKind::File };
+            let entry = Entry { path: path.to_string(), kind, size: contents.len() as u64, mode: 0o644, mtime: 0, link: None };
+            data.extend(entry.header());
+            data.extend_from_slice(contents);
+            data.extend(vec![0; tar::padding(contents.len() as u64)]);
+        }
+        data.extend(tar::end_of_archive());
+        data
+    }
+
+    #[test]
+    fn test_changes_waste_and_efficiency() {
+        let base = tar_of(&[("etc/", b""), ("etc/os-release", &[b'o'; 100]), ("var/cache/apt/pkgcache.bin", &[b'c'; 600])]);
+        let install = tar_of(&[("usr/sbin/nginx", &[b'n'; 200]), ("etc/os-release", &[b'O'; 100])]);
+        // Saves from the containerd image store keep layers gzipped
+        let mut cleanup = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut cleanup, &tar_of(&[("var/cache/apt/.wh.pkgcache.bin", b"")])).unwrap();
+        let cleanup = cleanup.finish().unwrap();
+        let config = br#"{"history":[
+            {"created_by":"/bin/sh -c #(nop) ADD file:1234 in / "},
+            {"created_by":"/bin/sh -c #(nop)  CMD [\"sh\"]","empty_layer":true},
+            {"created_by":"RUN /bin/sh -c apk add nginx # buildkit"},
+            {"created_by":"/bin/sh -c rm -rf /var/cache/apt"}]}"#;
+        let manifest = br#"[{"Config":"blobs/sha256/c0ffee","Layers":["blobs/sha256/aaa","blobs/sha256/bbb","blobs/sha256/ccc"]}]"#;
+        let archive = tar_of(&[
+            ("blobs/sha256/aaa", &base),
+            ("blobs/sha256/bbb", &install),
+            ("blobs/sha256/ccc", &cleanup),
+            ("blobs/sha256/c0ffee", config),
+            ("manifest.json", manifest),
+            ("oci-layout", br#"{"imageLayoutVersion":"1.0.0"}"#),
+        ]);
+
+        let analysis = analyze(archive.as_slice()).unwrap();
+        let instructions: Vec<&str> = analysis.layers.iter().map(|layer| layer.instruction.as_str()).collect();
+        assert_eq!(instructions, vec!["ADD file:1234 in /", "RUN apk add nginx", "RUN rm -rf /var/cache/apt"]);
+        assert_eq!(analysis.layers[0].size, 700);
+        assert_eq!(
+            (analysis.layers[1].count(ChangeKind::Added), analysis.layers[1].count(ChangeKind::Modified)),
+            (1, 1)
+        );
+        assert_eq!(
+            analysis.layers[2].changes,
+            vec![FileChange { path: "var/cache/apt/pkgcache.bin".to_string(), kind: ChangeKind::Deleted, size: 600 }]
+        );
+        assert_eq!(analysis.wasted[0], WastedFile { path: "var/cache/apt/pkgcache.bin".to_string(), copies: 1, size: 600 });
+        assert_eq!(analysis.wasted[1], WastedFile { path: "etc/os-release".to_string(), copies: 2, size: 100 });
+        assert_eq!(analysis.wasted_size(), 700);
+        assert_eq!(analysis.total_size(), 1000);
+        assert!((analysis.efficiency() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_missing_manifest_is_an_error() {
+        assert!(analyze(tar_of(&[("VERSION", b"1.0")]).as_slice()).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+// Container log lines and the options for fetching them.
+//
+// Backends keep stdout and stderr apart (the docker CLI on two pipes, the
+// Engine API through its multiplexed frames), so every line knows where it
+// came from. Lines are streamed, which lets follow mode print them as the
+// container writes them.
+
+use std::ops::Range;
+use std::sync::OnceLock;
+use regex::Regex;
+
+/// Where a container wrote a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub stream: LogStream,
+    /// RFC 3339 time the daemon recorded, when timestamps were requested.
+    pub timestamp: Option<String>,
+    pub text: String,
+}
+
+impl LogLine {
+    /// Parses one line of output, splitting off the leading timestamp the
+    /// daemon adds when `timestamps` is set.
+    pub fn parse(stream: LogStream, line: &str, timestamps: bool) -> LogLine {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let (timestamp, text) = match line.split_once(' ') {
+            Some((timestamp, text)) if timestamps => (Some(timestamp), text),
+            None if timestamps => (Some(line), ""),
+            _ => (None, line),
+        };
+        LogLine { stream, timestamp: timestamp.map(str::to_string), text: text.to_string() }
+    }
+
+    /// Sorts lines by `timestamp`: the whole seconds, then the nanoseconds,
+    /// since the daemon trims trailing zeros from the fraction.
+    pub fn time_key(&self) -> (&str, u32) {
+        let timestamp = self.timestamp.as_deref().unwrap_or("");
+        let (seconds, rest) = timestamp.split_at(timestamp.len().min(19));
+        let digits: String = rest.strip_prefix('.').unwrap_or("").chars().take_while(char::is_ascii_digit).take(9).collect();
+        let nanos = format!("{:0<9}", digits).parse().unwrap_or(0);
+        (seconds, nanos)
+    }
+}
+
+/// Severity named in a log line, e.g. `ERROR`, `[warn]` or `level=info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    /// The first severity word in `text` and where it is.
+    pub fn find(text: &str) -> Option<(LogLevel, Range<usize>)> {
+        static LEVEL: OnceLock<Regex> = OnceLock::new();
+        let level = LEVEL.get_or_init(|| {
+            Regex::new(r"(?i)\b(fatal|panic|crit(?:ical)?|err(?:or)?|warn(?:ing)?|info|debug|trace)\b").unwrap()
+        });
+        let word = level.find(text)?;
+        let kind = match word.as_str().to_lowercase().as_str() {
+            "info" => LogLevel::Info,
+            "debug" | "trace" => LogLevel::Debug,
+            level if level.starts_with("warn") => LogLevel::Warn,
+            _ => LogLevel::Error,
+        };
+        Some((kind, word.range()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogOptions {
+    /// Keep streaming new output until the reader stops.
+    pub follow: bool,
+    /// Unix seconds; only output written at or after this time.
+    pub since: Option<i64>,
+    /// Unix seconds; only output written before this time.
+    pub until: Option<i64>,
+    pub timestamps: bool,
+    /// How many lines to show from the end of the log; None shows everything.
+    pub tail: Option<usize>,
+}
+
+// The last 50 lines, which is what `dui containers logs` shows by default
+impl Default for LogOptions {
+    fn default() -> Self {
+        LogOptions { follow: false, since: None, until: None, timestamps: false, tail: Some(50) }
+    }
+}
+
+impl LogOptions {
+    /// Arguments for `docker logs`, before the container name.
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.follow {
+            args.push("--follow".to_string());
+        }
+        if let Some(since) = self.since {
+            args.push("--since".to_string());
+            args.push(since.to_string());
+        }
+        if let Some(until) = self.until {
+            args.push("--until".to_string());
+            args.push(until.to_string());
+        }
+        if self.timestamps {
+            args.push("--timestamps".to_string());
+        }
+        args.push("--tail".to_string());
+        args.push(self.tail.map_or("all".to_string(), |tail| tail.to_string()));
+        args
+    }
+
+    /// Query string for the Engine API's `/containers/{id}/logs`.
+    pub fn api_query(&self) -> String {
+        let mut query = vec!["stdout=1".to_string(), "stderr=1".to_string()];
+        if self.follow {
+            query.push("follow=1".to_string());
+        }
+        if let Some(since) = self.since {
+            query.push(format!("since={}", since));
+        }
+        if let Some(until) = self.until {
+            query.push(format!("until={}", until));
+        }
+        if self.timestamps {
+            query.push("timestamps=1".to_string());
+        }
+        query.push(format!("tail={}", self.tail.map_or("all".to_string(), |tail| tail.to_string())));
+        query.join("&")
+    }
+}
+
+/// Parses `--tail`: a line count or "all".
+pub fn parse_tail(text: &str) -> Result<Option<usize>, String> {
+    if text.eq_ignore_ascii_case("all") {
+        return Ok(None);
+    }
+    text.trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("Invalid tail '{}': use a number of lines or 'all'", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_line() {
+        let line = LogLine::parse(LogStream::Stderr, "2024-01-02T03:04:05.123456789Z connection refused\r", true);
+        assert_eq!(line.timestamp.as_deref(), Some("2024-01-02T03:04:05.123456789Z"));
+        assert_eq!(line.text, "connection refused");
+        assert_eq!(line.stream, LogStream::Stderr);
+
+        let line = LogLine::parse(LogStream::Stdout, "GET /health 200", false);
+        assert_eq!(line.timestamp, None);
+        assert_eq!(line.text, "GET /health 200");
+        assert_eq!(LogLine::parse(LogStream::Stdout, "2024-01-02T03:04:05Z", true).text, "");
+    }
+
+    #[test]
+    fn test_time_key_orders_trimmed_fractions() {
+        let at = |timestamp: &str| LogLine::parse(LogStream::Stdout, &format!("{} x", timestamp), true);
+        let (early, late) = (at("2024-01-02T03:04:05.9Z"), at("2024-01-02T03:04:05.10203Z"));
+        assert_eq!(early.time_key(), ("2024-01-02T03:04:05", 900_000_000));
+        assert!(late.time_key() < early.time_key());
+        assert!(at("2024-01-02T03:04:05Z").time_key() < late.time_key());
+    }
+
+    #[test]
+    fn test_find_level() {
+        assert_eq!(LogLevel::find("2024/01/02 [error] 31#31: connect() failed"), Some((LogLevel::Error, 12..17)));
+        assert_eq!(LogLevel::find("level=warning msg=\"slow\"").map(|(level, _)| level), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::find("INFO  Started in 1.2s").map(|(level, _)| level), Some(LogLevel::Info));
+        assert_eq!(LogLevel::find("FATAL:  password authentication failed").map(|(level, _)| level), Some(LogLevel::Error));
+        assert_eq!(LogLevel::find("GET /errors 200"), None);
+    }
+
+    #[test]
+    fn test_log_options_arguments() {
+        let options = LogOptions { follow: true, since: Some(1_704_164_645), timestamps: true, tail: None, ..Default::default() };
+        assert_eq!(options.cli_args(), vec!["--follow", "--since", "1704164645", "--timestamps", "--tail", "all"]);
+        assert_eq!(options.api_query(), "stdout=1&stderr=1&follow=1&since=1704164645&timestamps=1&tail=all");
+        assert_eq!(LogOptions::default().cli_args(), vec!["--tail", "50"]);
+
+        assert_eq!(parse_tail("all"), Ok(None));
+        assert_eq!(parse_tail("200"), Ok(Some(200)));
+        assert!(parse_tail("-5").is_err());
+    }
+}
@@ -0,0 +1,335 @@
+// Progress of image pulls and pushes.
+//
+// The daemon reports a pull or push as a stream of JSON messages, one per
+// layer change: "Downloading" with byte counts, "Extracting", "Pull complete",
+// "Already exists" and so on. Without a terminal the docker CLI prints the same
+// changes as "id: status" lines, minus the byte counts. Both become
+// `ProgressMessage`s, which `TransferProgress` folds into per-layer state.
+
+use std::time::{Duration, Instant};
+use serde_json::Value;
+use crate::error::DockerError;
+use crate::utils::format_size;
+
+/// How often `follow` redraws while messages keep arriving.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// One message of a pull or push.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressMessage {
+    /// Short layer ID, for messages about one layer.
+    pub id: Option<String>,
+    pub status: String,
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+    /// Set when the pull or push failed.
+    pub error: Option<String>,
+}
+
+impl ProgressMessage {
+    /// Parses one line of the Engine API's JSON progress stream.
+    pub fn parse(line: &str) -> Option<ProgressMessage> {
+        let json: Value = serde_json::from_str(line.trim()).ok()?;
+        let detail = &json["progressDetail"];
+        let error = json["error"].as_str().or_else(|| json["errorDetail"]["message"].as_str());
+        Some(ProgressMessage {
+            id: json["id"].as_str().map(str::to_string),
+            status: json["status"].as_str().unwrap_or("").to_string(),
+            current: detail["current"].as_u64(),
+            total: detail["total"].as_u64().filter(|&total| total > 0),
+            error: error.map(str::to_string),
+        })
+    }
+
+    /// Parses a line of `docker pull`/`push` output, or podman's "Copying blob" lines.
+    pub fn parse_cli(line: &str) -> Option<ProgressMessage> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        if let Some(blob) = line.strip_prefix("Copying blob ") {
+            let digest = blob.split_whitespace().next().unwrap_or("");
+            let id: String = digest.trim_start_matches("sha256:").chars().take(12).collect();
+            let status = if blob.contains("already exists") {
+                "Already exists"
+            } else if blob.contains("done") {
+                "Pull complete"
+            } else {
+                "Downloading"
+            };
+            return Some(ProgressMessage::layer(&id, status));
+        }
+        match line.split_once(": ") {
+            Some((id, status)) if id.len() == 12 && id.chars().all(|c| c.is_ascii_hexdigit()) => {
+                Some(ProgressMessage::layer(id, status))
+            }
+            _ => Some(ProgressMessage::note(line)),
+        }
+    }
+
+    pub fn layer(id: &str, status: &str) -> ProgressMessage {
+        ProgressMessage { id: Some(id.to_string()), status: status.to_string(), current: None, total: None, error: None }
+    }
+
+    /// A message about the whole transfer rather than one layer.
+    pub fn note(status: &str) -> ProgressMessage {
+        ProgressMessage { id: None, ..ProgressMessage::layer("", status) }
+    }
+
+    pub fn failure(message: impl Into<String>) -> ProgressMessage {
+        ProgressMessage { id: None, status: String::new(), current: None, total: None, error: Some(message.into()) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerPhase {
+    Waiting,
+    Downloading,
+    Downloaded,
+    Extracting,
+    Complete,
+    /// Already present locally (pull) or in the registry (push).
+    Exists,
+    Pushing,
+    Pushed,
+}
+
+impl LayerPhase {
+    fn from_status(status: &str) -> Option<LayerPhase> {
+        let status = status.to_lowercase();
+        Some(match status.as_str() {
+            "pulling fs layer" | "waiting" | "preparing" => LayerPhase::Waiting,
+            "downloading" => LayerPhase::Downloading,
+            "verifying checksum" | "download complete" => LayerPhase::Downloaded,
+            "extracting" => LayerPhase::Extracting,
+            "pull complete" => LayerPhase::Complete,
+            "already exists" | "layer already exists" => LayerPhase::Exists,
+            "pushing" => LayerPhase::Pushing,
+            "pushed" => LayerPhase::Pushed,
+            status if status.starts_with("mounted from") => LayerPhase::Exists,
+            _ => return None,
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LayerPhase::Waiting => "Waiting",
+            LayerPhase::Downloading => "Downloading",
+            LayerPhase::Downloaded => "Downloaded",
+            LayerPhase::Extracting => "Extracting",
+            LayerPhase::Complete => "Pull complete",
+            LayerPhase::Exists => "Already exists",
+            LayerPhase::Pushing => "Pushing",
+            LayerPhase::Pushed => "Pushed",
+        }
+    }
+
+    /// Whether the layer's bytes have all been transferred.
+    fn transferred(self) -> bool {
+        matches!(self, LayerPhase::Downloaded | LayerPhase::Extracting | LayerPhase::Complete | LayerPhase::Pushed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerProgress {
+    pub id: String,
+    pub phase: LayerPhase,
+    /// Bytes downloaded or pushed.
+    pub transferred: u64,
+    /// Layer size, once the daemon has said.
+    pub size: Option<u64>,
+    /// Bytes extracted, while extracting.
+    pub extracted: u64,
+}
+
+impl LayerProgress {
+    /// How far the current phase is, when it has a size.
+    pub fn fraction(&self) -> Option<f64> {
+        let size = self.size? as f64;
+        match self.phase {
+            LayerPhase::Downloading | LayerPhase::Pushing => Some(self.transferred as f64 / size),
+            LayerPhase::Extracting => Some(self.extracted as f64 / size),
+            _ => None,
+        }
+    }
+}
+
+/// Everything known about a pull or push so far.
+#[derive(Debug, Clone)]
+pub struct TransferProgress {
+    /// In the order the daemon first mentioned them.
+    pub layers: Vec<LayerProgress>,
+    /// Messages not about a layer, e.g. "Digest: sha256:...".
+    pub notes: Vec<String>,
+    started: Instant,
+}
+
+impl Default for TransferProgress {
+    fn default() -> Self {
+        TransferProgress { layers: Vec::new(), notes: Vec::new(), started: Instant::now() }
+    }
+}
+
+impl TransferProgress {
+    /// Folds in one message; an error message fails the transfer.
+    pub fn apply(&mut self, message: ProgressMessage) -> Result<(), DockerError> {
+        if let Some(error) = message.error {
+            return Err(DockerError::from_message(error));
+        }
+        let id = match message.id {
+            Some(id) if !id.is_empty() => id,
+            // Per-tag lines such as "latest: Pulling from library/nginx" carry an ID too
+            _ => {
+                self.notes.push(message.status);
+                return Ok(());
+            }
+        };
+        let phase = match LayerPhase::from_status(&message.status) {
+            Some(phase) => phase,
+            None if self.layers.iter().any(|layer| layer.id == id) => return Ok(()),
+            None => {
+                self.notes.push(format!("{}: {}", id, message.status));
+                return Ok(());
+            }
+        };
+
+        let index = match self.layers.iter().position(|layer| layer.id == id) {
+            Some(index) => index,
+            None => {
+                self.layers.push(LayerProgress { id, phase, transferred: 0, size: None, extracted: 0 });
+                self.layers.len() - 1
+            }
+        };
+        let layer = &mut self.layers[index];
+        layer.phase = phase;
+        match phase {
+            LayerPhase::Downloading | LayerPhase::Pushing => {
+                layer.transferred = message.current.unwrap_or(layer.transferred);
+                layer.size = message.total.or(layer.size);
+            }
+            LayerPhase::Extracting => {
+                layer.extracted = message.current.unwrap_or(layer.extracted);
+                layer.size = message.total.or(layer.size);
+            }
+            _ => {}
+        }
+        if phase.transferred() {
+            if let Some(size) = layer.size {
+                layer.transferred = size;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes transferred and the total, over layers that need transferring.
+    pub fn bytes(&self) -> (u64, u64) {
+        self.layers
+            .iter()
+            .filter(|layer| layer.phase != LayerPhase::Exists)
+            .fold((0, 0), |(done, total), layer| (done + layer.transferred, total + layer.size.unwrap_or(0)))
+    }
+
+    /// Time left at the average rate so far, once there is one.
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta_after(self.started.elapsed())
+    }
+
+    fn eta_after(&self, elapsed: Duration) -> Option<Duration> {
+        let (done, total) = self.bytes();
+        if done == 0 || total <= done || elapsed.is_zero() {
+            return None;
+        }
+        let rate = done as f64 / elapsed.as_secs_f64();
+        Some(Duration::from_secs_f64((total - done) as f64 / rate))
+    }
+
+    /// Layers that were already there and didn't need transferring.
+    pub fn existing(&self) -> usize {
+        self.layers.iter().filter(|layer| layer.phase == LayerPhase::Exists).count()
+    }
+
+    /// e.g. "3 layers, 1 already existed, 12.4 MB"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![match self.layers.len() {
+            1 => "1 layer".to_string(),
+            count => format!("{} layers", count),
+        }];
+        if self.existing() > 0 {
+            parts.push(format!("{} already existed", self.existing()));
+        }
+        let (done, _) = self.bytes();
+        if done > 0 {
+            parts.push(format_size(done));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Applies every message of `messages`, calling `draw` at most every
+/// `REDRAW_INTERVAL` and once more at the end.
+pub fn follow(messages: impl Iterator<Item = ProgressMessage>, mut draw: impl FnMut(&TransferProgress)) -> Result<TransferProgress, DockerError> {
+    let mut progress = TransferProgress::default();
+    let mut last_draw: Option<Instant> = None;
+    for message in messages {
+        progress.apply(message)?;
+        if last_draw.map_or(true, |last| last.elapsed() >= REDRAW_INTERVAL) {
+            last_draw = Some(Instant::now());
+            draw(&progress);
+        }
+    }
+    draw(&progress);
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_messages() {
+        let line = r#"{"status":"Downloading","progressDetail":{"current":1048576,"total":4194304},"progress":"[=>  ]","id":"a2abf6c4d29d"}"#;
+        let message = ProgressMessage::parse(line).unwrap();
+        assert_eq!(message.id.as_deref(), Some("a2abf6c4d29d"));
+        assert_eq!((message.current, message.total), (Some(1_048_576), Some(4_194_304)));
+
+        let error = ProgressMessage::parse(r#"{"errorDetail":{"message":"manifest unknown"},"error":"manifest unknown"}"#).unwrap();
+        assert_eq!(error.error.as_deref(), Some("manifest unknown"));
+
+        assert_eq!(ProgressMessage::parse_cli("a2abf6c4d29d: Pull complete"), Some(ProgressMessage::layer("a2abf6c4d29d", "Pull complete")));
+        assert_eq!(ProgressMessage::parse_cli("latest: Pulling from library/nginx").unwrap().id, None);
+        assert_eq!(
+            ProgressMessage::parse_cli("Copying blob sha256:a2abf6c4d29d43a4bf9fbb769f524d0fb36a2edab49819c1bf3e76f409f953ea skipped: already exists"),
+            Some(ProgressMessage::layer("a2abf6c4d29d", "Already exists"))
+        );
+    }
+
+    #[test]
+    fn test_layers_bytes_and_eta() {
+        let mut progress = TransferProgress::default();
+        let download = |id: &str, current: u64, total: u64| ProgressMessage { current: Some(current), total: Some(total), ..ProgressMessage::layer(id, "Downloading") };
+        for message in [
+            ProgressMessage::layer("1111aaaa2222", "Already exists"),
+            ProgressMessage::layer("3333bbbb4444", "Pulling fs layer"),
+            ProgressMessage::layer("5555cccc6666", "Pulling fs layer"),
+            download("3333bbbb4444", 300, 1000),
+            download("5555cccc6666", 100, 1000),
+            ProgressMessage::layer("3333bbbb4444", "Verifying Checksum"),
+            ProgressMessage::layer("3333bbbb4444", "Download complete"),
+            ProgressMessage { current: Some(500), total: Some(1000), ..ProgressMessage::layer("3333bbbb4444", "Extracting") },
+        ] {
+            progress.apply(message).unwrap();
+        }
+
+        assert_eq!(progress.layers.len(), 3);
+        assert_eq!(progress.existing(), 1);
+        assert_eq!(progress.layers[1].phase, LayerPhase::Extracting);
+        assert_eq!(progress.layers[1].fraction(), Some(0.5));
+        assert_eq!(progress.bytes(), (1100, 2000));
+        // 1100 bytes in 11s is 100 B/s, with 900 to go
+        assert_eq!(progress.eta_after(Duration::from_secs(11)), Some(Duration::from_secs(9)));
+        assert_eq!(progress.summary(), format!("3 layers, 1 already existed, {}", format_size(1100)));
+
+        let failed = progress.apply(ProgressMessage::failure("toomanyrequests: You have reached your pull rate limit"));
+        assert!(failed.is_err());
+    }
+}
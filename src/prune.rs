@@ -0,0 +1,265 @@
+// Finding what can be pruned, item by item.
+//
+// Instead of `docker system prune`'s all-or-nothing, orphans are listed
+// with their size and why they count as orphans: containers exited longer
+// than a number of days, images no container references, volumes no
+// container mounts and networks no container is attached to. Like
+// `docker system prune`, which needs --volumes and -a for them, named
+// volumes and tagged images start deselected unless their kind is asked
+// for; everything else starts selected unless a filter excludes it. Only
+// the selection is removed.
+
+use crate::disk::{Reclaim, UsageKind};
+use crate::docker::DockerClient;
+use crate::error::DockerError;
+use crate::utils::glob_match;
+
+/// Networks every daemon creates, which can't be removed.
+const BUILTIN_NETWORKS: [&str; 4] = ["bridge", "host", "none", "podman"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanKind {
+    Container,
+    Image,
+    Volume,
+    Network,
+}
+
+impl OrphanKind {
+    pub fn parse(name: &str) -> Option<OrphanKind> {
+        match name {
+            "container" => Some(OrphanKind::Container),
+            "image" => Some(OrphanKind::Image),
+            "volume" => Some(OrphanKind::Volume),
+            "network" => Some(OrphanKind::Network),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrphanKind::Container => "container",
+            OrphanKind::Image => "image",
+            OrphanKind::Volume => "volume",
+            OrphanKind::Network => "network",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orphan {
+    pub kind: OrphanKind,
+    /// What to remove it by: a name, "repo:tag", or an image id.
+    pub name: String,
+    /// Unknown for networks.
+    pub size: Option<u64>,
+    /// e.g. "Exited (0) 3 weeks ago" or "no container mounts it".
+    pub reason: String,
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PruneFilter {
+    /// Kinds to look for; empty means all of them. Named volumes and tagged
+    /// images only start selected when their kind is listed.
+    pub kinds: Vec<OrphanKind>,
+    /// Glob patterns of names that start deselected.
+    pub exclude: Vec<String>,
+    /// Containers must have exited at least this many days ago.
+    pub exited_days: u64,
+}
+
+impl Default for PruneFilter {
+    fn default() -> Self {
+        PruneFilter { kinds: Vec::new(), exclude: Vec::new(), exited_days: 7 }
+    }
+}
+
+impl PruneFilter {
+    fn wants(&self, kind: OrphanKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    // Named with --type, rather than included by leaving the kinds empty
+    fn asks_for(&self, kind: OrphanKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    fn excludes(&self, name: &str) -> bool {
+        self.exclude.iter().any(|pattern| glob_match(pattern, name))
+    }
+}
+
+/// Orphans of the kinds `filter` asks for, containers first so that
+/// removing them in order never trips over something still in use.
+pub fn find_orphans(docker: &DockerClient, filter: &PruneFilter) -> Result<Vec<Orphan>, DockerError> {
+    let mut orphans = Vec::new();
+    let mut add = |kind: OrphanKind, name: &str, size: Option<u64>, reason: String, preselect: bool| {
+        let selected = preselect && !filter.excludes(name);
+        orphans.push(Orphan { kind, name: name.to_string(), size, reason, selected });
+    };
+
+    if [OrphanKind::Container, OrphanKind::Image, OrphanKind::Volume].iter().any(|kind| filter.wants(*kind)) {
+        let usage = docker.disk_usage()?;
+        if filter.wants(OrphanKind::Container) {
+            for item in usage.items_of(UsageKind::Container) {
+                let days = exited_age(&item.detail).map(|seconds| seconds / 86_400);
+                if days.is_some_and(|days| days >= filter.exited_days) {
+                    add(OrphanKind::Container, &item.name, Some(item.size), item.detail.clone(), true);
+                }
+            }
+        }
+        if filter.wants(OrphanKind::Image) {
+            for item in usage.items_of(UsageKind::Image) {
+                let (reason, preselect) = match item.reclaim {
+                    Some(Reclaim::DanglingImage) => ("untagged, no container uses it", true),
+                    Some(Reclaim::UnusedImage) => ("no container uses it", filter.asks_for(OrphanKind::Image)),
+                    _ => continue,
+                };
+                add(OrphanKind::Image, &item.name, Some(item.size), reason.to_string(), preselect);
+            }
+        }
+        if filter.wants(OrphanKind::Volume) {
+            for item in usage.items_of(UsageKind::Volume).filter(|item| item.reclaim.is_some()) {
+                add(OrphanKind::Volume, &item.name, Some(item.size), "no container mounts it".to_string(), filter.asks_for(OrphanKind::Volume));
+            }
+        }
+    }
+
+    if filter.wants(OrphanKind::Network) {
+        for network in docker.list_networks()? {
+            if !BUILTIN_NETWORKS.contains(&network.name.as_str()) && docker.network_containers(&network.name)? == 0 {
+                add(OrphanKind::Network, &network.name, None, "no container is attached".to_string(), true);
+            }
+        }
+    }
+    Ok(orphans)
+}
+
+/// Removes one orphan.
+pub fn remove(docker: &DockerClient, orphan: &Orphan) -> Result<(), DockerError> {
+    match orphan.kind {
+        OrphanKind::Container => docker.remove_container(&orphan.name),
+        OrphanKind::Image => docker.remove_image(&orphan.name),
+        OrphanKind::Volume => docker.remove_volume(&orphan.name),
+        OrphanKind::Network => docker.remove_network(&orphan.name),
+    }
+}
+
+/// Applies a selection command from the preview: "all", "none", or item
+/// numbers and ranges to toggle, e.g. "2 5-7".
+pub fn toggle(orphans: &mut [Orphan], input: &str) -> Result<(), String> {
+    match input.trim() {
+        "all" | "none" => {
+            let selected = input.trim() == "all";
+            orphans.iter_mut().for_each(|orphan| orphan.selected = selected);
+            return Ok(());
+        }
+        _ => {}
+    }
+    let invalid = |token: &str| format!("Invalid selection '{}': use numbers from 1 to {}, ranges like 2-4, 'all' or 'none'", token, orphans.len());
+    let mut picked = Vec::new();
+    for token in input.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()) {
+        let (first, last) = match token.split_once('-') {
+            Some((first, last)) => (first.parse::<usize>(), last.parse::<usize>()),
+            None => (token.parse::<usize>(), token.parse::<usize>()),
+        };
+        match (first, last) {
+            (Ok(first), Ok(last)) if first >= 1 && first <= last && last <= orphans.len() => picked.extend(first..=last),
+            _ => return Err(invalid(token)),
+        }
+    }
+    // Checked before changing anything, so a typo doesn't half-apply
+    for number in picked {
+        orphans[number - 1].selected = !orphans[number - 1].selected;
+    }
+    Ok(())
+}
+
+/// Bytes the selected orphans would free (networks count as nothing).
+pub fn selected_size(orphans: &[Orphan]) -> u64 {
+    orphans.iter().filter(|orphan| orphan.selected).filter_map(|orphan| orphan.size).sum()
+}
+
+// Seconds since a container exited, from a status like "Exited (0) 3 days ago"
+fn exited_age(status: &str) -> Option<u64> {
+    let rest = status.strip_prefix("Exited")?.trim_start();
+    let ago = match rest.split_once(") ") {
+        Some((code, ago)) if code.starts_with('(') => ago,
+        _ => rest,
+    };
+    let ago = ago.strip_suffix(" ago")?.trim_start_matches("About ");
+    if ago.starts_with("Less than") {
+        return Some(0);
+    }
+    let (count, unit) = ago.split_once(' ')?;
+    let count: u64 = match count {
+        "a" | "an" => 1,
+        count => count.parse().ok()?,
+    };
+    let unit = match unit.trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 3_600,
+        "day" => 86_400,
+        "week" => 7 * 86_400,
+        "month" => 30 * 86_400,
+        "year" => 365 * 86_400,
+        _ => return None,
+    };
+    Some(count * unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::fake::FakeBackend;
+
+    #[test]
+    fn test_exited_age() {
+        assert_eq!(exited_age("Exited (0) 2 hours ago"), Some(7_200));
+        assert_eq!(exited_age("Exited (137) About a minute ago"), Some(60));
+        assert_eq!(exited_age("Exited (1) 3 weeks ago"), Some(21 * 86_400));
+        assert_eq!(exited_age("Exited (0) Less than a second ago"), Some(0));
+        assert_eq!(exited_age("Up 3 hours"), None);
+        assert_eq!(exited_age("Created"), None);
+    }
+
+    #[test]
+    fn test_find_orphans_and_toggle() {
+        let docker = DockerClient::with_backend(Arc::new(FakeBackend::demo()));
+        let filter = PruneFilter { exited_days: 0, ..PruneFilter::default() };
+        let mut orphans = find_orphans(&docker, &filter).unwrap();
+        let found: Vec<(OrphanKind, &str, bool)> = orphans.iter().map(|orphan| (orphan.kind, orphan.name.as_str(), orphan.selected)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (OrphanKind::Container, "worker", true),
+                (OrphanKind::Image, "5e1d2c3b4a59", true),
+                (OrphanKind::Volume, "old_reports", false),
+                (OrphanKind::Network, "legacy_net", true),
+            ]
+        );
+        assert_eq!(selected_size(&orphans), 12_300_000 + 96_500_000);
+
+        toggle(&mut orphans, "1-2 3").unwrap();
+        assert_eq!(orphans.iter().map(|orphan| orphan.selected).collect::<Vec<_>>(), vec![false, false, true, true]);
+        assert!(toggle(&mut orphans, "2 9").is_err());
+        assert!(!orphans[1].selected);
+        toggle(&mut orphans, "all").unwrap();
+        assert!(orphans.iter().all(|orphan| orphan.selected));
+
+        // Asking for volumes selects them, short of an exclude
+        let volumes = |exclude: Vec<String>| {
+            let filter = PruneFilter { kinds: vec![OrphanKind::Volume], exclude, ..PruneFilter::default() };
+            find_orphans(&docker, &filter).unwrap().iter().map(|orphan| orphan.selected).collect::<Vec<_>>()
+        };
+        assert_eq!(volumes(Vec::new()), vec![true]);
+        assert_eq!(volumes(vec!["old_*".to_string()]), vec![false]);
+
+        // The worker exited two hours ago, not a week
+        let containers = find_orphans(&docker, &PruneFilter { kinds: vec![OrphanKind::Container], ..PruneFilter::default() }).unwrap();
+        assert!(containers.is_empty());
+    }
+}
@@ -0,0 +1,951 @@
+// Docker Registry HTTP API v2 client.
+//
+// Lists repositories and tags, fetches manifests and multi-arch manifest
+// lists, resolves tags to digests and deletes manifests. Credentials are
+// the ones `docker login` stored in ~/.docker/config.json, inline or behind
+// a credential helper, and are sent as Basic auth or traded for a Bearer
+// token, whichever the registry's challenge asks for. Plain HTTP is spoken
+// directly, which is all a local `registry:2` needs; there is no TLS here,
+// so HTTPS registries are reached through curl.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufReader, Cursor, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use serde_json::Value;
+use crate::context;
+use crate::docker::DockerClient;
+use crate::engine::{read_response, Response};
+use crate::error::DockerError;
+use crate::utils::{base64_decode, base64_encode};
+
+pub const DOCKER_HUB: &str = "docker.io";
+/// Where Docker Hub's registry API actually lives.
+const DOCKER_HUB_API: &str = "registry-1.docker.io";
+/// The server `docker login` files Docker Hub credentials under.
+const DOCKER_HUB_LOGIN: &str = "https://index.docker.io/v1/";
+
+/// Manifest types we accept, lists first so a multi-arch tag isn't
+/// narrowed down to a single platform.
+const MANIFEST_TYPES: [&str; 4] = [
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+];
+
+/// Page size asked for when listing repositories and tags.
+const PAGE_SIZE: usize = 100;
+
+/// An image reference split the way a registry sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageRef {
+    /// e.g. "localhost:5000", or "docker.io" when none is given.
+    pub registry: String,
+    /// The full repository path, with Docker Hub's implicit "library/".
+    pub repository: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+
+impl ImageRef {
+    pub fn parse(name: &str) -> Result<ImageRef, String> {
+        let invalid = || format!("Invalid image reference '{}'", name);
+        let (rest, digest) = match name.split_once('@') {
+            Some((rest, digest)) if digest.contains(':') => (rest, Some(digest.to_string())),
+            Some(_) => return Err(invalid()),
+            None => (name, None),
+        };
+        let (rest, tag) = match rest.rfind(':') {
+            Some(i) if !rest[i + 1..].contains('/') => (&rest[..i], Some(rest[i + 1..].to_string())),
+            _ => (rest, None),
+        };
+        // Like docker, the first component is a registry only if it looks like a host
+        let (registry, repository) = match rest.split_once('/') {
+            Some((first, path)) if first.contains('.') || first.contains(':') || first == "localhost" => {
+                (first.to_string(), path.to_string())
+            }
+            _ => (DOCKER_HUB.to_string(), rest.to_string()),
+        };
+        let repository = if registry == DOCKER_HUB && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+        if repository.split('/').any(str::is_empty) || tag.as_deref() == Some("") {
+            return Err(invalid());
+        }
+        Ok(ImageRef { registry, repository, tag, digest })
+    }
+
+    /// What to ask the registry for: the digest if pinned, else the tag, else "latest".
+    pub fn reference(&self) -> &str {
+        self.digest.as_deref().or(self.tag.as_deref()).unwrap_or("latest")
+    }
+
+    pub fn same_repository(&self, other: &ImageRef) -> bool {
+        self.registry == other.registry && self.repository == other.repository
+    }
+}
+
+/// The short form docker prints, e.g. "nginx:1.25" or "localhost:5000/app@sha256:...".
+impl fmt::Display for ImageRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.registry == DOCKER_HUB {
+            write!(f, "{}", self.repository.strip_prefix("library/").unwrap_or(&self.repository))?;
+        } else {
+            write!(f, "{}/{}", self.registry, self.repository)?;
+        }
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    Basic { username: String, password: String },
+    /// An OAuth refresh token, which `docker login` stores for some registries.
+    IdentityToken(String),
+}
+
+impl Credentials {
+    /// What `docker login` stored for `registry` in `config_dir`: from the
+    /// credential helper in credHelpers or credsStore if one is set, otherwise
+    /// from `auths`.
+    pub fn from_config(config_dir: &Path, registry: &str) -> Result<Option<Credentials>, DockerError> {
+        let path = config_dir.join("config.json");
+        let config: Value = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?,
+            Err(_) => return Ok(None),
+        };
+        let host = if registry == DOCKER_HUB { login_host(DOCKER_HUB_LOGIN) } else { registry };
+        let entry = |section: &str| {
+            config[section]
+                .as_object()
+                .and_then(|entries| entries.iter().find(|(server, _)| login_host(server) == host))
+        };
+
+        let helper = entry("credHelpers")
+            .and_then(|(_, helper)| helper.as_str())
+            .or_else(|| config["credsStore"].as_str())
+            .filter(|helper| !helper.is_empty());
+        if let Some(helper) = helper {
+            let server = entry("auths")
+                .map(|(server, _)| server.as_str())
+                .unwrap_or(if registry == DOCKER_HUB { DOCKER_HUB_LOGIN } else { registry });
+            return from_helper(helper, server);
+        }
+        Ok(entry("auths").and_then(|(_, auth)| from_auth_entry(auth)))
+    }
+
+    /// The `X-Registry-Auth` value the Engine API takes for pulls from
+    /// `registry`: the credentials as base64url-encoded JSON.
+    pub fn engine_auth(&self, registry: &str) -> String {
+        let server = if registry == DOCKER_HUB { DOCKER_HUB_LOGIN } else { registry };
+        let auth = match self {
+            Credentials::Basic { username, password } => {
+                serde_json::json!({"username": username, "password": password, "serveraddress": server})
+            }
+            Credentials::IdentityToken(token) => serde_json::json!({"identitytoken": token, "serveraddress": server}),
+        };
+        base64_encode(auth.to_string().as_bytes()).replace('+', "-").replace('/', "_")
+    }
+
+    fn basic_header(&self) -> Option<String> {
+        match self {
+            Credentials::Basic { username, password } => {
+                Some(format!("Basic {}", base64_encode(format!("{}:{}", username, password).as_bytes())))
+            }
+            Credentials::IdentityToken(_) => None,
+        }
+    }
+}
+
+// "https://index.docker.io/v1/" and "index.docker.io" are the same login
+fn login_host(server: &str) -> &str {
+    let host = server.trim_start_matches("https://").trim_start_matches("http://");
+    host.split('/').next().unwrap_or(host)
+}
+
+fn from_auth_entry(entry: &Value) -> Option<Credentials> {
+    if let Some(token) = entry["identitytoken"].as_str().filter(|token| !token.is_empty()) {
+        return Some(Credentials::IdentityToken(token.to_string()));
+    }
+    let (username, password) = match entry["auth"].as_str().filter(|auth| !auth.is_empty()) {
+        Some(auth) => {
+            let decoded = String::from_utf8(base64_decode(auth)?).ok()?;
+            let (username, password) = decoded.split_once(':')?;
+            (username.to_string(), password.to_string())
+        }
+        None => (entry["username"].as_str()?.to_string(), entry["password"].as_str()?.to_string()),
+    };
+    Some(Credentials::Basic { username, password })
+}
+
+// Asks `docker-credential-<helper> get`, which reads the server from stdin
+fn from_helper(helper: &str, server: &str) -> Result<Option<Credentials>, DockerError> {
+    let program = format!("docker-credential-{}", helper);
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run credential helper {}: {}", program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(server.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run credential helper {}: {}", program, e))?;
+
+    if !output.status.success() {
+        let message = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        if message.to_lowercase().contains("credentials not found") {
+            return Ok(None);
+        }
+        return Err(DockerError::from_message(format!("{} failed: {}", program, message.trim())));
+    }
+    let reply: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Unexpected reply from {}: {}", program, e))?;
+    let username = reply["Username"].as_str().unwrap_or("");
+    let secret = reply["Secret"].as_str().unwrap_or("");
+    Ok(match username {
+        _ if secret.is_empty() => None,
+        "<token>" => Some(Credentials::IdentityToken(secret.to_string())),
+        _ => Some(Credentials::Basic { username: username.to_string(), password: secret.to_string() }),
+    })
+}
+
+/// A blob or manifest that a manifest points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    /// "os/arch[/variant]", given for the entries of a manifest list.
+    pub platform: Option<String>,
+}
+
+impl Descriptor {
+    fn from_json(value: &Value) -> Descriptor {
+        let platform = value.get("platform").map(|platform| {
+            ["os", "architecture", "variant"]
+                .iter()
+                .filter_map(|key| platform[*key].as_str().filter(|part| !part.is_empty()))
+                .collect::<Vec<_>>()
+                .join("/")
+        });
+        Descriptor {
+            media_type: value["mediaType"].as_str().unwrap_or_default().to_string(),
+            digest: value["digest"].as_str().unwrap_or_default().to_string(),
+            size: value["size"].as_u64().unwrap_or(0),
+            platform,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub digest: String,
+    pub media_type: String,
+    /// One entry per platform when this is a manifest list or OCI index.
+    pub manifests: Vec<Descriptor>,
+    pub config: Option<Descriptor>,
+    pub layers: Vec<Descriptor>,
+}
+
+impl Manifest {
+    fn from_json(digest: String, value: &Value) -> Result<Manifest, DockerError> {
+        // Schema 1 manifests predate content digests and registries stopped serving them
+        if value["schemaVersion"].as_u64() != Some(2) {
+            return Err(format!("Unsupported manifest schema version {} for {}", value["schemaVersion"], digest).into());
+        }
+        let descriptors = |field: &str| -> Vec<Descriptor> {
+            value[field].as_array().into_iter().flatten().map(Descriptor::from_json).collect()
+        };
+        let manifests = descriptors("manifests");
+        // mediaType is optional in OCI documents
+        let media_type = value["mediaType"].as_str().unwrap_or(if value.get("manifests").is_some() {
+            MANIFEST_TYPES[0]
+        } else {
+            MANIFEST_TYPES[2]
+        });
+        Ok(Manifest {
+            digest,
+            media_type: media_type.to_string(),
+            manifests,
+            config: value.get("config").map(Descriptor::from_json),
+            layers: descriptors("layers"),
+        })
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.media_type == MANIFEST_TYPES[0] || self.media_type == MANIFEST_TYPES[1]
+    }
+
+    /// Compressed size of the image's config and layers; zero for a list.
+    pub fn size(&self) -> u64 {
+        self.config.iter().chain(&self.layers).map(|descriptor| descriptor.size).sum()
+    }
+}
+
+/// A client for one registry.
+pub struct RegistryClient {
+    /// As the user wrote it, e.g. "localhost:5000" or "docker.io".
+    registry: String,
+    /// "http://host[:port]" or "https://host[:port]".
+    base: String,
+    credentials: Option<Credentials>,
+    /// The last Authorization header that worked, tried first next time.
+    authorization: RefCell<Option<String>>,
+}
+
+impl RegistryClient {
+    /// A client for `registry` using what `docker login` stored for it.
+    /// Local registries, and any others with `insecure`, get plain HTTP.
+    pub fn for_registry(registry: &str, insecure: bool) -> Result<RegistryClient, DockerError> {
+        let credentials = match context::config_dir() {
+            Some(dir) => Credentials::from_config(&dir, registry)?,
+            None => None,
+        };
+        let host = if registry == DOCKER_HUB { DOCKER_HUB_API } else { registry };
+        let scheme = if insecure || is_local(host) { "http" } else { "https" };
+        Ok(RegistryClient::new(registry, &format!("{}://{}", scheme, host), credentials))
+    }
+
+    pub fn new(registry: &str, base: &str, credentials: Option<Credentials>) -> RegistryClient {
+        RegistryClient {
+            registry: registry.to_string(),
+            base: base.trim_end_matches('/').to_string(),
+            credentials,
+            authorization: RefCell::new(None),
+        }
+    }
+
+    /// Every repository in the catalog. Docker Hub and some hosted
+    /// registries don't offer one.
+    pub fn repositories(&self) -> Result<Vec<String>, DockerError> {
+        self.paged(&format!("/v2/_catalog?n={}", PAGE_SIZE), "repositories")
+    }
+
+    pub fn tags(&self, repository: &str) -> Result<Vec<String>, DockerError> {
+        self.paged(&format!("/v2/{}/tags/list?n={}", repository, PAGE_SIZE), "tags")
+    }
+
+    /// The manifest `reference` (a tag or digest) points to, as stored: a
+    /// manifest list for a multi-arch tag, otherwise an image manifest.
+    pub fn manifest(&self, repository: &str, reference: &str) -> Result<Manifest, DockerError> {
+        let response = self.request("GET", &manifest_path(repository, reference), &[("Accept", MANIFEST_TYPES.join(", "))])?;
+        let response = self.manifest_found(response, repository, reference)?;
+        let digest = self.content_digest(&response, repository, reference)?;
+        Manifest::from_json(digest, &response.json()?)
+    }
+
+    /// The digest `reference` resolves to, without downloading the manifest.
+    pub fn digest(&self, repository: &str, reference: &str) -> Result<String, DockerError> {
+        let response = self.request("HEAD", &manifest_path(repository, reference), &[("Accept", MANIFEST_TYPES.join(", "))])?;
+        let response = self.manifest_found(response, repository, reference)?;
+        self.content_digest(&response, repository, reference)
+    }
+
+    /// Deletes the manifest `reference` points to, which also untags every
+    /// other tag of it. Returns the deleted digest.
+    pub fn delete(&self, repository: &str, reference: &str) -> Result<String, DockerError> {
+        let digest = if reference.contains(':') {
+            reference.to_string()
+        } else {
+            self.digest(repository, reference)?
+        };
+        let response = self.request("DELETE", &manifest_path(repository, &digest), &[])?;
+        if response.status == 405 {
+            return Err(format!(
+                "{} doesn't allow deletes; a registry:2 needs REGISTRY_STORAGE_DELETE_ENABLED=true",
+                self.registry
+            )
+            .into());
+        }
+        self.manifest_found(response, repository, &digest)?.bytes()?;
+        Ok(digest)
+    }
+
+    // Follows `Link: <...>; rel="next"` headers, collecting `field` from each page
+    fn paged(&self, path: &str, field: &str) -> Result<Vec<String>, DockerError> {
+        let mut items = Vec::new();
+        let mut next = Some(path.to_string());
+        while let Some(path) = next.take() {
+            let response = self.call("GET", &path)?;
+            next = response.header("Link").and_then(next_page).filter(|next| *next != path);
+            // A repository without tags has "tags": null
+            let page = response.json()?;
+            items.extend(page[field].as_array().into_iter().flatten().filter_map(|item| item.as_str()).map(str::to_string));
+        }
+        Ok(items)
+    }
+
+    fn call(&self, method: &str, path: &str) -> Result<Response, DockerError> {
+        let response = self.request(method, path, &[])?;
+        self.check(response)
+    }
+
+    // A manifest request's 404, which has no body to explain it for HEAD
+    fn manifest_found(&self, response: Response, repository: &str, reference: &str) -> Result<Response, DockerError> {
+        if response.status == 404 {
+            let separator = if reference.contains(':') { "@" } else { ":" };
+            return Err(DockerError::ImageNotFound(format!(
+                "Error response from registry: manifest for {}/{}{}{} not found",
+                self.registry, repository, separator, reference
+            )));
+        }
+        self.check(response)
+    }
+
+    fn content_digest(&self, response: &Response, repository: &str, reference: &str) -> Result<String, DockerError> {
+        match response.header("Docker-Content-Digest") {
+            Some(digest) => Ok(digest.to_string()),
+            None if reference.contains(':') => Ok(reference.to_string()),
+            None => Err(format!("{} didn't return a digest for {}:{}", self.registry, repository, reference).into()),
+        }
+    }
+
+    // Turns non-2xx replies into the registry's error message
+    fn check(&self, response: Response) -> Result<Response, DockerError> {
+        if response.is_success() {
+            return Ok(response);
+        }
+        let status = response.status;
+        let data = response.bytes().unwrap_or_default();
+        // {"errors":[{"code":"NAME_UNKNOWN","message":"repository name not known to registry"}]}
+        let message = serde_json::from_slice::<Value>(&data)
+            .ok()
+            .map(|reply| {
+                reply["errors"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|error| error["message"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| String::from_utf8_lossy(&data).trim().to_string());
+        match status {
+            401 => Err(format!("{} requires authentication ({}); run 'docker login {}'", self.registry, message, self.registry).into()),
+            _ if message.is_empty() => Err(format!("Registry request failed with status {}", status).into()),
+            _ => Err(format!("Error response from registry: {}", message).into()),
+        }
+    }
+
+    // Sends a request, answering the registry's authentication challenge once if it makes one
+    fn request(&self, method: &str, path: &str, headers: &[(&str, String)]) -> Result<Response, DockerError> {
+        let url = format!("{}{}", self.base, path);
+        let cached = self.authorization.borrow().clone();
+        let response = send(method, &url, headers, cached.as_deref(), None)?;
+        if response.status != 401 {
+            return Ok(response);
+        }
+        let challenge = match response.header("WWW-Authenticate").and_then(Challenge::parse) {
+            Some(challenge) => challenge,
+            None => return Ok(response),
+        };
+        let authorization = match self.authorize(&challenge)? {
+            Some(authorization) if Some(&authorization) != cached.as_ref() => authorization,
+            _ => return Ok(response),
+        };
+        let response = send(method, &url, headers, Some(&authorization), None)?;
+        if response.status != 401 {
+            *self.authorization.borrow_mut() = Some(authorization);
+        }
+        Ok(response)
+    }
+
+    // The Authorization header answering `challenge`, if we have what it takes
+    fn authorize(&self, challenge: &Challenge) -> Result<Option<String>, DockerError> {
+        match challenge.scheme.as_str() {
+            "basic" => Ok(self.credentials.as_ref().and_then(Credentials::basic_header)),
+            "bearer" => self.token(challenge).map(|token| Some(format!("Bearer {}", token))),
+            _ => Ok(None),
+        }
+    }
+
+    // Gets a token from the challenge's realm, anonymously if there are no credentials
+    fn token(&self, challenge: &Challenge) -> Result<String, DockerError> {
+        let realm = challenge
+            .params
+            .get("realm")
+            .ok_or_else(|| format!("{} asked for a token without saying where to get one", self.registry))?;
+        let params: Vec<String> = ["service", "scope"]
+            .iter()
+            .filter_map(|name| challenge.params.get(*name).map(|value| format!("{}={}", name, form_encode(value))))
+            .collect();
+
+        let response = match &self.credentials {
+            Some(Credentials::IdentityToken(token)) => {
+                let mut form = vec![
+                    "grant_type=refresh_token".to_string(),
+                    "client_id=dui".to_string(),
+                    format!("refresh_token={}", form_encode(token)),
+                ];
+                form.extend(params);
+                let content_type = [("Content-Type", "application/x-www-form-urlencoded".to_string())];
+                send("POST", realm, &content_type, None, Some(&form.join("&")))?
+            }
+            credentials => {
+                let separator = if realm.contains('?') { "&" } else { "?" };
+                let url = if params.is_empty() { realm.clone() } else { format!("{}{}{}", realm, separator, params.join("&")) };
+                let authorization = credentials.as_ref().and_then(Credentials::basic_header);
+                send("GET", &url, &[], authorization.as_deref(), None)?
+            }
+        };
+        if response.status == 401 || response.status == 403 {
+            return Err(format!("{} rejected the stored credentials; run 'docker login {}'", self.registry, self.registry).into());
+        }
+        let reply = self.check(response)?.json()?;
+        reply["token"]
+            .as_str()
+            .or_else(|| reply["access_token"].as_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("No token in the reply from {}", realm).into())
+    }
+}
+
+// Docker talks plain HTTP to these without being told to
+fn is_local(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "localhost" | "[::1]") || name.starts_with("127.")
+}
+
+fn manifest_path(repository: &str, reference: &str) -> String {
+    format!("/v2/{}/manifests/{}", repository, reference)
+}
+
+// The path of a `Link: </v2/_catalog?last=x&n=100>; rel="next"` header
+fn next_page(link: &str) -> Option<String> {
+    let (target, params) = link.trim().strip_prefix('<')?.split_once('>')?;
+    if !params.contains("next") {
+        return None;
+    }
+    match target.strip_prefix("http://").or_else(|| target.strip_prefix("https://")) {
+        Some(url) => url.find('/').map(|i| url[i..].to_string()),
+        None => Some(target.to_string()),
+    }
+}
+
+fn form_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// A `WWW-Authenticate` challenge, e.g.
+/// `Bearer realm="https://auth.example.com/token",service="registry",scope="repository:app:pull"`.
+#[derive(Debug, PartialEq, Eq)]
+struct Challenge {
+    scheme: String,
+    params: BTreeMap<String, String>,
+}
+
+impl Challenge {
+    fn parse(header: &str) -> Option<Challenge> {
+        let header = header.trim();
+        let (scheme, mut rest) = header.split_once(' ').unwrap_or((header, ""));
+        let mut params = BTreeMap::new();
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+            let (key, after) = match rest.split_once('=') {
+                Some(pair) => pair,
+                None => break,
+            };
+            // Quoted values may contain commas, as in "repository:app:pull,push"
+            let (value, remainder) = match after.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"')?;
+                    (&quoted[..end], &quoted[end + 1..])
+                }
+                None => after.split_once(',').unwrap_or((after, "")),
+            };
+            params.insert(key.trim().to_lowercase(), value.trim().to_string());
+            rest = remainder;
+        }
+        Some(Challenge { scheme: scheme.to_lowercase(), params })
+    }
+}
+
+// One HTTP exchange: plain HTTP over a socket, HTTPS through curl
+fn send(method: &str, url: &str, headers: &[(&str, String)], authorization: Option<&str>, body: Option<&str>) -> Result<Response, DockerError> {
+    let mut headers = headers.to_vec();
+    if let Some(authorization) = authorization {
+        headers.push(("Authorization", authorization.to_string()));
+    }
+    match url.strip_prefix("http://") {
+        Some(address) => send_plain(method, address, &headers, body),
+        None => send_curl(method, url, &headers, body),
+    }
+}
+
+fn send_plain(method: &str, address: &str, headers: &[(&str, String)], body: Option<&str>) -> Result<Response, DockerError> {
+    let (host, path) = match address.find('/') {
+        Some(i) => (&address[..i], &address[i..]),
+        None => (address, "/"),
+    };
+    let target = match host.rsplit_once(':') {
+        Some((_, port)) if !port.ends_with(']') => host.to_string(),
+        _ => format!("{}:80", host),
+    };
+    let mut stream = TcpStream::connect(&target)
+        .map_err(|e| format!("Cannot connect to registry at http://{}: {}", host, e))?;
+
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: dui/{}\r\nConnection: close\r\n",
+        method,
+        path,
+        host,
+        env!("CARGO_PKG_VERSION")
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    let body = body.unwrap_or("");
+    if method == "POST" || method == "PUT" || !body.is_empty() {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    head.push_str("\r\n");
+    head.push_str(body);
+
+    stream
+        .write_all(head.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("Failed to send request to registry: {}", e))?;
+    read_response(BufReader::new(stream), method == "HEAD")
+}
+
+fn send_curl(method: &str, url: &str, headers: &[(&str, String)], body: Option<&str>) -> Result<Response, DockerError> {
+    // Everything goes in as a config on stdin, so credentials never show up in `ps`
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut config = format!("url = {}\n", quote(url));
+    config.push_str(&format!("user-agent = {}\n", quote(&format!("dui/{}", env!("CARGO_PKG_VERSION")))));
+    match method {
+        "HEAD" => config.push_str("head\n"),
+        "GET" => {}
+        "POST" if body.is_some() => {}
+        _ => config.push_str(&format!("request = {}\n", quote(method))),
+    }
+    for (name, value) in headers {
+        config.push_str(&format!("header = {}\n", quote(&format!("{}: {}", name, value))));
+    }
+    if let Some(body) = body {
+        config.push_str(&format!("data-binary = {}\n", quote(body)));
+    }
+
+    // --raw keeps chunked bodies framed, as read_response expects
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--include", "--raw", "--http1.1", "--suppress-connect-headers", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => "curl is required for HTTPS registries; install it and make sure it is on PATH".to_string(),
+            _ => format!("HTTPS registries are reached through curl, which failed to start: {}", e),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config.as_bytes())
+            .map_err(|e| format!("Failed to send request to curl: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(DockerError::from_message(format!(
+            "Cannot reach registry at {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    read_response(Cursor::new(output.stdout), method == "HEAD")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestStatus {
+    UpToDate,
+    /// The tag has moved on since the local image was pulled.
+    Outdated,
+    /// The local image has no digest from this repository, e.g. it was built
+    /// here and never pushed.
+    Unpushed,
+    /// There's no local image by that name.
+    Missing,
+}
+
+impl DigestStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DigestStatus::UpToDate => "up to date",
+            DigestStatus::Outdated => "outdated",
+            DigestStatus::Unpushed => "not pulled from this repository",
+            DigestStatus::Missing => "not present locally",
+        }
+    }
+}
+
+/// A tag's digest in the registry next to what the local image was pulled as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestComparison {
+    pub remote: String,
+    /// The local image's digests from the same repository; None without a local image.
+    pub local: Option<Vec<String>>,
+}
+
+impl DigestComparison {
+    pub fn status(&self) -> DigestStatus {
+        match &self.local {
+            None => DigestStatus::Missing,
+            Some(local) if local.is_empty() => DigestStatus::Unpushed,
+            Some(local) if local.contains(&self.remote) => DigestStatus::UpToDate,
+            Some(_) => DigestStatus::Outdated,
+        }
+    }
+}
+
+/// Resolves `image` in the registry and compares it with the repo digests
+/// of the local image of the same name.
+pub fn compare(docker: &DockerClient, client: &RegistryClient, image: &ImageRef) -> Result<DigestComparison, DockerError> {
+    let remote = client.digest(&image.repository, image.reference())?;
+    let name = image.to_string();
+    if !docker.image_exists(&name)? {
+        return Ok(DigestComparison { remote, local: None });
+    }
+    let local = local_digests(docker, &name, image)?;
+    Ok(DigestComparison { remote, local: Some(local) })
+}
+
+/// The digests the local image `name` (a tag or ID) has from `image`'s repository.
+pub fn local_digests(docker: &DockerClient, name: &str, image: &ImageRef) -> Result<Vec<String>, DockerError> {
+    Ok(docker
+        .image_repo_digests(name)?
+        .iter()
+        .filter_map(|entry| ImageRef::parse(entry).ok())
+        .filter(|entry| entry.same_repository(image))
+        .filter_map(|entry| entry.digest)
+        .collect())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{BufRead, Read};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+    use crate::docker::Image;
+    use crate::fake::FakeBackend;
+
+    /// Serves one canned response per connection on a local port, built
+    /// from its address, and returns the request lines seen with the
+    /// Authorization header and any body appended.
+    pub(crate) fn serve(responses: impl FnOnce(&str) -> Vec<String>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let responses = responses(&address);
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut seen = request_line.trim().trim_end_matches(" HTTP/1.1").to_string();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_once(':').unwrap();
+                    match name.to_ascii_lowercase().as_str() {
+                        "authorization" => seen.push_str(&format!(" [{}]", value.trim())),
+                        "content-length" => content_length = value.trim().parse().unwrap(),
+                        _ => {}
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                if !body.is_empty() {
+                    seen.push_str(&format!(" {}", String::from_utf8_lossy(&body)));
+                }
+                requests.push(seen);
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (address, handle)
+    }
+
+    pub(crate) fn response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+        let mut text = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+        for (name, value) in headers {
+            text.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        format!("{}\r\n{}", text, body)
+    }
+
+    #[test]
+    fn test_parse_image_ref() {
+        let nginx = ImageRef::parse("nginx").unwrap();
+        assert_eq!((nginx.registry.as_str(), nginx.repository.as_str(), nginx.reference()), ("docker.io", "library/nginx", "latest"));
+        assert_eq!(nginx.to_string(), "nginx");
+
+        let app = ImageRef::parse("localhost:5000/team/app:1.0").unwrap();
+        assert_eq!((app.registry.as_str(), app.repository.as_str(), app.reference()), ("localhost:5000", "team/app", "1.0"));
+        assert_eq!(app.to_string(), "localhost:5000/team/app:1.0");
+
+        let pinned = ImageRef::parse("user/tool:2@sha256:abc").unwrap();
+        assert_eq!((pinned.repository.as_str(), pinned.reference()), ("user/tool", "sha256:abc"));
+        assert!(pinned.same_repository(&ImageRef::parse("docker.io/user/tool@sha256:def").unwrap()));
+        assert!(ImageRef::parse("app:").is_err());
+        assert!(ImageRef::parse("app@latest").is_err());
+    }
+
+    #[test]
+    fn test_credentials_from_config() {
+        let dir = std::env::temp_dir().join(format!("dui-registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("config.json"),
+            r#"{"auths": {
+                "localhost:5000": {"auth": "dXNlcjpwYXNz"},
+                "https://index.docker.io/v1/": {"auth": "", "identitytoken": "refresh-me"}
+            }}"#,
+        )
+        .unwrap();
+
+        let basic = Credentials::Basic { username: "user".to_string(), password: "pass".to_string() };
+        assert_eq!(Credentials::from_config(&dir, "localhost:5000").unwrap(), Some(basic));
+        assert_eq!(Credentials::from_config(&dir, DOCKER_HUB).unwrap(), Some(Credentials::IdentityToken("refresh-me".to_string())));
+        assert_eq!(Credentials::from_config(&dir, "ghcr.io").unwrap(), None);
+        let _ = fs::remove_dir_all(&dir);
+
+        let auth = base64_decode(&Credentials::IdentityToken("refresh-me".to_string()).engine_auth(DOCKER_HUB)).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&auth).unwrap(),
+            serde_json::json!({"identitytoken": "refresh-me", "serveraddress": DOCKER_HUB_LOGIN})
+        );
+
+        let challenge = Challenge::parse(r#"Bearer realm="http://auth/token",service="registry",scope="repository:app:pull,delete""#).unwrap();
+        assert_eq!(challenge.scheme, "bearer");
+        assert_eq!(challenge.params["scope"], "repository:app:pull,delete");
+    }
+
+    #[test]
+    fn test_bearer_auth_and_paged_tags() {
+        let (address, server) = serve(|address| {
+            let challenge = format!(r#"Bearer realm="http://{}/token",service="local",scope="repository:app:pull""#, address);
+            vec![
+                response("401 Unauthorized", &[("WWW-Authenticate", &challenge)], r#"{"errors":[{"code":"UNAUTHORIZED"}]}"#),
+                response("200 OK", &[], r#"{"token":"t0k3n"}"#),
+                response("200 OK", &[("Link", r#"</v2/app/tags/list?last=1.0&n=100>; rel="next""#)], r#"{"name":"app","tags":["1.0"]}"#),
+                response("200 OK", &[], r#"{"name":"app","tags":["latest"]}"#),
+            ]
+        });
+        let credentials = Credentials::Basic { username: "user".to_string(), password: "pass".to_string() };
+        let client = RegistryClient::new(&address, &format!("http://{}", address), Some(credentials));
+
+        assert_eq!(client.tags("app").unwrap(), vec!["1.0", "latest"]);
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "GET /v2/app/tags/list?n=100".to_string(),
+                "GET /token?service=local&scope=repository%3Aapp%3Apull [Basic dXNlcjpwYXNz]".to_string(),
+                "GET /v2/app/tags/list?n=100 [Bearer t0k3n]".to_string(),
+                "GET /v2/app/tags/list?last=1.0&n=100 [Bearer t0k3n]".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_manifest_list_and_delete() {
+        let index = r#"{"schemaVersion":2,"mediaType":"application/vnd.oci.image.index.v1+json","manifests":[
+            {"mediaType":"application/vnd.oci.image.manifest.v1+json","digest":"sha256:aaa","size":1200,"platform":{"os":"linux","architecture":"amd64"}},
+            {"mediaType":"application/vnd.oci.image.manifest.v1+json","digest":"sha256:bbb","size":1200,"platform":{"os":"linux","architecture":"arm64","variant":"v8"}}]}"#;
+        let (address, server) = serve(|_| {
+            vec![
+                response("200 OK", &[("Docker-Content-Digest", "sha256:111")], index),
+                response("200 OK", &[("Docker-Content-Digest", "sha256:111")], ""),
+                response("202 Accepted", &[], ""),
+                response("405 Method Not Allowed", &[], r#"{"errors":[{"code":"UNSUPPORTED","message":"The operation is unsupported."}]}"#),
+                response("404 Not Found", &[], ""),
+            ]
+        });
+        let client = RegistryClient::new(&address, &format!("http://{}", address), None);
+
+        let manifest = client.manifest("app", "1.0").unwrap();
+        assert!(manifest.is_list());
+        assert_eq!(manifest.digest, "sha256:111");
+        let platforms: Vec<_> = manifest.manifests.iter().map(|entry| entry.platform.as_deref().unwrap()).collect();
+        assert_eq!(platforms, vec!["linux/amd64", "linux/arm64/v8"]);
+
+        assert_eq!(client.delete("app", "1.0").unwrap(), "sha256:111");
+        assert!(client.delete("app", "sha256:222").unwrap_err().message().contains("REGISTRY_STORAGE_DELETE_ENABLED"));
+        assert!(matches!(client.digest("app", "gone"), Err(DockerError::ImageNotFound(_))));
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "GET /v2/app/manifests/1.0",
+                "HEAD /v2/app/manifests/1.0",
+                "DELETE /v2/app/manifests/sha256:111",
+                "DELETE /v2/app/manifests/sha256:222",
+                "HEAD /v2/app/manifests/gone",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_with_local_digests() {
+        let (address, server) = serve(|_| {
+            vec![
+                response("200 OK", &[("Docker-Content-Digest", "sha256:new")], ""),
+                response("200 OK", &[("Docker-Content-Digest", "sha256:new")], ""),
+                response("200 OK", &[("Docker-Content-Digest", "sha256:new")], ""),
+            ]
+        });
+        let image = |name: &str| ImageRef::parse(&format!("{}/{}", address, name)).unwrap();
+        let fake = FakeBackend::new()
+            .with_image(Image {
+                id: "0a1b2c3d4e5f".to_string(),
+                repository: format!("{}/app", address),
+                tag: "1.0".to_string(),
+                size: "12MB".to_string(),
+                created: String::new(),
+            })
+            .with_repo_digest(&format!("{}/app:1.0", address), "nginx@sha256:new")
+            .with_repo_digest(&format!("{}/app:1.0", address), &format!("{}/app@sha256:old", address));
+        let docker = DockerClient::with_backend(Arc::new(fake));
+        let client = RegistryClient::new(&address, &format!("http://{}", address), None);
+
+        // Only the digest from the same repository counts
+        let outdated = compare(&docker, &client, &image("app:1.0")).unwrap();
+        assert_eq!(outdated.local, Some(vec!["sha256:old".to_string()]));
+        assert_eq!(outdated.status(), DigestStatus::Outdated);
+        assert_eq!(compare(&docker, &client, &image("other:1.0")).unwrap().status(), DigestStatus::Missing);
+        let pinned = compare(&docker, &client, &image("app:1.0@sha256:new")).unwrap();
+        assert_eq!(pinned.remote, "sha256:new");
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,533 @@
+// Container runtime detection and JSON normalisation.
+//
+// Podman ships a Docker-compatible CLI and socket, but `podman ... --format json`
+// prints one JSON array instead of one object per line, and several keys differ
+// (`Id` vs `ID`, `Names` as an array, lowercase stats keys, structured ports).
+// The record helpers below accept either shape.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::Value;
+use crate::docker::{Container, ContainerStats, Image, Network, Volume};
+use crate::engine::split_reference;
+use crate::utils::{decimal_size, format_timestamp, parse_percent, parse_size_pair};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runtime {
+    Docker,
+    Podman,
+}
+
+impl Runtime {
+    /// Picks the runtime from DUI_RUNTIME, DOCKER_HOST, or the binaries on PATH.
+    /// A `docker` that is really the podman-docker shim counts as Podman.
+    pub fn detect() -> Runtime {
+        match std::env::var("DUI_RUNTIME").ok().as_deref() {
+            Some("podman") => return Runtime::Podman,
+            Some("docker") => return Runtime::Docker,
+            _ => {}
+        }
+        if std::env::var("DOCKER_HOST").is_ok_and(|host| host.contains("podman")) {
+            return Runtime::Podman;
+        }
+        match find_in_path("docker") {
+            Some(docker) if is_podman_shim(&docker) => Runtime::Podman,
+            Some(_) => Runtime::Docker,
+            None if find_in_path("podman").is_some() => Runtime::Podman,
+            None => Runtime::Docker,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Runtime::Docker => "Docker",
+            Runtime::Podman => "Podman",
+        }
+    }
+
+    /// Binary to run for CLI operations.
+    pub fn program(self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Podman => "podman",
+        }
+    }
+
+    /// The first API socket that exists for this runtime.
+    pub fn default_socket(self) -> Option<PathBuf> {
+        let mut candidates = Vec::new();
+        if self == Runtime::Podman {
+            if let Ok(dir) = std::env::var("XDG_RUNTIME_DIR") {
+                candidates.push(PathBuf::from(dir).join("podman").join("podman.sock"));
+            }
+            candidates.push(PathBuf::from("/run/podman/podman.sock"));
+        }
+        candidates.push(PathBuf::from("/var/run/docker.sock"));
+        candidates.into_iter().find(|path| path.exists())
+    }
+
+    /// Translates docker global flags into the runtime's own spelling.
+    pub fn cli_args(self, args: &[String]) -> Vec<String> {
+        match self {
+            Runtime::Docker => args.to_vec(),
+            Runtime::Podman => args
+                .iter()
+                .map(|arg| match arg.as_str() {
+                    "--host" => "--url".to_string(),
+                    "--context" => "--connection".to_string(),
+                    _ => arg.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .flat_map(|dir| {
+            let candidate = dir.join(program);
+            [candidate.with_extension("exe"), candidate]
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+// podman-docker installs `docker` as a symlink to podman or a small wrapper script
+fn is_podman_shim(path: &Path) -> bool {
+    if let Ok(target) = fs::canonicalize(path) {
+        if target.file_name().is_some_and(|name| name.to_string_lossy().contains("podman")) {
+            return true;
+        }
+    }
+    let mut head = [0u8; 512];
+    match fs::File::open(path).and_then(|mut file| file.read(&mut head)) {
+        Ok(read) => head[..read].starts_with(b"#!") && String::from_utf8_lossy(&head[..read]).contains("podman"),
+        Err(_) => false,
+    }
+}
+
+/// Splits `--format json` output into records: one object per line (Docker)
+/// or a single array (Podman).
+pub fn json_records(output: &str, kind: &str) -> Vec<Value> {
+    let trimmed = output.trim();
+    if trimmed.starts_with('[') {
+        return match serde_json::from_str::<Value>(trimmed) {
+            Ok(Value::Array(records)) => records,
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                eprintln!("Failed to parse {} JSON: {}", kind, e);
+                Vec::new()
+            }
+        };
+    }
+
+    let mut records = Vec::new();
+    for line in trimmed.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Value>(line) {
+            Ok(json) => records.push(json),
+            Err(e) => eprintln!("Failed to parse {} JSON: {} for line: {}", kind, e, line),
+        }
+    }
+    records
+}
+
+/// The first of `keys` that holds a string or number.
+pub fn text_field(json: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| match json.get(*key)? {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    })
+}
+
+/// Container ID from `ID` (docker CLI) or `Id` (API, podman).
+pub fn container_id(json: &Value) -> Option<String> {
+    text_field(json, &["ID", "Id", "id"])
+}
+
+/// Container name from `Names` as a string or an array, without the API's leading slash.
+pub fn container_name(json: &Value) -> Option<String> {
+    let name = match json.get("Names")? {
+        Value::String(names) => names.split(',').next().unwrap_or("").to_string(),
+        Value::Array(names) => names.first()?.as_str()?.to_string(),
+        _ => return None,
+    };
+    Some(name.trim_start_matches('/').to_string())
+}
+
+pub fn container_from_record(json: &Value) -> Option<Container> {
+    // Podman may leave Status empty and only fill State
+    let status = text_field(json, &["Status"])
+        .filter(|status| !status.is_empty())
+        .or_else(|| text_field(json, &["State"]))?;
+
+    Some(Container {
+        id: container_id(json)?,
+        name: container_name(json)?,
+        image: text_field(json, &["Image"])?,
+        status,
+        ports: record_ports(json.get("Ports")),
+        labels: record_labels(json.get("Labels")),
+    })
+}
+
+/// Labels from an object (API, podman) or docker's "key=value,key=value" string.
+pub fn record_labels(labels: Option<&Value>) -> BTreeMap<String, String> {
+    match labels {
+        Some(Value::Object(labels)) => labels
+            .iter()
+            .map(|(key, value)| (key.clone(), value.as_str().unwrap_or_default().to_string()))
+            .collect(),
+        Some(Value::String(labels)) => labels
+            .split(',')
+            .filter(|label| !label.is_empty())
+            .map(|label| match label.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => (label.to_string(), String::new()),
+            })
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+// Ports are a preformatted string from docker and structured objects from podman
+fn record_ports(ports: Option<&Value>) -> String {
+    match ports {
+        Some(Value::String(ports)) => ports.clone(),
+        Some(Value::Array(ports)) => ports
+            .iter()
+            .map(|port| {
+                let container_port = text_field(port, &["container_port"]).unwrap_or_default();
+                let protocol = text_field(port, &["protocol"]).unwrap_or_else(|| "tcp".to_string());
+                match text_field(port, &["host_port"]) {
+                    Some(host_port) if host_port != "0" => {
+                        let host_ip = text_field(port, &["host_ip"]).filter(|ip| !ip.is_empty());
+                        format!(
+                            "{}:{}->{}/{}",
+                            host_ip.as_deref().unwrap_or("0.0.0.0"),
+                            host_port,
+                            container_port,
+                            protocol
+                        )
+                    }
+                    _ => format!("{}/{}", container_port, protocol),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => String::new(),
+    }
+}
+
+/// Docker prints one row per tag; podman lists every tag of an image in `Names`.
+pub fn images_from_record(json: &Value) -> Vec<Image> {
+    let id = match container_id(json) {
+        Some(id) => id.trim_start_matches("sha256:").to_string(),
+        None => return Vec::new(),
+    };
+    let size = match json.get("Size") {
+        Some(Value::Number(bytes)) => decimal_size(bytes.as_f64().unwrap_or(0.0)),
+        Some(Value::String(size)) => size.clone(),
+        _ => String::new(),
+    };
+    let created = match json.get("Created") {
+        Some(Value::Number(secs)) if json.get("CreatedAt").is_none() => format_timestamp(secs.as_i64().unwrap_or(0)),
+        _ => text_field(json, &["CreatedAt", "Created"]).unwrap_or_default(),
+    };
+
+    if let (Some(repository), Some(tag)) = (text_field(json, &["Repository"]), text_field(json, &["Tag"])) {
+        return vec![Image { id, repository, tag, size, created }];
+    }
+
+    let names: Vec<String> = json
+        .get("Names")
+        .and_then(|names| names.as_array())
+        .map(|names| names.iter().filter_map(|name| name.as_str().map(|name| name.to_string())).collect())
+        .unwrap_or_default();
+    if names.is_empty() {
+        return vec![Image {
+            id,
+            repository: "<none>".to_string(),
+            tag: "<none>".to_string(),
+            size,
+            created,
+        }];
+    }
+    names
+        .iter()
+        .map(|name| {
+            let (repository, tag) = split_reference(name);
+            Image { id: id.clone(), repository, tag, size: size.clone(), created: created.clone() }
+        })
+        .collect()
+}
+
+/// The CLI only prints formatted strings, so the numbers are parsed back out.
+/// `online_cpus` comes from `docker info` since stats rows don't carry it.
+pub fn stats_from_record(json: &Value, online_cpus: u32) -> Option<ContainerStats> {
+    let cpu_percent = parse_percent(&text_field(json, &["CPUPerc", "cpu_percent"])?).unwrap_or(0.0);
+    let (memory_used, memory_limit) = parse_size_pair(&text_field(json, &["MemUsage", "mem_usage"])?).unwrap_or_default();
+    let (net_rx, net_tx) = parse_size_pair(&text_field(json, &["NetIO", "net_io"])?).unwrap_or_default();
+    let (block_read, block_write) = parse_size_pair(&text_field(json, &["BlockIO", "block_io"])?).unwrap_or_default();
+    Some(ContainerStats {
+        name: text_field(json, &["Name", "name"])?,
+        cpu_fraction: cpu_percent / 100.0,
+        online_cpus,
+        memory_used,
+        memory_limit,
+        net_rx,
+        net_tx,
+        block_read,
+        block_write,
+    })
+}
+
+/// How long a refresh may go quiet before the rows seen so far are taken
+/// as all of it. A refresh is printed in one go, so this only has to cover
+/// the gap between its lines.
+const STATS_FRAME_GAP: Duration = Duration::from_millis(250);
+
+/// Splits streaming `stats --format json` output into one batch per refresh.
+/// Docker clears the screen before each refresh and prints a line per
+/// container; Podman prints a JSON array each time. A Docker refresh is
+/// handed over as soon as every container from the last one has reported,
+/// or once the output goes quiet, so a stopped container can't hold it back.
+pub struct StatsBatches {
+    lines: mpsc::Receiver<String>,
+    online_cpus: u32,
+    batch: Vec<ContainerStats>,
+    array: String,
+    /// The last batch handed over, and until when late rows still belong to it.
+    last: Vec<ContainerStats>,
+    amend_until: Option<Instant>,
+}
+
+impl StatsBatches {
+    pub fn new(lines: impl Iterator<Item = String> + Send + 'static, online_cpus: u32) -> Self {
+        // Read on a thread so a refresh can be handed over without waiting for the next one
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in lines {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        StatsBatches {
+            lines: receiver,
+            online_cpus,
+            batch: Vec::new(),
+            array: String::new(),
+            last: Vec::new(),
+            amend_until: None,
+        }
+    }
+
+    fn flush(&mut self) -> Vec<ContainerStats> {
+        self.last = std::mem::take(&mut self.batch);
+        self.amend_until = Some(Instant::now() + STATS_FRAME_GAP);
+        self.last.clone()
+    }
+
+    fn covers_last(&self) -> bool {
+        !self.last.is_empty() && self.last.iter().all(|seen| self.batch.iter().any(|stats| stats.name == seen.name))
+    }
+}
+
+impl Iterator for StatsBatches {
+    type Item = Vec<ContainerStats>;
+
+    fn next(&mut self) -> Option<Vec<ContainerStats>> {
+        loop {
+            let received = if self.batch.is_empty() && self.amend_until.is_none() {
+                self.lines.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected)
+            } else {
+                self.lines.recv_timeout(STATS_FRAME_GAP)
+            };
+            let line = match received {
+                Ok(line) => line,
+                Err(mpsc::RecvTimeoutError::Timeout) if self.batch.is_empty() => {
+                    self.amend_until = None;
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => return Some(self.flush()),
+                Err(mpsc::RecvTimeoutError::Disconnected) if self.batch.is_empty() => return None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Some(self.flush()),
+            };
+            let cleared = line.contains("\x1b[2J");
+            let text = line.replace("\x1b[2J", "").replace("\x1b[H", "");
+
+            if !self.array.is_empty() || text.trim_start().starts_with('[') {
+                self.array.push_str(&text);
+                match serde_json::from_str::<Value>(&self.array) {
+                    Err(e) if e.is_eof() => continue,
+                    Ok(Value::Array(records)) => {
+                        self.array.clear();
+                        return Some(records.iter().filter_map(|record| stats_from_record(record, self.online_cpus)).collect());
+                    }
+                    _ => self.array.clear(),
+                }
+                continue;
+            }
+
+            let stats = match serde_json::from_str(&text).ok().and_then(|record| stats_from_record(&record, self.online_cpus)) {
+                Some(stats) => stats,
+                None => continue,
+            };
+            // A container that wasn't in the refresh just handed over, arriving
+            // right behind it, was still part of it: hand it over again with it
+            let amending = self.amend_until.take().is_some_and(|until| Instant::now() < until);
+            if amending && self.batch.is_empty() && !cleared && !self.last.iter().any(|seen| seen.name == stats.name) {
+                self.batch = std::mem::take(&mut self.last);
+                self.batch.push(stats);
+                return Some(self.flush());
+            }
+            // A cleared screen or a container seen twice starts the next refresh
+            let repeated = self.batch.iter().any(|seen| seen.name == stats.name);
+            if (cleared || repeated) && !self.batch.is_empty() {
+                let done = self.flush();
+                self.batch.push(stats);
+                self.amend_until = None;
+                return Some(done);
+            }
+            self.batch.push(stats);
+            if self.covers_last() {
+                return Some(self.flush());
+            }
+        }
+    }
+}
+
+pub fn network_from_record(json: &Value) -> Option<Network> {
+    Some(Network {
+        id: text_field(json, &["ID", "Id", "id"])?,
+        name: text_field(json, &["Name", "name"])?,
+        driver: text_field(json, &["Driver", "driver"])?,
+        // Podman networks are always local
+        scope: text_field(json, &["Scope", "scope"]).unwrap_or_else(|| "local".to_string()),
+    })
+}
+
+pub fn volume_from_record(json: &Value) -> Option<Volume> {
+    Some(Volume {
+        name: text_field(json, &["Name", "name"])?,
+        driver: text_field(json, &["Driver", "driver"])?,
+        mountpoint: text_field(json, &["Mountpoint", "mountpoint"])?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_docker_and_podman_containers_normalise_alike() {
+        let docker = json_records(
+            "{\"ID\":\"3f4e5d6c7b8a\",\"Names\":\"web\",\"Image\":\"nginx\",\"Status\":\"Up 3 hours\",\"Ports\":\"0.0.0.0:8080->80/tcp\",\"Labels\":\"app=shop,tier=front\"}\n",
+            "container",
+        );
+        let podman = json_records(
+            r#"[{"Id":"3f4e5d6c7b8a","Names":["web"],"Image":"nginx","State":"running","Status":"Up 3 hours",
+                 "Ports":[{"host_ip":"","container_port":80,"host_port":8080,"range":1,"protocol":"tcp"}],
+                 "Labels":{"app":"shop","tier":"front"}}]"#,
+            "container",
+        );
+
+        let docker = container_from_record(&docker[0]).unwrap();
+        let podman = container_from_record(&podman[0]).unwrap();
+        assert_eq!(format!("{:?}", docker), format!("{:?}", podman));
+    }
+
+    #[test]
+    fn test_podman_images_expand_names() {
+        let record = json!({
+            "Id": "sha256:a8758716bb6a",
+            "Names": ["docker.io/library/nginx:1.25", "localhost/web:latest"],
+            "Size": 187_000_000,
+            "Created": 1_704_164_645
+        });
+        let images = images_from_record(&record);
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].repository, "docker.io/library/nginx");
+        assert_eq!(images[0].tag, "1.25");
+        assert_eq!(images[1].size, "187MB");
+        assert_eq!(images[1].created, "2024-01-02 03:04:05 +0000 UTC");
+    }
+
+    #[test]
+    fn test_podman_stats_keys() {
+        let record = json!({"id": "3f4e", "name": "web", "cpu_percent": "1.50%", "mem_usage": "24MB / 2GB",
+                            "mem_percent": "1.20%", "net_io": "1kB / 2kB", "block_io": "0B / 0B", "pids": "2"});
+        let stats = stats_from_record(&record, 4).unwrap();
+        assert_eq!(stats.name, "web");
+        assert_eq!(stats.cpu_fraction, 0.015);
+        assert_eq!(stats.online_cpus, 4);
+        assert_eq!((stats.memory_used, stats.memory_limit), (24_000_000, 2_000_000_000));
+        assert_eq!((stats.net_rx, stats.net_tx), (1_000, 2_000));
+    }
+
+    #[test]
+    fn test_stats_batches_split_refreshes() {
+        let row = |name: &str, cpu: &str| format!(r#"{{"Name":"{}","CPUPerc":"{}","MemUsage":"1MiB / 2GiB","NetIO":"0B / 0B","BlockIO":"0B / 0B"}}"#, name, cpu);
+        let docker = vec![
+            format!("\x1b[2J\x1b[H{}", row("web", "1.00%")),
+            row("db", "2.00%"),
+            format!("\x1b[2J\x1b[H{}", row("web", "3.00%")),
+            row("db", "4.00%"),
+        ];
+        let batches: Vec<Vec<String>> = StatsBatches::new(docker.into_iter(), 2)
+            .map(|batch| batch.iter().map(|stats| format!("{} {}", stats.name, stats.cpu_fraction)).collect())
+            .collect();
+        assert_eq!(batches, vec![vec!["web 0.01", "db 0.02"], vec!["web 0.03", "db 0.04"]]);
+
+        let podman = r#"[
+ {"name": "web", "cpu_percent": "1.50%", "mem_usage": "24MB / 2GB", "net_io": "1kB / 2kB", "block_io": "0B / 0B"}
+]
+[
+ {"name": "web", "cpu_percent": "2.50%", "mem_usage": "24MB / 2GB", "net_io": "1kB / 2kB", "block_io": "0B / 0B"}
+]"#;
+        let batches: Vec<Vec<ContainerStats>> = StatsBatches::new(podman.lines().map(str::to_string), 4).collect();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1][0].cpu_fraction, 0.025);
+    }
+
+    #[test]
+    fn test_stats_batches_hand_over_without_waiting_for_the_next_refresh() {
+        let row = |name: &str| format!(r#"{{"Name":"{}","CPUPerc":"1.00%","MemUsage":"1MiB / 2GiB","NetIO":"0B / 0B","BlockIO":"0B / 0B"}}"#, name);
+        let (sender, lines) = std::sync::mpsc::channel();
+        let mut batches = StatsBatches::new(lines.into_iter(), 2);
+        let mut next = |rows: &[&str]| {
+            for (index, name) in rows.iter().enumerate() {
+                let clear = if index == 0 { "\x1b[2J\x1b[H" } else { "" };
+                sender.send(format!("{}{}", clear, row(name))).unwrap();
+            }
+            batches.next().unwrap().into_iter().map(|stats| stats.name).collect::<Vec<_>>()
+        };
+
+        // Nothing to compare the first refresh with, so it goes once the output is quiet
+        assert_eq!(next(&["web", "db"]), vec!["web", "db"]);
+        // Then as soon as both have reported, with the next refresh still to come
+        assert_eq!(next(&["web", "db"]), vec!["web", "db"]);
+        // A stopped container doesn't hold the refresh back
+        assert_eq!(next(&["web"]), vec!["web"]);
+        // One that started shows up with the rest of its refresh
+        assert_eq!(next(&["web", "cache"]), vec!["web"]);
+        assert_eq!(batches.next().unwrap().into_iter().map(|stats| stats.name).collect::<Vec<_>>(), vec!["web", "cache"]);
+    }
+
+    #[test]
+    fn test_podman_flag_spelling() {
+        let args = vec!["--context".to_string(), "ci".to_string()];
+        assert_eq!(Runtime::Podman.cli_args(&args), vec!["--connection", "ci"]);
+        assert_eq!(Runtime::Docker.cli_args(&args), args);
+    }
+}
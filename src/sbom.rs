@@ -0,0 +1,684 @@
+// Software bill of materials for an image.
+//
+// The image's `docker save` archive is flattened (see layers.rs) keeping only
+// the package databases, which are then read for an inventory: dpkg's status
+// (and distroless' status.d), apk's installed file, rpm's sqlite database,
+// Python dist-info/egg-info metadata and node_modules package.json files.
+// The inventory is written out as CycloneDX 1.5 or SPDX 2.3 JSON.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use serde_json::{json, Value};
+use crate::error::DockerError;
+use crate::layers;
+use crate::sqlite::{Database, SqlValue};
+use crate::utils::format_rfc3339;
+
+const OS_RELEASE: [&str; 2] = ["etc/os-release", "usr/lib/os-release"];
+const DPKG_STATUS: &str = "var/lib/dpkg/status";
+const DPKG_STATUS_DIR: &str = "var/lib/dpkg/status.d/";
+const APK_INSTALLED: &str = "lib/apk/db/installed";
+const RPM_SQLITE: [&str; 2] = ["var/lib/rpm/rpmdb.sqlite", "usr/lib/sysimage/rpm/rpmdb.sqlite"];
+/// Berkeley DB and ndb rpm databases, which aren't read.
+const RPM_OTHER: [&str; 4] = ["var/lib/rpm/Packages", "var/lib/rpm/Packages.db", "usr/lib/sysimage/rpm/Packages", "usr/lib/sysimage/rpm/Packages.db"];
+
+// RPM header tags and types
+const RPMTAG_NAME: u32 = 1000;
+const RPMTAG_VERSION: u32 = 1001;
+const RPMTAG_RELEASE: u32 = 1002;
+const RPMTAG_EPOCH: u32 = 1003;
+const RPMTAG_LICENSE: u32 = 1014;
+const RPMTAG_ARCH: u32 = 1022;
+const RPM_INT32_TYPE: u32 = 4;
+const RPM_STRING_TYPES: [u32; 3] = [6, 8, 9];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PackageKind {
+    Deb,
+    Apk,
+    Rpm,
+    Python,
+    Npm,
+}
+
+impl fmt::Display for PackageKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PackageKind::Deb => "deb",
+            PackageKind::Apk => "apk",
+            PackageKind::Rpm => "rpm",
+            PackageKind::Python => "python",
+            PackageKind::Npm => "npm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    /// For rpm, "VERSION-RELEASE"; the epoch is kept apart.
+    pub version: String,
+    pub kind: PackageKind,
+    pub epoch: Option<String>,
+    pub arch: Option<String>,
+    pub license: Option<String>,
+    /// The database or metadata file the package was found in.
+    pub location: String,
+}
+
+/// The distribution, from os-release, e.g. ("debian", "12").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distro {
+    pub id: String,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sbom {
+    pub image: String,
+    pub distro: Option<Distro>,
+    pub packages: Vec<Package>,
+    /// Package databases found but not read.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    CycloneDx,
+    Spdx,
+}
+
+impl SbomFormat {
+    pub fn parse(name: &str) -> Option<SbomFormat> {
+        match name.to_lowercase().as_str() {
+            "cyclonedx" => Some(SbomFormat::CycloneDx),
+            "spdx" => Some(SbomFormat::Spdx),
+            _ => None,
+        }
+    }
+}
+
+/// Builds the package inventory of a `docker save` archive of `image`.
+pub fn inventory(image: &str, archive: impl Read) -> Result<Sbom, DockerError> {
+    let files = layers::flatten(&layers::read_saved_image(archive, is_package_database)?);
+    let distro = OS_RELEASE.iter().find_map(|path| files.get(*path)).and_then(|data| parse_os_release(&String::from_utf8_lossy(data)));
+    let mut sbom = Sbom { image: image.to_string(), distro, packages: Vec::new(), warnings: Vec::new() };
+
+    for (path, data) in &files {
+        let text = || String::from_utf8_lossy(data);
+        if path == DPKG_STATUS || (path.starts_with(DPKG_STATUS_DIR) && !path.ends_with(".md5sums")) {
+            sbom.packages.extend(parse_dpkg(&text(), path));
+        } else if path == APK_INSTALLED {
+            sbom.packages.extend(parse_apk(&text(), path));
+        } else if RPM_SQLITE.contains(&path.as_str()) {
+            match parse_rpmdb(data, path) {
+                Ok(packages) => sbom.packages.extend(packages),
+                Err(e) => sbom.warnings.push(format!("Couldn't read the rpm database /{}: {}", path, e)),
+            }
+        } else if RPM_OTHER.contains(&path.as_str()) {
+            sbom.warnings.push(format!("Skipped /{}: only sqlite rpm databases can be read", path));
+        } else if path.ends_with("/package.json") {
+            sbom.packages.extend(parse_package_json(data, path));
+        } else if is_python_metadata(path) {
+            sbom.packages.extend(parse_python_metadata(&text(), path));
+        }
+    }
+    Ok(sbom)
+}
+
+// The files `inventory` reads; everything else in the layers is skipped
+fn is_package_database(path: &str) -> bool {
+    OS_RELEASE.contains(&path)
+        || path == DPKG_STATUS
+        || path.starts_with(DPKG_STATUS_DIR)
+        || path == APK_INSTALLED
+        || RPM_SQLITE.contains(&path)
+        || RPM_OTHER.contains(&path)
+        || is_python_metadata(path)
+        || is_node_package(path)
+}
+
+// ".../site-packages/requests-2.31.0.dist-info/METADATA", ".../dist-packages/six-1.16.0.egg-info/PKG-INFO",
+// or an egg-info that is itself the metadata file
+fn is_python_metadata(path: &str) -> bool {
+    let parts: Vec<&str> = path.rsplit('/').take(3).collect();
+    let in_packages = |dir: &str| dir.ends_with("site-packages") || dir.ends_with("dist-packages");
+    match parts.as_slice() {
+        [file, info, packages] if in_packages(packages) => {
+            (info.ends_with(".dist-info") && *file == "METADATA") || (info.ends_with(".egg-info") && *file == "PKG-INFO")
+        }
+        [info, packages, ..] => in_packages(packages) && info.ends_with(".egg-info"),
+        _ => false,
+    }
+}
+
+// ".../node_modules/express/package.json" or ".../node_modules/@types/node/package.json"
+fn is_node_package(path: &str) -> bool {
+    let package = match path.rsplit_once("node_modules/") {
+        Some((_, package)) => package,
+        None => return false,
+    };
+    let parts: Vec<&str> = package.split('/').collect();
+    match parts.as_slice() {
+        [name, "package.json"] => !name.starts_with('.'),
+        [scope, _, "package.json"] => scope.starts_with('@'),
+        _ => false,
+    }
+}
+
+fn parse_os_release(text: &str) -> Option<Distro> {
+    let fields = key_values(text.lines().filter_map(|line| line.split_once('=')));
+    let unquote = |value: &String| value.trim_matches(|c| c == '"' || c == '\'').to_string();
+    let id = fields.get("ID").map(unquote)?;
+    Some(Distro { id, version: fields.get("VERSION_ID").map(unquote) })
+}
+
+fn key_values<'a>(pairs: impl Iterator<Item = (&'a str, &'a str)>) -> BTreeMap<String, String> {
+    pairs.map(|(key, value)| (key.trim().to_string(), value.trim().to_string())).collect()
+}
+
+// Paragraphs of "Field: value" lines; continuation lines start with a space
+fn paragraphs(text: &str) -> Vec<BTreeMap<String, String>> {
+    text.split("\n\n")
+        .map(|paragraph| key_values(paragraph.lines().filter(|line| !line.starts_with([' ', '\t'])).filter_map(|line| line.split_once(':'))))
+        .filter(|fields| !fields.is_empty())
+        .collect()
+}
+
+fn parse_dpkg(text: &str, path: &str) -> Vec<Package> {
+    paragraphs(text)
+        .into_iter()
+        // Distroless' status.d has no Status; the main status file lists removed packages too
+        .filter(|fields| fields.get("Status").map_or(true, |status| status.ends_with(" installed")))
+        .filter_map(|mut fields| {
+            Some(Package {
+                name: fields.remove("Package")?,
+                version: fields.remove("Version")?,
+                kind: PackageKind::Deb,
+                epoch: None,
+                arch: fields.remove("Architecture"),
+                license: None,
+                location: format!("/{}", path),
+            })
+        })
+        .collect()
+}
+
+// apk's installed database is "X:value" lines: P name, V version, A arch, L license
+fn parse_apk(text: &str, path: &str) -> Vec<Package> {
+    text.split("\n\n")
+        .filter_map(|paragraph| {
+            let fields: BTreeMap<&str, &str> = paragraph.lines().filter_map(|line| line.split_once(':')).collect();
+            Some(Package {
+                name: fields.get("P")?.to_string(),
+                version: fields.get("V")?.to_string(),
+                kind: PackageKind::Apk,
+                epoch: None,
+                arch: fields.get("A").map(|arch| arch.to_string()),
+                license: fields.get("L").map(|license| license.to_string()),
+                location: format!("/{}", path),
+            })
+        })
+        .collect()
+}
+
+fn parse_rpmdb(data: &[u8], path: &str) -> Result<Vec<Package>, DockerError> {
+    let mut packages = Vec::new();
+    for row in Database::open(data)?.rows("Packages")? {
+        let header = row.iter().find_map(|value| match value {
+            SqlValue::Blob(blob) => Some(blob),
+            _ => None,
+        });
+        if let Some(package) = header.and_then(|header| parse_rpm_header(header, path)) {
+            // The signing keys rpm imported are stored as packages too
+            if package.name != "gpg-pubkey" {
+                packages.push(package);
+            }
+        }
+    }
+    Ok(packages)
+}
+
+// An RPM header blob: index length and data length, then 16-byte index
+// entries (tag, type, offset, count), then the data they point into.
+fn parse_rpm_header(header: &[u8], path: &str) -> Option<Package> {
+    let word = |offset: usize| header.get(offset..offset + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    let entries = word(0)? as usize;
+    let store = 8 + entries.checked_mul(16)?;
+    let data = header.get(store..store + word(4)? as usize)?;
+
+    let mut tags: BTreeMap<u32, String> = BTreeMap::new();
+    for entry in 0..entries {
+        let (tag, kind, offset) = (word(8 + entry * 16)?, word(12 + entry * 16)?, word(16 + entry * 16)? as usize);
+        let value = if RPM_STRING_TYPES.contains(&kind) {
+            // Arrays (and i18n strings) give their first element
+            let bytes = data.get(offset..)?;
+            let end = bytes.iter().position(|byte| *byte == 0)?;
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        } else if kind == RPM_INT32_TYPE {
+            let bytes = data.get(offset..offset + 4)?;
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_string()
+        } else {
+            continue;
+        };
+        tags.insert(tag, value);
+    }
+
+    let version = tags.get(&RPMTAG_VERSION)?;
+    Some(Package {
+        name: tags.get(&RPMTAG_NAME)?.clone(),
+        version: match tags.get(&RPMTAG_RELEASE) {
+            Some(release) => format!("{}-{}", version, release),
+            None => version.clone(),
+        },
+        kind: PackageKind::Rpm,
+        epoch: tags.remove(&RPMTAG_EPOCH),
+        arch: tags.remove(&RPMTAG_ARCH),
+        license: tags.remove(&RPMTAG_LICENSE),
+        location: format!("/{}", path),
+    })
+}
+
+// Core metadata is email-style headers up to the first blank line
+fn parse_python_metadata(text: &str, path: &str) -> Option<Package> {
+    let headers = text.split("\n\n").next().unwrap_or("");
+    let fields = key_values(headers.lines().filter(|line| !line.starts_with([' ', '\t'])).filter_map(|line| line.split_once(':')));
+    let license = fields
+        .get("License-Expression")
+        .or_else(|| fields.get("License"))
+        .filter(|license| !license.is_empty() && *license != "UNKNOWN")
+        .cloned();
+    Some(Package {
+        name: fields.get("Name")?.clone(),
+        version: fields.get("Version")?.clone(),
+        kind: PackageKind::Python,
+        epoch: None,
+        arch: None,
+        license,
+        location: format!("/{}", path),
+    })
+}
+
+fn parse_package_json(data: &[u8], path: &str) -> Option<Package> {
+    let package: Value = serde_json::from_slice(data).ok()?;
+    // "MIT", or the older {"type": "MIT", "url": ...}
+    let license = package["license"].as_str().or_else(|| package["license"]["type"].as_str());
+    Some(Package {
+        name: package["name"].as_str()?.to_string(),
+        version: package["version"].as_str()?.to_string(),
+        kind: PackageKind::Npm,
+        epoch: None,
+        arch: None,
+        license: license.map(str::to_string),
+        location: format!("/{}", path),
+    })
+}
+
+impl Package {
+    /// The package URL, e.g. "pkg:deb/debian/curl@7.88.1-10?arch=amd64&distro=debian-12".
+    pub fn purl(&self, distro: Option<&Distro>) -> String {
+        let distro_id = |default: &str| distro.map_or(default.to_string(), |distro| distro.id.clone());
+        let (kind, namespace) = match self.kind {
+            PackageKind::Deb => ("deb", Some(distro_id("debian"))),
+            PackageKind::Apk => ("apk", Some(distro_id("alpine"))),
+            PackageKind::Rpm => ("rpm", Some(distro_id("redhat"))),
+            PackageKind::Python => ("pypi", None),
+            PackageKind::Npm => ("npm", None),
+        };
+        let (namespace, name) = match self.kind {
+            PackageKind::Npm => match self.name.split_once('/') {
+                Some((scope, name)) => (Some(scope.to_string()), name.to_string()),
+                None => (None, self.name.clone()),
+            },
+            // PyPI names are case-insensitive with - and _ the same
+            PackageKind::Python => (None, self.name.to_lowercase().replace('_', "-")),
+            _ => (namespace, self.name.clone()),
+        };
+
+        let mut purl = format!("pkg:{}/", kind);
+        if let Some(namespace) = namespace {
+            purl.push_str(&percent_encode(&namespace));
+            purl.push('/');
+        }
+        purl.push_str(&format!("{}@{}", percent_encode(&name), percent_encode(&self.version)));
+
+        let mut qualifiers = Vec::new();
+        if let Some(arch) = &self.arch {
+            qualifiers.push(format!("arch={}", percent_encode(arch)));
+        }
+        if let Some(epoch) = self.epoch.as_ref().filter(|epoch| *epoch != "0") {
+            qualifiers.push(format!("epoch={}", epoch));
+        }
+        if let Some(distro) = distro.filter(|_| namespace_is_distro(self.kind)) {
+            let release = distro.version.as_ref().map_or(distro.id.clone(), |version| format!("{}-{}", distro.id, version));
+            qualifiers.push(format!("distro={}", percent_encode(&release)));
+        }
+        if !qualifiers.is_empty() {
+            purl.push('?');
+            purl.push_str(&qualifiers.join("&"));
+        }
+        purl
+    }
+}
+
+fn namespace_is_distro(kind: PackageKind) -> bool {
+    matches!(kind, PackageKind::Deb | PackageKind::Apk | PackageKind::Rpm)
+}
+
+// Everything but unreserved characters, so "1:2.3+dfsg" is "1%3A2.3%2Bdfsg"
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// Whether a license reads as an SPDX expression ("MIT", "GPL-2.0-or-later AND
+// BSD-3-Clause") rather than free text ("GPLv2+", "BSD-like"). Identifiers
+// aren't checked against the SPDX list; most have a dash or a dot in them.
+fn is_spdx_expression(license: &str) -> bool {
+    const PLAIN_IDS: [&str; 8] = ["MIT", "ISC", "Zlib", "Unlicense", "WTFPL", "Beerware", "curl", "Vim"];
+    let tokens: Vec<&str> = license.split(|c: char| c.is_whitespace() || c == '(' || c == ')').filter(|token| !token.is_empty()).collect();
+    !tokens.is_empty()
+        && tokens.iter().all(|token| {
+            let id = token.trim_end_matches('+');
+            matches!(*token, "AND" | "OR" | "WITH")
+                || PLAIN_IDS.contains(&id)
+                || (id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                    && id.contains(['-', '.'])
+                    && id.starts_with(|c: char| c.is_ascii_alphabetic()))
+        })
+}
+
+impl Sbom {
+    /// Packages found per kind, e.g. [(Apk, 15), (Python, 3)].
+    pub fn counts(&self) -> Vec<(PackageKind, usize)> {
+        let mut counts: BTreeMap<PackageKind, usize> = BTreeMap::new();
+        for package in &self.packages {
+            *counts.entry(package.kind).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    pub fn render(&self, format: SbomFormat, now: i64) -> Value {
+        match format {
+            SbomFormat::CycloneDx => self.cyclonedx(now),
+            SbomFormat::Spdx => self.spdx(now),
+        }
+    }
+
+    pub fn cyclonedx(&self, now: i64) -> Value {
+        let distro = self.distro.as_ref();
+        let components: Vec<Value> = self
+            .packages
+            .iter()
+            .enumerate()
+            .map(|(index, package)| {
+                let purl = package.purl(distro);
+                let mut component = json!({
+                    "type": "library",
+                    "bom-ref": format!("{}#{}", purl, index),
+                    "name": package.name,
+                    "version": package.version,
+                    "purl": purl,
+                    "properties": [
+                        {"name": "dui:package:type", "value": package.kind.to_string()},
+                        {"name": "dui:location", "value": package.location},
+                    ],
+                });
+                if let Some(license) = &package.license {
+                    component["licenses"] = if is_spdx_expression(license) {
+                        json!([{"expression": license}])
+                    } else {
+                        json!([{"license": {"name": license}}])
+                    };
+                }
+                component
+            })
+            .collect();
+
+        let mut image = json!({"type": "container", "bom-ref": self.image, "name": self.image});
+        if let Some(distro) = distro {
+            image["properties"] = json!([{"name": "dui:distro", "value": distro_name(distro)}]);
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "serialNumber": format!("urn:uuid:{}", self.document_uuid(now)),
+            "version": 1,
+            "metadata": {
+                "timestamp": format_rfc3339(now),
+                "tools": {"components": [{"type": "application", "name": "dui", "version": env!("CARGO_PKG_VERSION")}]},
+                "component": image,
+            },
+            "components": components,
+        })
+    }
+
+    pub fn spdx(&self, now: i64) -> Value {
+        let distro = self.distro.as_ref();
+        let image_id = "SPDXRef-Image".to_string();
+        let mut packages = vec![json!({
+            "SPDXID": image_id,
+            "name": self.image,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "primaryPackagePurpose": "CONTAINER",
+        })];
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": image_id,
+        })];
+
+        for (index, package) in self.packages.iter().enumerate() {
+            let id = format!("SPDXRef-Package-{}-{}-{}", package.kind, spdx_id_part(&package.name), index);
+            let mut entry = json!({
+                "SPDXID": id,
+                "name": package.name,
+                "versionInfo": package.version,
+                "downloadLocation": "NOASSERTION",
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": "NOASSERTION",
+                "copyrightText": "NOASSERTION",
+                "sourceInfo": format!("found in {}", package.location),
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": package.purl(distro),
+                }],
+            });
+            match &package.license {
+                Some(license) if is_spdx_expression(license) => entry["licenseDeclared"] = json!(license),
+                Some(license) => entry["licenseComments"] = json!(format!("Declared license: {}", license)),
+                None => {}
+            }
+            packages.push(entry);
+            relationships.push(json!({"spdxElementId": image_id, "relationshipType": "CONTAINS", "relatedSpdxElement": id}));
+        }
+
+        let name: String = self.image.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' }).collect();
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": self.image,
+            "documentNamespace": format!("https://spdx.org/spdxdocs/dui-{}-{}", name, self.document_uuid(now)),
+            "creationInfo": {
+                "created": format_rfc3339(now),
+                "creators": [format!("Tool: dui-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+
+    // A version-4-shaped UUID from the image, its packages and the time,
+    // so documents for different images or runs get different ids
+    fn document_uuid(&self, now: i64) -> String {
+        let half = |salt: u8| {
+            let mut hasher = DefaultHasher::new();
+            (salt, &self.image, now, self.packages.len()).hash(&mut hasher);
+            for package in &self.packages {
+                (&package.name, &package.version).hash(&mut hasher);
+            }
+            hasher.finish()
+        };
+        let bits = ((half(0) as u128) << 64 | half(1) as u128) & !(0xf000u128 << 64) & !(0xc000u128 << 48);
+        let bits = bits | (0x4000u128 << 64) | (0x8000u128 << 48);
+        let hex = format!("{:032x}", bits);
+        format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+/// e.g. "alpine 3.19.1"
+pub fn distro_name(distro: &Distro) -> String {
+    match &distro.version {
+        Some(version) => format!("{} {}", distro.id, version),
+        None => distro.id.clone(),
+    }
+}
+
+// SPDX ids allow letters, digits, dots and dashes
+fn spdx_id_part(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::tar_of;
+    use crate::sqlite;
+
+    fn rpm_header(tags: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut data: Vec<u8> = Vec::new();
+        for (tag, kind, value) in tags {
+            index.extend(tag.to_be_bytes());
+            index.extend(kind.to_be_bytes());
+            index.extend((data.len() as u32).to_be_bytes());
+            index.extend(1u32.to_be_bytes());
+            data.extend(*value);
+        }
+        let mut header = (tags.len() as u32).to_be_bytes().to_vec();
+        header.extend((data.len() as u32).to_be_bytes());
+        header.extend(index);
+        header.extend(data);
+        header
+    }
+
+    fn saved_image(layers: Vec<Vec<u8>>) -> Vec<u8> {
+        let paths: Vec<String> = (0..layers.len()).map(|index| format!("{}/layer.tar", index)).collect();
+        let manifest = json!([{"Config": "config.json", "Layers": paths}]).to_string().into_bytes();
+        let mut files: Vec<(&str, Vec<u8>)> = paths.iter().map(String::as_str).zip(layers).collect();
+        files.push(("manifest.json", manifest));
+        tar_of(&files)
+    }
+
+    #[test]
+    fn test_inventory_of_every_database() {
+        let dpkg = "Package: curl\nStatus: install ok installed\nArchitecture: amd64\nVersion: 7.88.1-10+deb12u5\n\
+                    Description: tool\n multi-line\n\nPackage: gone\nStatus: deinstall ok config-files\nVersion: 1.0\n";
+        let rpm = sqlite::tests::database(
+            "Packages",
+            &[
+                vec![
+                    SqlValue::Null,
+                    SqlValue::Blob(rpm_header(&[
+                        (RPMTAG_NAME, 6, b"bash\0"),
+                        (RPMTAG_VERSION, 6, b"5.1.8\0"),
+                        (RPMTAG_RELEASE, 6, b"9.el9\0"),
+                        (RPMTAG_EPOCH, RPM_INT32_TYPE, &2u32.to_be_bytes()),
+                        (RPMTAG_LICENSE, 6, b"GPLv3+\0"),
+                        (RPMTAG_ARCH, 6, b"x86_64\0"),
+                    ])),
+                ],
+                vec![SqlValue::Null, SqlValue::Blob(rpm_header(&[(RPMTAG_NAME, 6, b"gpg-pubkey\0"), (RPMTAG_VERSION, 6, b"fd431d51\0")]))],
+            ],
+        );
+        let lower = tar_of(&[
+            ("etc/os-release", b"NAME=\"Debian GNU/Linux\"\nID=debian\nVERSION_ID=\"12\"\n".to_vec()),
+            ("var/lib/dpkg/status", dpkg.as_bytes().to_vec()),
+            ("var/lib/rpm/rpmdb.sqlite", rpm),
+            ("usr/lib/python3/dist-packages/six-1.16.0.egg-info", b"Metadata-Version: 1.2\nName: six\nVersion: 1.16.0\nLicense: MIT\n".to_vec()),
+            ("app/node_modules/left-pad/package.json", br#"{"name": "left-pad", "version": "1.3.0", "license": "WTFPL"}"#.to_vec()),
+        ]);
+        let upper = tar_of(&[
+            ("app/node_modules/.wh.left-pad", Vec::new()),
+            ("app/node_modules/@types/node/package.json", br#"{"name": "@types/node", "version": "20.1.0", "license": {"type": "MIT"}}"#.to_vec()),
+            (
+                "usr/local/lib/python3.12/site-packages/Flask_Cors-4.0.0.dist-info/METADATA",
+                b"Metadata-Version: 2.1\nName: Flask_Cors\nVersion: 4.0.0\nLicense: UNKNOWN\n\nLicense: not a header\n".to_vec(),
+            ),
+            ("lib/apk/db/installed", b"C:Q1abc=\nP:musl\nV:1.2.4-r2\nA:x86_64\nL:MIT\n\nP:busybox\nV:1.36.1-r15\nL:GPL-2.0-only\n".to_vec()),
+        ]);
+        let sbom = inventory("app:latest", &saved_image(vec![lower, upper])[..]).unwrap();
+
+        let found: Vec<(PackageKind, &str, &str)> =
+            sbom.packages.iter().map(|package| (package.kind, package.name.as_str(), package.version.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (PackageKind::Npm, "@types/node", "20.1.0"),
+                (PackageKind::Apk, "musl", "1.2.4-r2"),
+                (PackageKind::Apk, "busybox", "1.36.1-r15"),
+                (PackageKind::Python, "six", "1.16.0"),
+                (PackageKind::Python, "Flask_Cors", "4.0.0"),
+                (PackageKind::Deb, "curl", "7.88.1-10+deb12u5"),
+                (PackageKind::Rpm, "bash", "5.1.8-9.el9"),
+            ]
+        );
+        assert_eq!(sbom.distro, Some(Distro { id: "debian".to_string(), version: Some("12".to_string()) }));
+        assert_eq!(sbom.packages[4].license, None);
+        assert_eq!(sbom.packages[0].license.as_deref(), Some("MIT"));
+
+        let distro = sbom.distro.as_ref();
+        assert_eq!(sbom.packages[0].purl(distro), "pkg:npm/%40types/node@20.1.0");
+        assert_eq!(sbom.packages[4].purl(distro), "pkg:pypi/flask-cors@4.0.0");
+        assert_eq!(sbom.packages[5].purl(distro), "pkg:deb/debian/curl@7.88.1-10%2Bdeb12u5?arch=amd64&distro=debian-12");
+        assert_eq!(sbom.packages[6].purl(distro), "pkg:rpm/debian/bash@5.1.8-9.el9?arch=x86_64&epoch=2&distro=debian-12");
+        assert!(sbom.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_cyclonedx_and_spdx_documents() {
+        let image = tar_of(&[
+            ("etc/os-release", b"ID=alpine\nVERSION_ID=3.19.1\n".to_vec()),
+            ("lib/apk/db/installed", b"P:musl\nV:1.2.4-r2\nA:x86_64\nL:MIT\n\nP:ssl_client\nV:3.1.4-r5\nL:custom license\n".to_vec()),
+            ("var/lib/rpm/Packages", vec![0; 64]),
+        ]);
+        let sbom = inventory("alpine:3.19", &saved_image(vec![image])[..]).unwrap();
+        assert_eq!(sbom.counts(), vec![(PackageKind::Apk, 2)]);
+        assert_eq!(sbom.warnings, vec!["Skipped /var/lib/rpm/Packages: only sqlite rpm databases can be read".to_string()]);
+
+        let cyclonedx = sbom.render(SbomFormat::CycloneDx, 1_704_164_645);
+        assert_eq!(cyclonedx["specVersion"], "1.5");
+        assert_eq!(cyclonedx["metadata"]["timestamp"], "2024-01-02T03:04:05Z");
+        assert_eq!(cyclonedx["metadata"]["component"]["name"], "alpine:3.19");
+        assert_eq!(cyclonedx["components"][0]["purl"], "pkg:apk/alpine/musl@1.2.4-r2?arch=x86_64&distro=alpine-3.19.1");
+        assert_eq!(cyclonedx["components"][0]["licenses"][0]["expression"], "MIT");
+        assert_eq!(cyclonedx["components"][1]["licenses"][0]["license"]["name"], "custom license");
+        assert!(cyclonedx["serialNumber"].as_str().unwrap().starts_with("urn:uuid:"));
+
+        let spdx = sbom.render(SbomFormat::Spdx, 1_704_164_645);
+        assert_eq!(spdx["spdxVersion"], "SPDX-2.3");
+        assert_eq!(spdx["packages"].as_array().unwrap().len(), 3);
+        assert_eq!(spdx["packages"][1]["SPDXID"], "SPDXRef-Package-apk-musl-0");
+        assert_eq!(spdx["packages"][1]["licenseDeclared"], "MIT");
+        assert_eq!(spdx["packages"][2]["SPDXID"], "SPDXRef-Package-apk-ssl-client-1");
+        assert_eq!(spdx["packages"][2]["licenseDeclared"], "NOASSERTION");
+        assert_eq!(spdx["packages"][2]["licenseComments"], "Declared license: custom license");
+        assert_eq!(spdx["relationships"][2]["relatedSpdxElement"], "SPDXRef-Package-apk-ssl-client-1");
+        assert_ne!(spdx["documentNamespace"], sbom.render(SbomFormat::Spdx, 1_704_164_646)["documentNamespace"]);
+
+        assert!(is_spdx_expression("(MIT OR Apache-2.0) AND GPL-2.0+"));
+        assert!(!is_spdx_expression("GPLv2+"));
+    }
+}
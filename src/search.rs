@@ -0,0 +1,204 @@
+// Regex search over log streams.
+//
+// The search is an iterator adapter, so it works the same on a finished log
+// and on a followed one: lines are checked as they arrive, the last few
+// non-matching lines are kept for leading context, and trailing context is
+// counted down after each match, as with `grep -C`.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use regex::Regex;
+use crate::logs::LogLine;
+use crate::tail::TaggedLine;
+
+/// Anything with log text to search.
+pub trait Searchable {
+    fn text(&self) -> &str;
+}
+
+impl Searchable for LogLine {
+    fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Searchable for TaggedLine {
+    fn text(&self) -> &str {
+        &self.line.text
+    }
+}
+
+/// What a search lets through. The default search shows every line.
+#[derive(Debug, Clone, Default)]
+pub struct LogSearch {
+    /// A line matches if any of these match; no patterns match every line.
+    include: Vec<Regex>,
+    /// Lines matching any of these are dropped, context included.
+    exclude: Vec<Regex>,
+    /// Lines shown before and after each match.
+    context: usize,
+}
+
+/// One line of search output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchLine<T> {
+    /// A matching line and the byte ranges the patterns matched.
+    Match(T, Vec<Range<usize>>),
+    /// A line shown around a match.
+    Context(T),
+    /// Lines were skipped between two groups.
+    Gap,
+}
+
+impl LogSearch {
+    pub fn new(include: &[String], exclude: &[String], context: usize) -> Result<LogSearch, String> {
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>, String> {
+            patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e)))
+                .collect()
+        };
+        Ok(LogSearch { include: compile(include)?, exclude: compile(exclude)?, context })
+    }
+
+    /// The ranges to highlight when `text` matches, None when it doesn't.
+    pub fn find(&self, text: &str) -> Option<Vec<Range<usize>>> {
+        if self.excludes(text) {
+            return None;
+        }
+        if self.include.is_empty() {
+            return Some(Vec::new());
+        }
+        let mut ranges: Vec<Range<usize>> = self
+            .include
+            .iter()
+            .flat_map(|pattern| pattern.find_iter(text).map(|found| found.range()))
+            .filter(|range| !range.is_empty())
+            .collect();
+        if ranges.is_empty() && !self.include.iter().any(|pattern| pattern.is_match(text)) {
+            return None;
+        }
+        ranges.sort_by_key(|range| range.start);
+        Some(ranges)
+    }
+
+    fn excludes(&self, text: &str) -> bool {
+        self.exclude.iter().any(|pattern| pattern.is_match(text))
+    }
+
+    pub fn apply<I>(self, lines: I) -> SearchResults<I>
+    where
+        I: Iterator,
+        I::Item: Searchable,
+    {
+        SearchResults { lines, search: self, read: 0, before: VecDeque::new(), after: 0, last_shown: None, ready: VecDeque::new() }
+    }
+}
+
+/// Search output for a stream of lines; see `LogSearch::apply`.
+pub struct SearchResults<I: Iterator> {
+    lines: I,
+    search: LogSearch,
+    // Lines read so far that weren't excluded, used to spot gaps
+    read: usize,
+    before: VecDeque<(usize, I::Item)>,
+    // Trailing context still owed to the last match
+    after: usize,
+    last_shown: Option<usize>,
+    ready: VecDeque<SearchLine<I::Item>>,
+}
+
+impl<I> SearchResults<I>
+where
+    I: Iterator,
+    I::Item: Searchable,
+{
+    fn show(&mut self, index: usize, line: SearchLine<I::Item>) {
+        if self.last_shown.is_some_and(|last| index > last + 1) {
+            self.ready.push_back(SearchLine::Gap);
+        }
+        self.last_shown = Some(index);
+        self.ready.push_back(line);
+    }
+}
+
+impl<I> Iterator for SearchResults<I>
+where
+    I: Iterator,
+    I::Item: Searchable,
+{
+    type Item = SearchLine<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.ready.pop_front() {
+                return Some(line);
+            }
+            let line = self.lines.next()?;
+            if self.search.excludes(line.text()) {
+                continue;
+            }
+            let index = self.read;
+            self.read += 1;
+
+            match self.search.find(line.text()) {
+                Some(ranges) => {
+                    while let Some((before, context)) = self.before.pop_front() {
+                        self.show(before, SearchLine::Context(context));
+                    }
+                    self.show(index, SearchLine::Match(line, ranges));
+                    self.after = self.search.context;
+                }
+                None if self.after > 0 => {
+                    self.after -= 1;
+                    self.show(index, SearchLine::Context(line));
+                }
+                None => {
+                    self.before.push_back((index, line));
+                    if self.before.len() > self.search.context {
+                        self.before.pop_front();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::LogStream;
+
+    fn lines(texts: &[&str]) -> Vec<LogLine> {
+        texts.iter().map(|text| LogLine::parse(LogStream::Stdout, text, false)).collect()
+    }
+
+    fn render(results: impl Iterator<Item = SearchLine<LogLine>>) -> Vec<String> {
+        results
+            .map(|line| match line {
+                SearchLine::Match(line, _) => format!("> {}", line.text),
+                SearchLine::Context(line) => format!("  {}", line.text),
+                SearchLine::Gap => "--".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_context_and_gaps() {
+        let log = lines(&["boot", "GET /a 200", "GET /b 500", "GET /c 200", "GET /c2 200", "GET /d 200", "GET /health 200", "GET /e 502", "bye"]);
+        let search = LogSearch::new(&[r" 5\d\d$".to_string()], &["/health".to_string()], 1).unwrap();
+        assert_eq!(
+            render(search.apply(log.into_iter())),
+            vec!["  GET /a 200", "> GET /b 500", "  GET /c 200", "--", "  GET /d 200", "> GET /e 502", "  bye"]
+        );
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        let search = LogSearch::new(&["timed? out".to_string(), "upstream".to_string()], &[], 0).unwrap();
+        assert_eq!(search.find("upstream timed out"), Some(vec![0..8, 9..18]));
+        assert_eq!(search.find("GET / 200"), None);
+        assert_eq!(LogSearch::default().find("anything"), Some(vec![]));
+        assert!(LogSearch::new(&["(unclosed".to_string()], &[], 0).is_err());
+    }
+}
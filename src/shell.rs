@@ -0,0 +1,119 @@
+// Shell access for `dui shell`.
+//
+// The target is probed for the best available shell. Images without one
+// (distroless, scratch) get an ephemeral debug container instead: it runs a
+// tools image that is already available locally and joins the target's PID and
+// network namespaces, so the target's processes, ports and, through
+// /proc/1/root, its filesystem are all reachable.
+
+use crate::docker::{ContainerSpec, DockerClient, ExecOptions};
+use crate::error::DockerError;
+
+/// Tools images tried for the debug container, best first.
+pub const DEBUG_IMAGES: &[&str] = &["nicolaka/netshoot", "busybox", "alpine", "ubuntu", "debian"];
+
+const PROBE: &str = "for shell in bash zsh ash sh; do command -v \"$shell\" && break; done; true";
+
+/// The best shell in `container`, or None when it has no shell at all.
+pub fn detect_shell(docker: &DockerClient, container: &str) -> Result<Option<String>, DockerError> {
+    match docker.exec_container(container, PROBE, &ExecOptions::default()) {
+        // The probe itself ran under sh, so that's the fallback
+        Ok(output) => Ok(Some(
+            output
+                .lines()
+                .map(str::trim)
+                .find(|line| line.starts_with('/'))
+                .unwrap_or("sh")
+                .to_string(),
+        )),
+        Err(e) if is_missing_executable(e.message()) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// What docker and podman report when `sh` doesn't exist in the image
+fn is_missing_executable(message: &str) -> bool {
+    let message = message.to_lowercase();
+    (message.contains("executable file") && message.contains("not found"))
+        || (message.contains("exec") && message.contains("no such file or directory"))
+}
+
+/// `preferred` if given, otherwise the first of `DEBUG_IMAGES` available locally.
+pub fn debug_image(docker: &DockerClient, preferred: Option<&str>) -> Result<String, DockerError> {
+    if let Some(image) = preferred {
+        return Ok(image.to_string());
+    }
+    let images = docker.list_images()?;
+    DEBUG_IMAGES
+        .iter()
+        .find_map(|candidate| {
+            images.iter().find(|image| {
+                image.tag != "<none>"
+                    && (image.repository == *candidate || image.repository.ends_with(&format!("/{}", candidate)))
+            })
+        })
+        .map(|image| format!("{}:{}", image.repository, image.tag))
+        .ok_or_else(|| {
+            DockerError::ImageNotFound(format!(
+                "No debug image available locally; pull one of {} or pass --debug-image",
+                DEBUG_IMAGES.join(", ")
+            ))
+        })
+}
+
+/// A throwaway container that shares `target`'s PID and network namespaces
+/// and idles until the session ends.
+pub fn debug_spec(target: &str, image: &str) -> ContainerSpec {
+    let prefix: String = target.chars().take(40).collect();
+    ContainerSpec {
+        networks: vec![format!("container:{}", target)],
+        pid: Some(format!("container:{}", target)),
+        labels: vec![format!("dui.debug-target={}", target)],
+        entrypoint: Some("sleep".to_string()),
+        command: vec!["86400".to_string()],
+        auto_remove: true,
+        ..ContainerSpec::new(&format!("{}-debug-{}", prefix, std::process::id()), image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::docker::Image;
+    use crate::fake::FakeBackend;
+
+    #[test]
+    fn test_detect_shell() {
+        let fake = FakeBackend::demo()
+            .with_exec_output("web", "/bin/bash\n")
+            .with_exec_error("db", "OCI runtime exec failed: exec failed: unable to start container process: exec: \"sh\": executable file not found in $PATH: unknown");
+        let docker = DockerClient::with_backend(Arc::new(fake));
+
+        assert_eq!(detect_shell(&docker, "web").unwrap().as_deref(), Some("/bin/bash"));
+        assert_eq!(detect_shell(&docker, "worker").unwrap().as_deref(), Some("sh"));
+        assert_eq!(detect_shell(&docker, "db").unwrap(), None);
+        assert!(detect_shell(&docker, "nope").is_err());
+    }
+
+    #[test]
+    fn test_debug_image_prefers_local_tools() {
+        let docker = DockerClient::with_backend(Arc::new(FakeBackend::demo()));
+        assert!(matches!(debug_image(&docker, None), Err(DockerError::ImageNotFound(_))));
+
+        let fake = FakeBackend::demo().with_image(Image {
+            id: "3f57d9401f8d".to_string(),
+            repository: "docker.io/library/busybox".to_string(),
+            tag: "1.36".to_string(),
+            size: "4.26MB".to_string(),
+            created: "2024-01-02 03:04:05 +0000 UTC".to_string(),
+        });
+        let docker = DockerClient::with_backend(Arc::new(fake));
+        assert_eq!(debug_image(&docker, None).unwrap(), "docker.io/library/busybox:1.36");
+
+        let spec = debug_spec("api", "busybox:1.36");
+        assert!(spec.validate().is_ok());
+        assert_eq!(spec.pid.as_deref(), Some("container:api"));
+        assert_eq!(spec.networks, vec!["container:api"]);
+    }
+}
@@ -0,0 +1,361 @@
+// Reading the rows of a table straight out of an SQLite database file.
+//
+// Just enough of the file format for rpm's rpmdb.sqlite: the header's page
+// size, table B-trees (interior and leaf pages), overflow chains for large
+// payloads and the record format. Indexes, WAL files and anything that
+// writes are out of scope.
+
+use crate::error::DockerError;
+
+const MAGIC: &[u8] = b"SQLite format 3\0";
+/// B-trees deeper than this are taken to be corrupt (or cyclic).
+const MAX_DEPTH: usize = 32;
+
+const INTERIOR_TABLE_PAGE: u8 = 0x05;
+const LEAF_TABLE_PAGE: u8 = 0x0d;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl SqlValue {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            SqlValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+pub struct Database<'a> {
+    data: &'a [u8],
+    page_size: usize,
+    /// Page size less the bytes reserved at the end of each page.
+    usable: usize,
+}
+
+impl<'a> Database<'a> {
+    pub fn open(data: &'a [u8]) -> Result<Database<'a>, DockerError> {
+        if data.len() < 100 || !data.starts_with(MAGIC) {
+            return Err("Not an SQLite database".into());
+        }
+        let page_size = match u16::from_be_bytes([data[16], data[17]]) {
+            1 => 65536,
+            size => size as usize,
+        };
+        if page_size < 512 || !page_size.is_power_of_two() {
+            return Err(format!("Unsupported SQLite page size {}", page_size).into());
+        }
+        Ok(Database { data, page_size, usable: page_size - data[20] as usize })
+    }
+
+    /// Every row of the table called `name`, in rowid order. An INTEGER
+    /// PRIMARY KEY column reads as Null, as SQLite stores it as the rowid.
+    pub fn rows(&self, name: &str) -> Result<Vec<Vec<SqlValue>>, DockerError> {
+        // sqlite_master(type, name, tbl_name, rootpage, sql) is rooted on page 1
+        let mut root = None;
+        for row in self.table(1)? {
+            if row.first().and_then(SqlValue::as_text) == Some("table") && row.get(1).and_then(SqlValue::as_text) == Some(name) {
+                if let Some(SqlValue::Integer(page)) = row.get(3) {
+                    root = Some(*page as usize);
+                }
+            }
+        }
+        match root {
+            Some(page) => self.table(page),
+            None => Err(format!("No table named {} in the database", name).into()),
+        }
+    }
+
+    fn table(&self, root: usize) -> Result<Vec<Vec<SqlValue>>, DockerError> {
+        let mut rows = Vec::new();
+        self.walk(root, 0, &mut rows)?;
+        Ok(rows)
+    }
+
+    fn walk(&self, number: usize, depth: usize, rows: &mut Vec<Vec<SqlValue>>) -> Result<(), DockerError> {
+        if depth > MAX_DEPTH {
+            return Err(corrupt());
+        }
+        let page = self.page(number)?;
+        // Page 1 starts with the 100-byte file header
+        let header = if number == 1 { 100 } else { 0 };
+        let kind = *page.get(header).ok_or_else(corrupt)?;
+        let cells = read_u16(page, header + 3)? as usize;
+        let pointers = header + if kind == INTERIOR_TABLE_PAGE { 12 } else { 8 };
+        for cell in 0..cells {
+            let offset = read_u16(page, pointers + cell * 2)? as usize;
+            match kind {
+                INTERIOR_TABLE_PAGE => self.walk(read_u32(page, offset)? as usize, depth + 1, rows)?,
+                LEAF_TABLE_PAGE => rows.push(self.leaf_cell(page, offset)?),
+                _ => return Err(format!("Unexpected SQLite page type {:#04x}", kind).into()),
+            }
+        }
+        if kind == INTERIOR_TABLE_PAGE {
+            self.walk(read_u32(page, header + 8)? as usize, depth + 1, rows)?;
+        }
+        Ok(())
+    }
+
+    fn leaf_cell(&self, page: &[u8], offset: usize) -> Result<Vec<SqlValue>, DockerError> {
+        let (size, used) = read_varint(page, offset)?;
+        let (_rowid, rowid_used) = read_varint(page, offset + used)?;
+        let start = offset + used + rowid_used;
+        let size = size as usize;
+        let local = self.local_payload(size);
+        let mut payload = page.get(start..start + local).ok_or_else(corrupt)?.to_vec();
+        if local < size {
+            let mut next = read_u32(page, start + local)? as usize;
+            let mut remaining = size - local;
+            // A chain can't have more links than the file has pages
+            let mut links = self.data.len() / self.page_size;
+            while remaining > 0 {
+                if next == 0 || links == 0 {
+                    return Err(corrupt());
+                }
+                links -= 1;
+                let overflow = self.page(next)?;
+                let take = remaining.min(self.usable - 4);
+                payload.extend_from_slice(overflow.get(4..4 + take).ok_or_else(corrupt)?);
+                remaining -= take;
+                next = read_u32(overflow, 0)? as usize;
+            }
+        }
+        read_record(&payload)
+    }
+
+    // How much of a leaf cell's payload is on the page itself
+    fn local_payload(&self, size: usize) -> usize {
+        let max_local = self.usable - 35;
+        if size <= max_local {
+            return size;
+        }
+        let min_local = (self.usable - 12) * 32 / 255 - 23;
+        let local = min_local + (size - min_local) % (self.usable - 4);
+        if local <= max_local {
+            local
+        } else {
+            min_local
+        }
+    }
+
+    fn page(&self, number: usize) -> Result<&'a [u8], DockerError> {
+        let start = number.checked_sub(1).ok_or_else(corrupt)? * self.page_size;
+        self.data.get(start..start + self.page_size).ok_or_else(corrupt)
+    }
+}
+
+fn read_record(payload: &[u8]) -> Result<Vec<SqlValue>, DockerError> {
+    let (header_size, mut position) = read_varint(payload, 0)?;
+    let mut types = Vec::new();
+    while position < header_size as usize {
+        let (serial, used) = read_varint(payload, position)?;
+        types.push(serial);
+        position += used;
+    }
+    let mut body = header_size as usize;
+    let mut values = Vec::with_capacity(types.len());
+    for serial in types {
+        let size = match serial {
+            0 | 8 | 9 => 0,
+            1..=4 => serial as usize,
+            5 => 6,
+            6 | 7 => 8,
+            10 | 11 => return Err(corrupt()),
+            _ => (serial as usize - 12) / 2,
+        };
+        let bytes = payload.get(body..body + size).ok_or_else(corrupt)?;
+        body += size;
+        values.push(match serial {
+            0 => SqlValue::Null,
+            8 => SqlValue::Integer(0),
+            9 => SqlValue::Integer(1),
+            7 => SqlValue::Real(f64::from_be_bytes(bytes.try_into().map_err(|_| corrupt())?)),
+            1..=6 => {
+                // Big-endian two's complement of 1 to 8 bytes
+                let sign = if bytes[0] & 0x80 != 0 { -1i64 } else { 0 };
+                SqlValue::Integer(bytes.iter().fold(sign, |value, byte| (value << 8) | *byte as i64))
+            }
+            _ if serial % 2 == 0 => SqlValue::Blob(bytes.to_vec()),
+            _ => SqlValue::Text(String::from_utf8_lossy(bytes).into_owned()),
+        });
+    }
+    Ok(values)
+}
+
+// SQLite's big-endian varint: 7 bits a byte with the high bit set to go on,
+// except that a ninth byte gives all 8 bits. Returns the value and its length.
+fn read_varint(data: &[u8], offset: usize) -> Result<(u64, usize), DockerError> {
+    let mut value = 0u64;
+    for index in 0..9 {
+        let byte = *data.get(offset + index).ok_or_else(corrupt)?;
+        if index == 8 {
+            return Ok(((value << 8) | byte as u64, 9));
+        }
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok((value, index + 1));
+        }
+    }
+    unreachable!()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, DockerError> {
+    let bytes = data.get(offset..offset + 2).ok_or_else(corrupt)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, DockerError> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(corrupt)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn corrupt() -> DockerError {
+    "The SQLite database is corrupt or truncated".into()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const PAGE: usize = 512;
+
+    fn varint(mut value: u64) -> Vec<u8> {
+        let mut bytes = vec![(value & 0x7f) as u8];
+        value >>= 7;
+        while value > 0 {
+            bytes.insert(0, (value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes
+    }
+
+    fn record(values: &[SqlValue]) -> Vec<u8> {
+        let mut types = Vec::new();
+        let mut body = Vec::new();
+        for value in values {
+            match value {
+                SqlValue::Null => types.extend(varint(0)),
+                SqlValue::Integer(number) => {
+                    types.extend(varint(6));
+                    body.extend(number.to_be_bytes());
+                }
+                SqlValue::Real(number) => {
+                    types.extend(varint(7));
+                    body.extend(number.to_be_bytes());
+                }
+                SqlValue::Text(text) => {
+                    types.extend(varint(text.len() as u64 * 2 + 13));
+                    body.extend(text.as_bytes());
+                }
+                SqlValue::Blob(blob) => {
+                    types.extend(varint(blob.len() as u64 * 2 + 12));
+                    body.extend(blob);
+                }
+            }
+        }
+        let mut payload = varint(types.len() as u64 + 1);
+        payload.extend(types);
+        payload.extend(body);
+        payload
+    }
+
+    /// A database of 512-byte pages with one table of `rows`, whose large
+    /// payloads spill into overflow pages. Page 2 is the table's interior
+    /// root, so both kinds of B-tree page get read.
+    pub(crate) fn database(table: &str, rows: &[Vec<SqlValue>]) -> Vec<u8> {
+        let usable = PAGE;
+        let mut pages: Vec<Vec<u8>> = vec![vec![0; PAGE]; 2];
+        let mut leaves = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            let payload = record(row);
+            let database = Database { data: &[], page_size: PAGE, usable };
+            let local = database.local_payload(payload.len());
+            let mut cell = varint(payload.len() as u64);
+            cell.extend(varint(index as u64 + 1));
+            cell.extend(&payload[..local]);
+            if local < payload.len() {
+                cell.extend((pages.len() as u32 + 1).to_be_bytes());
+                let chunks: Vec<&[u8]> = payload[local..].chunks(usable - 4).collect();
+                for (chunk_index, chunk) in chunks.iter().enumerate() {
+                    let next = if chunk_index + 1 < chunks.len() { pages.len() as u32 + 2 } else { 0 };
+                    let mut overflow = next.to_be_bytes().to_vec();
+                    overflow.extend(*chunk);
+                    overflow.resize(PAGE, 0);
+                    pages.push(overflow);
+                }
+            }
+            pages.push(leaf(0, &[cell]));
+            leaves.push(pages.len() as u32);
+        }
+        // Interior root: every leaf but the last is a cell, the last is the right pointer
+        let mut root = vec![INTERIOR_TABLE_PAGE, 0, 0];
+        root.extend((leaves.len() as u16 - 1).to_be_bytes());
+        root.extend([0, 0, 0]);
+        root.extend(leaves.last().unwrap().to_be_bytes());
+        let cells: Vec<Vec<u8>> = leaves[..leaves.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(index, page)| [page.to_be_bytes().to_vec(), varint(index as u64 + 1)].concat())
+            .collect();
+        pages[1] = with_cells(root, &cells);
+
+        let schema = vec![
+            SqlValue::Text("table".to_string()),
+            SqlValue::Text(table.to_string()),
+            SqlValue::Text(table.to_string()),
+            SqlValue::Integer(2),
+            SqlValue::Text(format!("CREATE TABLE {} (hnum INTEGER PRIMARY KEY, blob BLOB)", table)),
+        ];
+        let schema_payload = record(&schema);
+        let mut schema_cell = varint(schema_payload.len() as u64);
+        schema_cell.extend(varint(1));
+        schema_cell.extend(schema_payload);
+        pages[0] = leaf(100, &[schema_cell]);
+        pages[0][..16].copy_from_slice(MAGIC);
+        pages[0][16..18].copy_from_slice(&(PAGE as u16).to_be_bytes());
+        pages.concat()
+    }
+
+    fn leaf(header: usize, cells: &[Vec<u8>]) -> Vec<u8> {
+        let mut page = vec![0; header];
+        page.extend([LEAF_TABLE_PAGE, 0, 0]);
+        page.extend((cells.len() as u16).to_be_bytes());
+        page.extend([0, 0, 0]);
+        with_cells(page, cells)
+    }
+
+    // Appends the cell pointer array and lays the cells out from the page's end
+    fn with_cells(mut page: Vec<u8>, cells: &[Vec<u8>]) -> Vec<u8> {
+        let pointers = page.len();
+        page.resize(PAGE, 0);
+        let mut end = PAGE;
+        for (index, cell) in cells.iter().enumerate() {
+            end -= cell.len();
+            page[end..end + cell.len()].copy_from_slice(cell);
+            page[pointers + index * 2..pointers + index * 2 + 2].copy_from_slice(&(end as u16).to_be_bytes());
+        }
+        page
+    }
+
+    #[test]
+    fn test_reads_rows_with_overflow() {
+        let big: Vec<u8> = (0..1500u32).map(|n| n as u8).collect();
+        let rows = vec![
+            vec![SqlValue::Null, SqlValue::Blob(b"small".to_vec())],
+            vec![SqlValue::Null, SqlValue::Blob(big.clone())],
+            vec![SqlValue::Integer(-2), SqlValue::Text("text".to_string())],
+        ];
+        let data = database("Packages", &rows);
+        let database = Database::open(&data).unwrap();
+        assert_eq!(database.rows("Packages").unwrap(), rows);
+        assert!(database.rows("Missing").is_err());
+        assert!(Database::open(b"not a database").is_err());
+        assert!(Database::open(&data[..700]).unwrap().rows("Packages").is_err());
+    }
+}
@@ -0,0 +1,210 @@
+// Finding containers that run an older image than their tag points to now.
+//
+// Each running container's image reference is resolved in its registry and
+// the digest compared with the repo digests of the image the container
+// actually runs, by ID, so a tag that was already re-pulled locally still
+// counts as stale until the container is recreated. Recreating replaces the
+// container with one built from the same settings, keeping the old one,
+// renamed, until the new one has been created.
+
+use std::collections::HashMap;
+use serde_json::Value;
+use crate::docker::{ContainerSpec, DockerClient};
+use crate::error::DockerError;
+use crate::registry::{self, ImageRef, RegistryClient};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Freshness {
+    Current,
+    /// The tag now points to `remote`.
+    Stale { remote: String },
+    /// Why it can't be told, e.g. the image was built locally.
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleCheck {
+    pub container: String,
+    /// The reference the container was created from, e.g. "nginx:latest".
+    pub image: String,
+    pub freshness: Freshness,
+}
+
+/// Checks every running container, asking each registry about each image
+/// once. `connect` makes the client for a registry host; a registry that
+/// can't be reached leaves its containers unknown rather than failing.
+pub fn check_containers(
+    docker: &DockerClient,
+    connect: impl Fn(&str) -> Result<RegistryClient, DockerError>,
+) -> Result<Vec<StaleCheck>, DockerError> {
+    let mut clients: HashMap<String, Result<RegistryClient, String>> = HashMap::new();
+    let mut resolved: HashMap<String, Result<String, String>> = HashMap::new();
+    let mut checks = Vec::new();
+
+    for container in docker.list_containers()?.into_iter().filter(|c| c.status.starts_with("Up")) {
+        let inspect = first(&docker.inspect_container(&container.name)?)?;
+        let reference = inspect["Config"]["Image"].as_str().unwrap_or(&container.image).to_string();
+        let running = inspect["Image"].as_str().unwrap_or(&reference).to_string();
+
+        let freshness = match ImageRef::parse(&reference) {
+            _ if is_image_id(&reference) => Freshness::Unknown("started from an image ID, not a tag".to_string()),
+            Err(e) => Freshness::Unknown(e),
+            Ok(image) if image.digest.is_some() => Freshness::Unknown("pinned by digest".to_string()),
+            Ok(image) => {
+                let remote = resolved.entry(reference.clone()).or_insert_with(|| {
+                    let client = clients
+                        .entry(image.registry.clone())
+                        .or_insert_with(|| connect(&image.registry).map_err(|e| e.message().to_string()));
+                    match client {
+                        Ok(client) => client.digest(&image.repository, image.reference()).map_err(|e| e.message().to_string()),
+                        Err(e) => Err(e.clone()),
+                    }
+                });
+                match remote {
+                    Err(e) => Freshness::Unknown(e.clone()),
+                    Ok(remote) => {
+                        let local = registry::local_digests(docker, &running, &image)?;
+                        if local.is_empty() {
+                            Freshness::Unknown(format!("no digest from {}; built or loaded locally", image.registry))
+                        } else if local.contains(remote) {
+                            Freshness::Current
+                        } else {
+                            Freshness::Stale { remote: remote.clone() }
+                        }
+                    }
+                }
+            }
+        };
+        checks.push(StaleCheck { container: container.name, image: reference, freshness });
+    }
+    Ok(checks)
+}
+
+/// Stale containers grouped by image, in the order first seen, so each
+/// image is pulled once.
+pub fn stale_by_image(checks: &[StaleCheck]) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for check in checks.iter().filter(|check| matches!(check.freshness, Freshness::Stale { .. })) {
+        match groups.iter_mut().find(|(image, _)| *image == check.image) {
+            Some((_, containers)) => containers.push(check.container.clone()),
+            None => groups.push((check.image.clone(), vec![check.container.clone()])),
+        }
+    }
+    groups
+}
+
+/// Replaces `container` with a new one from the same settings, which picks
+/// up whatever its tag points to locally, so pull first. The old container
+/// is renamed out of the way before it is stopped, so one started with
+/// `--rm` is already clear of its name when the daemon deletes it, and it is
+/// only removed once the new one is created. If creating fails, whatever
+/// was half-created is removed and the old container gets its name back and
+/// is started again. A container with settings that wouldn't carry over is
+/// left alone unless `force`.
+pub fn recreate(docker: &DockerClient, container: &str, force: bool) -> Result<(), DockerError> {
+    let inspect = first(&docker.inspect_container(container)?)?;
+    let lost = ContainerSpec::unsupported_settings(&inspect);
+    if !lost.is_empty() && !force {
+        return Err(DockerError::Other(format!(
+            "Recreating '{}' would drop settings dui can't carry over: {}. Use --force to recreate it anyway",
+            container,
+            lost.join(", ")
+        )));
+    }
+    // The old image may be gone already; then every setting counts as the container's own
+    let image = match inspect["Image"].as_str() {
+        Some(id) => docker.inspect_image(id).ok().and_then(|json| first(&json).ok()).unwrap_or(Value::Null),
+        None => Value::Null,
+    };
+    let spec = ContainerSpec::from_inspect(&inspect, &image);
+    let backup = format!("{}-stale", spec.name);
+
+    docker.rename_container(&spec.name, &backup)?;
+    if let Err(e) = docker.stop_container(&backup) {
+        let _ = docker.rename_container(&backup, &spec.name);
+        return Err(e);
+    }
+    if let Err(e) = docker.create_container(&spec) {
+        // Created but not started still holds the name
+        let _ = docker.remove_container(&spec.name);
+        if spec.auto_remove {
+            return Err(DockerError::Other(format!(
+                "{}; '{}' was started with --rm, so the daemon removed it when it stopped",
+                e, container
+            )));
+        }
+        let _ = docker.rename_container(&backup, &spec.name).and_then(|_| docker.start_container(&spec.name));
+        return Err(e);
+    }
+    match docker.remove_container(&backup) {
+        // A --rm container is deleted by the daemon as soon as it stops
+        Err(DockerError::NoSuchContainer(_)) if spec.auto_remove => Ok(()),
+        result => result,
+    }
+}
+
+// The one object in `docker inspect`'s array
+fn first(json: &str) -> Result<Value, DockerError> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Failed to parse inspect output: {}", e))?;
+    match value {
+        Value::Array(mut items) if !items.is_empty() => Ok(items.swap_remove(0)),
+        _ => Err("Inspect output is empty".into()),
+    }
+}
+
+// "sha256:..." or a bare hex ID, which `docker ps` shows once a tag has moved on
+fn is_image_id(reference: &str) -> bool {
+    let hex = reference.strip_prefix("sha256:").unwrap_or(reference);
+    (12..=64).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::docker::Container;
+    use crate::fake::FakeBackend;
+    use crate::registry::tests::{response, serve};
+
+    // The demo postgres:16 was pulled as this
+    const POSTGRES: &str = "sha256:d2c94e258dcb3c5ac2798d32e1249e42ef01cba4841c2234249495f87264ac5a";
+
+    #[test]
+    fn test_check_and_recreate_stale_containers() {
+        let (address, server) = serve(|_| {
+            vec![
+                response("200 OK", &[("Docker-Content-Digest", "sha256:0b1c2d")], ""),
+                response("200 OK", &[("Docker-Content-Digest", POSTGRES)], ""),
+            ]
+        });
+        let fake = Arc::new(FakeBackend::demo().with_container(Container {
+            id: "5a4b3c2d1e0f5a4b3c2d1e0f".to_string(),
+            name: "web2".to_string(),
+            image: "nginx:1.25".to_string(),
+            status: "Up 3 hours".to_string(),
+            ports: String::new(),
+            labels: Default::default(),
+        }));
+        let docker = DockerClient::with_backend(fake.clone());
+
+        let checks = check_containers(&docker, |_| Ok(RegistryClient::new(&address, &format!("http://{}", address), None))).unwrap();
+        let found: Vec<(&str, &Freshness)> = checks.iter().map(|check| (check.container.as_str(), &check.freshness)).collect();
+        let stale = Freshness::Stale { remote: "sha256:0b1c2d".to_string() };
+        // The worker isn't running, and nginx:1.25 is only asked about once
+        assert_eq!(found, vec![("web", &stale), ("db", &Freshness::Current), ("web2", &stale)]);
+        assert_eq!(
+            server.join().unwrap(),
+            vec!["HEAD /v2/library/nginx/manifests/1.25", "HEAD /v2/library/postgres/manifests/16"]
+        );
+        assert_eq!(stale_by_image(&checks), vec![("nginx:1.25".to_string(), vec!["web".to_string(), "web2".to_string()])]);
+
+        recreate(&docker, "web", false).unwrap();
+        let calls = fake.calls();
+        assert_eq!(calls[calls.len() - 4..], ["rename web web-stale", "stop web-stale", "create web nginx:1.25", "remove web-stale"]);
+        let web = fake.containers().into_iter().find(|c| c.name == "web").unwrap();
+        assert_eq!(web.labels.get("app").map(String::as_str), Some("shop"));
+
+        assert!(is_image_id("a8758716bb6a"));
+        assert!(!is_image_id("nginx:1.25"));
+    }
+}
@@ -0,0 +1,229 @@
+// Structured (JSON) log lines.
+//
+// Many services log one JSON object per line. Those lines are rendered as
+// "time LEVEL message key=value ...", or as columns of chosen fields, and can
+// be filtered on field values. Anything that isn't a JSON log object passes
+// through unchanged, so banners and stack traces in between still show. The
+// rendered text feeds the search and the level colouring like any other line.
+
+use serde_json::{Map, Value};
+use crate::logs::LogLine;
+use crate::tail::TaggedLine;
+use crate::utils::format_timestamp;
+
+// Keys services commonly use, tried in order for `level`, `time` and `message`
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity", "log.level", "loglevel"];
+const TIME_KEYS: &[&str] = &["time", "timestamp", "ts", "@timestamp"];
+const MESSAGE_KEYS: &[&str] = &["msg", "message", "@message"];
+
+/// `--where key=value` or `key!=value`. Keys may be dotted paths into nested
+/// objects; values compare case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub key: String,
+    pub value: String,
+    pub negate: bool,
+}
+
+impl FieldFilter {
+    pub fn parse(text: &str) -> Result<FieldFilter, String> {
+        let (key, value, negate) = match text.split_once("!=") {
+            Some((key, value)) => (key, value, true),
+            None => match text.split_once('=') {
+                Some((key, value)) => (key, value, false),
+                None => return Err(format!("Invalid filter '{}': expected key=value or key!=value", text)),
+            },
+        };
+        if key.trim().is_empty() {
+            return Err(format!("Invalid filter '{}': the field name is empty", text));
+        }
+        Ok(FieldFilter { key: key.trim().to_string(), value: value.trim().to_string(), negate })
+    }
+
+    fn matches(&self, json: &Map<String, Value>) -> bool {
+        let equal = field_text(json, &self.key).is_some_and(|actual| actual.eq_ignore_ascii_case(&self.value));
+        equal != self.negate
+    }
+}
+
+/// How JSON log lines are shown. The default pretty-prints them.
+#[derive(Debug, Clone, Default)]
+pub struct JsonView {
+    /// All must match for a JSON line to be shown.
+    pub filters: Vec<FieldFilter>,
+    /// Show only these fields, as columns.
+    pub fields: Vec<String>,
+    /// Leave every line exactly as the container wrote it.
+    pub raw: bool,
+    // Column widths seen so far; followed logs can only widen them
+    widths: Vec<usize>,
+}
+
+impl JsonView {
+    pub fn new(filters: Vec<FieldFilter>, fields: Vec<String>, raw: bool) -> JsonView {
+        JsonView { filters, fields, raw, widths: Vec::new() }
+    }
+
+    /// The line as it should be shown, or None when the filters drop it.
+    pub fn render(&mut self, line: LogLine) -> Option<LogLine> {
+        if self.raw {
+            return Some(line);
+        }
+        let json = match parse_log_object(&line.text) {
+            Some(json) => json,
+            None => return Some(line),
+        };
+        if !self.filters.iter().all(|filter| filter.matches(&json)) {
+            return None;
+        }
+        let text = if self.fields.is_empty() { pretty(&json) } else { self.columns(&json) };
+        Some(LogLine { text, ..line })
+    }
+
+    pub fn apply(mut self, lines: impl Iterator<Item = LogLine>) -> impl Iterator<Item = LogLine> {
+        lines.filter_map(move |line| self.render(line))
+    }
+
+    pub fn apply_tagged(mut self, lines: impl Iterator<Item = TaggedLine>) -> impl Iterator<Item = TaggedLine> {
+        lines.filter_map(move |tagged| Some(TaggedLine { line: self.render(tagged.line)?, ..tagged }))
+    }
+
+    fn columns(&mut self, json: &Map<String, Value>) -> String {
+        self.widths.resize(self.fields.len(), 0);
+        let mut cells = Vec::new();
+        for (index, field) in self.fields.iter().enumerate() {
+            let value = field_text(json, field).unwrap_or_else(|| "-".to_string());
+            self.widths[index] = self.widths[index].max(value.chars().count());
+            cells.push(format!("{:<width$}", value, width = self.widths[index]));
+        }
+        cells.join("  ").trim_end().to_string()
+    }
+}
+
+// A JSON object that looks like a log record rather than arbitrary data
+fn parse_log_object(text: &str) -> Option<Map<String, Value>> {
+    let text = text.trim();
+    if !text.starts_with('{') {
+        return None;
+    }
+    match serde_json::from_str(text).ok()? {
+        Value::Object(json) if [LEVEL_KEYS, MESSAGE_KEYS].iter().any(|keys| first_of(&json, keys).is_some()) => Some(json),
+        _ => None,
+    }
+}
+
+// "time LEVEL message", then the remaining fields as key=value
+fn pretty(json: &Map<String, Value>) -> String {
+    let mut parts = Vec::new();
+    let mut shown = Vec::new();
+    for (name, keys) in [("time", TIME_KEYS), ("level", LEVEL_KEYS), ("message", MESSAGE_KEYS)] {
+        if let Some((key, _)) = first_of(json, keys) {
+            shown.push(key);
+            parts.push(field_text(json, name).unwrap_or_default());
+        }
+    }
+    for (key, value) in json {
+        if !shown.contains(&key.as_str()) {
+            parts.push(format!("{}={}", key, value_text(value, true)));
+        }
+    }
+    parts.join(" ")
+}
+
+// The first of `keys` present, with its value
+fn first_of<'a>(json: &'a Map<String, Value>, keys: &[&'static str]) -> Option<(&'static str, &'a Value)> {
+    keys.iter().find_map(|key| Some((*key, lookup(json, key)?)))
+}
+
+// An exact key first (e.g. "log.level"), then a dotted path into nested objects
+fn lookup<'a>(json: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    if let Some(value) = json.get(key) {
+        return Some(value);
+    }
+    let mut parts = key.split('.');
+    let mut value = json.get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+/// A field as text. `level`, `time` and `message` also find their common
+/// aliases; levels are upper-cased and numeric (pino, bunyan) levels named.
+fn field_text(json: &Map<String, Value>, key: &str) -> Option<String> {
+    let aliases = match key {
+        "level" => LEVEL_KEYS,
+        "time" => TIME_KEYS,
+        "message" | "msg" => MESSAGE_KEYS,
+        _ => return lookup(json, key).map(|value| value_text(value, false)),
+    };
+    let value = lookup(json, key).or_else(|| first_of(json, aliases).map(|(_, value)| value))?;
+    Some(match (key, value) {
+        ("level", Value::Number(number)) => match number.as_u64().unwrap_or(0) {
+            0..=10 => "TRACE".to_string(),
+            11..=20 => "DEBUG".to_string(),
+            21..=30 => "INFO".to_string(),
+            31..=40 => "WARN".to_string(),
+            41..=50 => "ERROR".to_string(),
+            _ => "FATAL".to_string(),
+        },
+        ("level", value) => value_text(value, false).to_uppercase(),
+        // Epoch seconds or milliseconds
+        ("time", Value::Number(number)) => match number.as_f64() {
+            Some(time) if time > 1e11 => format_timestamp((time / 1000.0) as i64),
+            Some(time) => format_timestamp(time as i64),
+            None => number.to_string(),
+        },
+        (_, value) => value_text(value, false),
+    })
+}
+
+// Strings unquoted unless they need quoting in key=value output
+fn value_text(value: &Value, quote_spaces: bool) -> String {
+    match value {
+        Value::String(text) if !(quote_spaces && (text.is_empty() || text.contains(char::is_whitespace))) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::LogStream;
+
+    fn line(text: &str) -> LogLine {
+        LogLine::parse(LogStream::Stdout, text, false)
+    }
+
+    #[test]
+    fn test_pretty_renders_json_and_passes_text_through() {
+        let mut view = JsonView::default();
+        let json = r#"{"level":"error","msg":"payment failed","time":"2024-01-02T03:04:05Z","user_id":42,"reason":"card declined"}"#;
+        assert_eq!(
+            view.render(line(json)).unwrap().text,
+            r#"2024-01-02T03:04:05Z ERROR payment failed reason="card declined" user_id=42"#
+        );
+        // pino: numeric level and epoch milliseconds
+        assert_eq!(view.render(line(r#"{"level":30,"time":1704164645000,"msg":"listening"}"#)).unwrap().text,
+            "2024-01-02 03:04:05 +0000 UTC INFO listening");
+        assert_eq!(view.render(line("Listening on :8080")).unwrap().text, "Listening on :8080");
+        assert_eq!(view.render(line(r#"{"rows":3}"#)).unwrap().text, r#"{"rows":3}"#);
+        assert_eq!(JsonView { raw: true, ..Default::default() }.render(line(json)).unwrap().text, json);
+    }
+
+    #[test]
+    fn test_filters_and_columns() {
+        let filters = vec![FieldFilter::parse("level=error").unwrap(), FieldFilter::parse("http.status!=404").unwrap()];
+        let mut view = JsonView::new(filters, vec!["level".to_string(), "user_id".to_string(), "msg".to_string()], false);
+
+        let error = r#"{"severity":"ERROR","msg":"boom","user_id":42,"http":{"status":500}}"#;
+        assert_eq!(view.render(line(error)).unwrap().text, "ERROR  42  boom");
+        assert!(view.render(line(r#"{"level":"info","msg":"ok"}"#)).is_none());
+        assert!(view.render(line(r#"{"level":"error","msg":"gone","http":{"status":404}}"#)).is_none());
+        assert_eq!(view.render(line(r#"{"level":"error","message":"no user"}"#)).unwrap().text, "ERROR  -   no user");
+        assert_eq!(view.render(line("panic: runtime error")).unwrap().text, "panic: runtime error");
+
+        assert!(FieldFilter::parse("level").is_err());
+        assert_eq!(FieldFilter::parse("user_id = 42").unwrap().value, "42");
+    }
+}
@@ -0,0 +1,658 @@
+// Tar archives, the format the daemon uses to move files in and out of
+// containers.
+//
+// Only what `cp` needs: ustar headers with GNU long-name records when writing,
+// and ustar, GNU and PAX headers when reading. Both directions stream, so a
+// directory tree never has to fit in memory.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    HardLink,
+    /// Devices, FIFOs and anything else `cp` skips.
+    Other,
+}
+
+impl EntryKind {
+    fn flag(self) -> u8 {
+        match self {
+            EntryKind::File | EntryKind::Other => b'0',
+            EntryKind::HardLink => b'1',
+            EntryKind::Symlink => b'2',
+            EntryKind::Directory => b'5',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Relative path, with a trailing `/` for directories.
+    pub path: String,
+    pub kind: EntryKind,
+    /// Length of the contents; only files have any.
+    pub size: u64,
+    pub mode: u32,
+    /// Unix seconds.
+    pub mtime: u64,
+    /// Target of a symlink or hard link.
+    pub link: Option<String>,
+}
+
+impl Entry {
+    /// The header blocks for this entry. Names and link targets too long for
+    /// ustar get a GNU long-name record in front, which every reader accepts.
+    pub fn header(&self) -> Vec<u8> {
+        let mut blocks = Vec::new();
+        if self.path.len() > 100 {
+            blocks.extend(long_name(b'L', &self.path));
+        }
+        if let Some(link) = self.link.as_ref().filter(|link| link.len() > 100) {
+            blocks.extend(long_name(b'K', link));
+        }
+        let size = if self.kind == EntryKind::File { self.size } else { 0 };
+        blocks.extend_from_slice(&header_block(&self.path, self.kind.flag(), size, self.mode, self.mtime, self.link.as_deref()));
+        blocks
+    }
+}
+
+/// Zero bytes that pad contents of `size` bytes out to a whole block.
+pub fn padding(size: u64) -> usize {
+    (BLOCK - (size % BLOCK as u64) as usize) % BLOCK
+}
+
+/// The two empty blocks that end an archive.
+pub fn end_of_archive() -> Vec<u8> {
+    vec![0; BLOCK * 2]
+}
+
+fn long_name(flag: u8, name: &str) -> Vec<u8> {
+    let mut data = name.as_bytes().to_vec();
+    data.push(0);
+    let mut blocks = header_block("././@LongLink", flag, data.len() as u64, 0o644, 0, None).to_vec();
+    let pad = padding(data.len() as u64);
+    blocks.extend(data);
+    blocks.extend(vec![0; pad]);
+    blocks
+}
+
+fn header_block(path: &str, flag: u8, size: u64, mode: u32, mtime: u64, link: Option<&str>) -> [u8; BLOCK] {
+    let mut block = [0u8; BLOCK];
+    put_text(&mut block[0..100], path);
+    put_number(&mut block[100..108], u64::from(mode & 0o7777));
+    put_number(&mut block[108..116], 0);
+    put_number(&mut block[116..124], 0);
+    put_number(&mut block[124..136], size);
+    put_number(&mut block[136..148], mtime);
+    block[156] = flag;
+    put_text(&mut block[157..257], link.unwrap_or(""));
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+
+    block[148..156].copy_from_slice(b"        ");
+    let checksum: u32 = block.iter().map(|&byte| u32::from(byte)).sum();
+    block[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    block
+}
+
+// Truncated when too long; the long-name record carries the full text
+fn put_text(field: &mut [u8], text: &str) {
+    let bytes = text.as_bytes();
+    let len = bytes.len().min(field.len());
+    field[..len].copy_from_slice(&bytes[..len]);
+}
+
+// Octal when it fits, otherwise the GNU base-256 form (files over 8 GiB)
+fn put_number(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    if value < 1u64 << (3 * digits) {
+        field.copy_from_slice(format!("{:0width$o}\0", value, width = digits).as_bytes());
+    } else {
+        field.fill(0);
+        field[0] = 0x80;
+        let bytes = value.to_be_bytes();
+        let len = field.len();
+        field[len - 8..].copy_from_slice(&bytes);
+    }
+}
+
+fn get_number(field: &[u8]) -> io::Result<u64> {
+    if field.first().is_some_and(|&byte| byte & 0x80 != 0) {
+        return Ok(field[1..].iter().fold(0u64, |value, &byte| (value << 8) | u64::from(byte)));
+    }
+    let text = String::from_utf8_lossy(field);
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| invalid(format!("bad number in tar header: {:?}", text)))
+}
+
+fn get_text(field: &[u8]) -> String {
+    let end = field.iter().position(|&byte| byte == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads an archive entry by entry. After `next_entry`, reading from the
+/// `TarReader` itself yields that entry's contents.
+pub struct TarReader<R> {
+    inner: R,
+    // Contents of the current entry not yet read, then its padding
+    remaining: u64,
+    padding: usize,
+    position: u64,
+    done: bool,
+}
+
+impl<R: Read> TarReader<R> {
+    pub fn new(inner: R) -> TarReader<R> {
+        TarReader { inner, remaining: 0, padding: 0, position: 0, done: false }
+    }
+
+    /// Bytes of the archive consumed so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn next_entry(&mut self) -> io::Result<Option<Entry>> {
+        let mut long_path = None;
+        let mut long_link = None;
+        let mut pax_size = None;
+        loop {
+            self.skip_rest()?;
+            if self.done {
+                return Ok(None);
+            }
+            let mut block = [0u8; BLOCK];
+            if !self.read_block(&mut block)? {
+                self.done = true;
+                return Ok(None);
+            }
+            if block.iter().all(|&byte| byte == 0) {
+                self.done = true;
+                return Ok(None);
+            }
+
+            let stored = get_number(&block[148..156])?;
+            let computed: u64 = block
+                .iter()
+                .enumerate()
+                .map(|(index, &byte)| if (148..156).contains(&index) { u64::from(b' ') } else { u64::from(byte) })
+                .sum();
+            if stored != computed {
+                return Err(invalid("not a tar archive (header checksum mismatch)".to_string()));
+            }
+
+            let size = get_number(&block[124..136])?;
+            let flag = block[156];
+            self.remaining = size;
+            self.padding = padding(size);
+
+            match flag {
+                b'L' => long_path = Some(self.read_text()?),
+                b'K' => long_link = Some(self.read_text()?),
+                // PAX extended header: "length key=value\n" records
+                b'x' => {
+                    for record in self.read_text()?.split('\n') {
+                        let (key, value) = match record.split_once(' ').and_then(|(_, record)| record.split_once('=')) {
+                            Some(pair) => pair,
+                            None => continue,
+                        };
+                        match key {
+                            "path" => long_path = Some(value.to_string()),
+                            "linkpath" => long_link = Some(value.to_string()),
+                            "size" => pax_size = value.parse().ok(),
+                            _ => {}
+                        }
+                    }
+                }
+                b'g' => {}
+                _ => {
+                    let kind = match flag {
+                        b'0' | 0 | b'7' => EntryKind::File,
+                        b'1' => EntryKind::HardLink,
+                        b'2' => EntryKind::Symlink,
+                        b'5' => EntryKind::Directory,
+                        _ => EntryKind::Other,
+                    };
+                    let mut path = get_text(&block[0..100]);
+                    let prefix = get_text(&block[345..500]);
+                    if block[257..262] == *b"ustar" && !prefix.is_empty() {
+                        path = format!("{}/{}", prefix, path);
+                    }
+                    let link = get_text(&block[157..257]);
+                    let size = pax_size.unwrap_or(size);
+                    self.remaining = size;
+                    self.padding = padding(size);
+                    return Ok(Some(Entry {
+                        path: long_path.unwrap_or(path),
+                        kind,
+                        size,
+                        mode: get_number(&block[100..108])? as u32,
+                        mtime: get_number(&block[136..148])?,
+                        link: long_link.or_else(|| Some(link).filter(|link| !link.is_empty())),
+                    }));
+                }
+            }
+        }
+    }
+
+    // Contents of a metadata record, without the trailing NULs
+    fn read_text(&mut self) -> io::Result<String> {
+        let mut data = Vec::new();
+        self.read_to_end(&mut data)?;
+        Ok(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())
+    }
+
+    fn skip_rest(&mut self) -> io::Result<()> {
+        let skip = self.remaining + self.padding as u64;
+        let skipped = io::copy(&mut (&mut self.inner).take(skip), &mut io::sink())?;
+        self.position += skipped;
+        if skipped < skip {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "tar archive ended in the middle of an entry"));
+        }
+        self.remaining = 0;
+        self.padding = 0;
+        Ok(())
+    }
+
+    // False at a clean end of input; some writers leave off the end blocks
+    fn read_block(&mut self, block: &mut [u8; BLOCK]) -> io::Result<bool> {
+        let mut filled = 0;
+        while filled < BLOCK {
+            match self.inner.read(&mut block[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "tar archive ended in the middle of a header")),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.position += BLOCK as u64;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for TarReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let max = buf.len().min(self.remaining.min(usize::MAX as u64) as usize);
+        let read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "tar archive ended in the middle of an entry"));
+        }
+        self.remaining -= read as u64;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+/// A tar archive of a local file or directory tree, produced as it is read.
+pub struct Packer {
+    entries: VecDeque<(PathBuf, Entry)>,
+    count: usize,
+    size: u64,
+    pending: Vec<u8>,
+    offset: usize,
+    file: Option<(io::Take<File>, usize)>,
+    finished: bool,
+}
+
+impl Packer {
+    /// Walks `source`, which is named `root` inside the archive.
+    pub fn new(source: &Path, root: &str) -> io::Result<Packer> {
+        let mut entries = VecDeque::new();
+        walk(source, root.trim_end_matches('/').to_string(), &mut entries)?;
+        let size = entries
+            .iter()
+            .map(|(_, entry)| entry.header().len() as u64 + if entry.kind == EntryKind::File { entry.size + padding(entry.size) as u64 } else { 0 })
+            .sum::<u64>()
+            + (BLOCK * 2) as u64;
+        Ok(Packer { count: entries.len(), entries, size, pending: Vec::new(), offset: 0, file: None, finished: false })
+    }
+
+    /// Length of the whole archive in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Files, directories and links in the archive.
+    pub fn entries(&self) -> usize {
+        self.count
+    }
+}
+
+fn walk(path: &Path, name: String, entries: &mut VecDeque<(PathBuf, Entry)>) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    let file_type = metadata.file_type();
+    let mtime = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map_or(0, |age| age.as_secs());
+    let mode = file_mode(&metadata);
+
+    if file_type.is_dir() {
+        entries.push_back((path.to_path_buf(), Entry { path: format!("{}/", name), kind: EntryKind::Directory, size: 0, mode, mtime, link: None }));
+        let mut children: Vec<_> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            walk(&child.path(), format!("{}/{}", name, child.file_name().to_string_lossy()), entries)?;
+        }
+    } else if file_type.is_symlink() {
+        let link = fs::read_link(path)?.to_string_lossy().into_owned();
+        entries.push_back((path.to_path_buf(), Entry { path: name, kind: EntryKind::Symlink, size: 0, mode, mtime, link: Some(link) }));
+    } else if file_type.is_file() {
+        entries.push_back((path.to_path_buf(), Entry { path: name, kind: EntryKind::File, size: metadata.len(), mode, mtime, link: None }));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    match (metadata.is_dir(), metadata.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
+impl Read for Packer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.offset < self.pending.len() {
+                let len = buf.len().min(self.pending.len() - self.offset);
+                buf[..len].copy_from_slice(&self.pending[self.offset..self.offset + len]);
+                self.offset += len;
+                return Ok(len);
+            }
+            if let Some((file, pad)) = &mut self.file {
+                if file.limit() > 0 {
+                    let read = file.read(buf)?;
+                    if read == 0 {
+                        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file shrank while it was being copied"));
+                    }
+                    return Ok(read);
+                }
+                self.pending = vec![0; *pad];
+                self.offset = 0;
+                self.file = None;
+                continue;
+            }
+            match self.entries.pop_front() {
+                Some((path, entry)) => {
+                    if entry.kind == EntryKind::File {
+                        let file = File::open(&path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                        self.file = Some((file.take(entry.size), padding(entry.size)));
+                    }
+                    self.pending = entry.header();
+                    self.offset = 0;
+                }
+                None if !self.finished => {
+                    self.finished = true;
+                    self.pending = end_of_archive();
+                    self.offset = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Extracts `archive` into the existing directory `dest`. With `root`, the
+/// archive's top-level entry is written under that name instead.
+/// `progress` gets the archive bytes read and the entries written so far.
+pub fn unpack<R: Read>(archive: R, dest: &Path, root: Option<&str>, mut progress: impl FnMut(u64, u64)) -> io::Result<u64> {
+    let mut archive = TarReader::new(archive);
+    let mut written = 0;
+    // Set last, so read-only directories can still be filled
+    let mut directory_modes = Vec::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    while let Some(entry) = archive.next_entry()? {
+        let target = match entry_path(dest, &entry.path, root)? {
+            Some(target) => target,
+            None => continue,
+        };
+        match entry.kind {
+            EntryKind::Directory => {
+                create_dirs(dest, &target)?;
+                directory_modes.push((target, entry.mode));
+            }
+            EntryKind::File => {
+                create_parent(dest, &target)?;
+                // A link left here by an earlier entry is replaced, not written through
+                let _ = fs::remove_file(&target);
+                let mut file = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&target)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", target.display(), e)))?;
+                loop {
+                    let read = archive.read(&mut buffer)?;
+                    if read == 0 {
+                        break;
+                    }
+                    io::Write::write_all(&mut file, &buffer[..read])?;
+                    progress(archive.position(), written);
+                }
+                set_mode(&target, entry.mode)?;
+            }
+            EntryKind::Symlink => {
+                create_parent(dest, &target)?;
+                let _ = fs::remove_file(&target);
+                symlink(entry.link.as_deref().unwrap_or(""), &target)?;
+            }
+            EntryKind::HardLink => {
+                let source = match entry.link.as_deref().map(|link| entry_path(dest, link, root)) {
+                    Some(Ok(Some(source))) => source,
+                    _ => return Err(invalid(format!("hard link {} points outside the archive", entry.path))),
+                };
+                create_parent(dest, &source)?;
+                create_parent(dest, &target)?;
+                let _ = fs::remove_file(&target);
+                fs::hard_link(source, &target)?;
+            }
+            EntryKind::Other => continue,
+        }
+        written += 1;
+        progress(archive.position(), written);
+    }
+    for (directory, mode) in directory_modes.into_iter().rev() {
+        set_mode(&directory, mode)?;
+    }
+    Ok(written)
+}
+
+// Where an archive path lands under `dest`, refusing anything that would
+// escape it. None for an entry for the archive root itself (`./`).
+fn entry_path(dest: &Path, path: &str, root: Option<&str>) -> io::Result<Option<PathBuf>> {
+    let mut parts = Vec::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir | Component::RootDir => {}
+            _ => return Err(invalid(format!("refusing to extract {} outside the destination", path))),
+        }
+    }
+    if parts.is_empty() {
+        return Ok(None);
+    }
+    if let Some(root) = root {
+        parts[0] = root.to_string();
+    }
+    Ok(Some(parts.iter().fold(dest.to_path_buf(), |target, part| target.join(part))))
+}
+
+fn create_parent(dest: &Path, path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => create_dirs(dest, parent),
+        None => Ok(()),
+    }
+}
+
+// Creates the directories from `dest` down to `path`, refusing to pass
+// through a symlink: an earlier entry could have pointed one anywhere.
+fn create_dirs(dest: &Path, path: &Path) -> io::Result<()> {
+    let relative = path
+        .strip_prefix(dest)
+        .map_err(|_| invalid(format!("refusing to extract {} outside the destination", path.display())))?;
+    let mut current = dest.to_path_buf();
+    for part in relative.components() {
+        current.push(part);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(invalid(format!("refusing to extract through the symlink {}", current.display())))
+            }
+            Ok(metadata) if metadata.is_dir() => {}
+            Ok(_) => return Err(invalid(format!("{} is not a directory", current.display()))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => fs::create_dir(&current)?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(link: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(link, path)
+}
+
+// Windows needs to know the target's type; leave the link out
+#[cfg(not(unix))]
+fn symlink(_link: &str, _path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dui-tar-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_pack_and_unpack_round_trip() {
+        let dir = scratch("round-trip");
+        let deep = format!("site/{}", "nested/".repeat(20));
+        fs::create_dir_all(dir.join(&deep)).unwrap();
+        fs::write(dir.join("site/index.html"), "<h1>hi</h1>").unwrap();
+        fs::write(dir.join(&deep).join("app.js"), vec![b'x'; 1500]).unwrap();
+
+        let mut packer = Packer::new(&dir.join("site"), "site").unwrap();
+        assert_eq!(packer.entries(), 23);
+        let mut archive = Vec::new();
+        packer.read_to_end(&mut archive).unwrap();
+        assert_eq!(archive.len() as u64, packer.size());
+
+        let out = dir.join("out");
+        fs::create_dir(&out).unwrap();
+        let mut last = (0, 0);
+        let written = unpack(archive.as_slice(), &out, Some("public"), |bytes, entries| last = (bytes, entries)).unwrap();
+        assert_eq!(written, 23);
+        assert_eq!(last.1, 23);
+        assert!(last.0 > 1500 && last.0 < archive.len() as u64);
+        assert_eq!(fs::read_to_string(out.join("public/index.html")).unwrap(), "<h1>hi</h1>");
+        assert_eq!(fs::read(out.join("public").join(&deep["site/".len()..]).join("app.js")).unwrap().len(), 1500);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reader_handles_pax_and_rejects_escapes() {
+        let mut archive = Vec::new();
+        let record = "32 path=etc/very-long-name.conf\n";
+        archive.extend_from_slice(&header_block("PaxHeaders/x", b'x', record.len() as u64, 0o644, 0, None));
+        archive.extend_from_slice(record.as_bytes());
+        archive.extend(vec![0; padding(record.len() as u64)]);
+        archive.extend(Entry { path: "etc/short".to_string(), kind: EntryKind::File, size: 3, mode: 0o600, mtime: 1, link: None }.header());
+        archive.extend_from_slice(b"abc");
+        archive.extend(vec![0; padding(3)]);
+        archive.extend(end_of_archive());
+
+        let mut reader = TarReader::new(archive.as_slice());
+        let entry = reader.next_entry().unwrap().unwrap();
+        assert_eq!(entry.path, "etc/very-long-name.conf");
+        assert_eq!((entry.size, entry.mode), (3, 0o600));
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "abc");
+        assert!(reader.next_entry().unwrap().is_none());
+
+        let dest = Path::new("/tmp/out");
+        assert!(entry_path(dest, "../../etc/passwd", None).is_err());
+        assert_eq!(entry_path(dest, "/nginx/nginx.conf", Some("conf")).unwrap(), Some(PathBuf::from("/tmp/out/conf/nginx.conf")));
+        assert!(TarReader::new(&[1u8; 512][..]).next_entry().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_does_not_write_through_symlinks() {
+        let dir = scratch("symlinks");
+        let outside = dir.join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("passwd"), "root").unwrap();
+        let out = dir.join("out");
+        fs::create_dir(&out).unwrap();
+        let archive = |entries: &[(&str, EntryKind, &str)]| {
+            let mut archive = Vec::new();
+            for (path, kind, content) in entries {
+                let link = (*kind == EntryKind::Symlink).then(|| content.to_string());
+                let size = if *kind == EntryKind::File { content.len() as u64 } else { 0 };
+                archive.extend(Entry { path: path.to_string(), kind: *kind, size, mode: 0o644, mtime: 0, link }.header());
+                if *kind == EntryKind::File {
+                    archive.extend_from_slice(content.as_bytes());
+                    archive.extend(vec![0; padding(size)]);
+                }
+            }
+            archive.extend(end_of_archive());
+            archive
+        };
+        let outside_path = outside.to_str().unwrap();
+
+        // A directory symlink followed by a file beneath it
+        let escape = archive(&[("x", EntryKind::Symlink, outside_path), ("x/evil", EntryKind::File, "pwned")]);
+        assert!(unpack(escape.as_slice(), &out, None, |_, _| {}).is_err());
+        assert!(!outside.join("evil").exists());
+
+        // A file symlink followed by a file of the same name replaces the link
+        let target = format!("{}/passwd", outside_path);
+        let replace = archive(&[("y", EntryKind::Symlink, &target), ("y", EntryKind::File, "pwned")]);
+        assert_eq!(unpack(replace.as_slice(), &out, None, |_, _| {}).unwrap(), 2);
+        assert_eq!(fs::read_to_string(outside.join("passwd")).unwrap(), "root");
+        assert_eq!(fs::read_to_string(out.join("y")).unwrap(), "pwned");
+        assert!(!fs::symlink_metadata(out.join("y")).unwrap().file_type().is_symlink());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,25 @@
 use colored::*;
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crossterm::{cursor, execute, terminal::{self, ClearType}};
+use crate::build::{BuildLineKind, BuildStep};
+use crate::context::DockerContext;
+use crate::copy::CopyProgress;
+use crate::disk::{DiskUsage, Reclaim, ReclaimAction, UsageItem, UsageKind};
 use crate::docker::{Container, Image, ContainerStats, Network, Volume, ContainerProcess};
+use crate::error::DockerError;
+use crate::events::DockerEvent;
+use crate::layers::{ChangeKind, ImageAnalysis};
+use crate::logs::{LogLevel, LogLine, LogStream};
+use crate::progress::{LayerPhase, TransferProgress};
+use crate::prune::{self, Orphan};
+use crate::registry::{DigestComparison, DigestStatus, ImageRef, Manifest};
+use crate::search::SearchLine;
+use crate::stale::{Freshness, StaleCheck};
+use crate::tail::TaggedLine;
+use crate::utils::{format_size, format_timestamp, truncate_string};
 
 pub struct UserInterface;
 
@@ -51,21 +70,23 @@ impl UserInterface {
         println!("{}", "🐳 CONTAINER MANAGEMENT".green().bold());
         println!("{}", "─".repeat(50).dimmed());
         println!("  {} {} {}", "list".green().bold(), "".dimmed(), "List all containers (running and stopped)".white());
-        println!("  {} {} {}", "create".green().bold(), "<name> <image>".dimmed(), "Create a new container".white());
+        println!("  {} {} {}", "create".green().bold(), "<name> <image> [flags] [-- cmd]".dimmed(), "Create a new container".white());
         println!("  {} {} {}", "start".green().bold(), "<name>".dimmed(), "Start a stopped container".white());
         println!("  {} {} {}", "stop".green().bold(), "<name>".dimmed(), "Stop a running container".white());
         println!("  {} {} {}", "restart".green().bold(), "<name>".dimmed(), "Restart a container".white());
         println!("  {} {} {}", "pause".green().bold(), "<name>".dimmed(), "Pause a running container".white());
         println!("  {} {} {}", "unpause".green().bold(), "<name>".dimmed(), "Unpause a paused container".white());
         println!("  {} {} {}", "remove".green().bold(), "<name>".dimmed(), "Remove a container (will prompt for confirmation)".white());
-        println!("  {} {} {}", "logs".green().bold(), "<name>".dimmed(), "Show container logs (last 50 lines)".white());
-        println!("  {} {} {}", "exec".green().bold(), "<name> <cmd>".dimmed(), "Execute command in container".white());
+        println!("  {} {} {}", "logs".green().bold(), "<name> [-f] [--since T] [--until T] [--timestamps] [-n N] [-g RE] [--exclude RE] [-C N] [--where F=V] [--fields F,..] [--raw]".dimmed(), "Show, follow or search stdout and stderr (last 50 lines)".white());
+        println!("  {} {} {}", "exec".green().bold(), "<name> <cmd> [-it]".dimmed(), "Execute command in container".white());
+        println!("  {} {} {}", "shell".green().bold(), "<name> [--debug]".dimmed(), "Open the best shell, or a debug container for distroless images".white());
+        println!("  {} {} {}", "dui logs".green().bold(), "<names|globs...> [-l KEY=VALUE] [-f] [-g RE]".dimmed(), "Interleave several containers' logs by timestamp".white());
         println!("  {} {} {}", "inspect".green().bold(), "<name>".dimmed(), "Inspect container details".white());
         println!("  {} {} {}", "info".green().bold(), "<name>".dimmed(), "Get detailed container information".white());
         println!("  {} {} {}", "size".green().bold(), "<name>".dimmed(), "Get container size information".white());
         println!("  {} {} {}", "attach".green().bold(), "<name>".dimmed(), "Attach to a running container".white());
         println!("  {} {} {}", "commit".green().bold(), "<name> <repo> [tag]".dimmed(), "Create image from container changes".white());
-        println!("  {} {} {}", "cp".green().bold(), "<name> <src> <dest>".dimmed(), "Copy files between container and host".white());
+        println!("  {} {} {}", "cp".green().bold(), "<src> <dest>".dimmed(), "Copy either way; one side is container:path, - streams a tar".white());
         println!("  {} {} {}", "diff".green().bold(), "<name>".dimmed(), "Show container filesystem changes".white());
         println!("  {} {} {}", "export".green().bold(), "<name> <file>".dimmed(), "Export container filesystem".white());
         println!("  {} {} {}", "kill".green().bold(), "<name> [signal]".dimmed(), "Kill a running container".white());
@@ -81,11 +102,13 @@ impl UserInterface {
         println!("{}", "─".repeat(50).dimmed());
         println!("  {} {} {}", "list".green().bold(), "".dimmed(), "List all Docker images".white());
         println!("  {} {} {}", "pull".green().bold(), "<name>".dimmed(), "Pull an image from Docker Hub".white());
-        println!("  {} {} {}", "build".green().bold(), "<path> <tag>".dimmed(), "Build an image from Dockerfile".white());
+        println!("  {} {} {}", "build".green().bold(), "<path> <tag>".dimmed(), "Build an image, streaming each step (-t, -f, --build-arg, --target, --no-cache)".white());
         println!("  {} {} {}", "tag".green().bold(), "<source> <target>".dimmed(), "Tag an image".white());
         println!("  {} {} {}", "push".green().bold(), "<name>".dimmed(), "Push an image to registry".white());
         println!("  {} {} {}", "remove".green().bold(), "<name>".dimmed(), "Remove an image (will prompt for confirmation)".white());
         println!("  {} {} {}", "history".green().bold(), "<name>".dimmed(), "Show image history".white());
+        println!("  {} {} {}", "analyze".green().bold(), "<name>".dimmed(), "Explore layers, wasted space and efficiency (--files)".white());
+        println!("  {} {} {}", "sbom".green().bold(), "<name>".dimmed(), "List installed packages as CycloneDX or SPDX JSON (--format, -o)".white());
         println!("  {} {} {}", "import".green().bold(), "<file> <repo> [tag]".dimmed(), "Import image from tarball".white());
         println!("  {} {} {}", "load".green().bold(), "<file>".dimmed(), "Load image from tar archive".white());
         println!("  {} {} {}", "save".green().bold(), "<name> <file>".dimmed(), "Save image to tar archive".white());
@@ -102,13 +125,35 @@ impl UserInterface {
         println!("{}", "─".repeat(50).dimmed());
         println!("  {} {} {}", "volumes".green().bold(), "".dimmed(), "List all Docker volumes".white());
         println!();
+
+        // Registry Section
+        println!("{}", "🏷️  REGISTRY".green().bold());
+        println!("{}", "─".repeat(50).dimmed());
+        println!("  {} {} {}", "registry repos".green().bold(), "<registry>".dimmed(), "List the repositories in a registry".white());
+        println!("  {} {} {}", "registry tags".green().bold(), "<repository>".dimmed(), "List a repository's tags".white());
+        println!("  {} {} {}", "registry manifest".green().bold(), "<image>".dimmed(), "Show a manifest or multi-arch manifest list".white());
+        println!("  {} {} {}", "registry compare".green().bold(), "<image>".dimmed(), "Check whether the local image matches the tag's digest".white());
+        println!("  {} {} {}", "registry delete".green().bold(), "<image> [--yes]".dimmed(), "Delete a tag's manifest from the registry".white());
+        println!("  {} {} {}", "outdated".green().bold(), "[--yes --force --dry-run --insecure]".dimmed(), "Find containers on outdated tags, pull and recreate them".white());
+        println!();
+
+        // Context Section
+        println!("{}", "🔌 CONTEXTS".green().bold());
+        println!("{}", "─".repeat(50).dimmed());
+        println!("  {} {} {}", "contexts".green().bold(), "[list]".dimmed(), "List Docker contexts (* marks the active one)".white());
+        println!("  {} {} {}", "contexts use".green().bold(), "<name>".dimmed(), "Switch the current Docker context".white());
+        println!("  {} {} {}", "--context".green().bold(), "<name>".dimmed(), "Run any command against another context".white());
+        println!("  {} {} {}", "--host".green().bold(), "<url>".dimmed(), "Run any command against a daemon socket".white());
+        println!();
         
         // Monitoring Section
         println!("{}", "📊 MONITORING & SYSTEM".green().bold());
         println!("{}", "─".repeat(50).dimmed());
         println!("  {} {} {}", "stats".green().bold(), "".dimmed(), "Show real-time container statistics".white());
         println!("  {} {} {}", "system".green().bold(), "".dimmed(), "Show Docker system information".white());
-        println!("  {} {} {}", "events".green().bold(), "".dimmed(), "Monitor Docker events in real-time".white());
+        println!("  {} {} {}", "system df".green().bold(), "[--all]".dimmed(), "Disk usage by resource and what each prune would free".white());
+        println!("  {} {} {}", "prune".green().bold(), "[--type --exclude --exited-days --yes --dry-run]".dimmed(), "Preview orphans, deselect some, remove the rest".white());
+        println!("  {} {} {}", "events".green().bold(), "[--type --event --label --since]".dimmed(), "Monitor Docker events in real-time".white());
         println!("  {} {} {}", "dashboard".green().bold(), "".dimmed(), "Show real-time system dashboard".white());
         println!("  {} {} {}", "charts".green().bold(), "".dimmed(), "Display all system charts".white());
         println!();
@@ -136,26 +181,40 @@ impl UserInterface {
         println!("  {} {}", "dui containers list".cyan(), "→ List all containers".dimmed());
         println!("  {} {}", "dui containers create my-app nginx:latest".cyan(), "→ Create container from image".dimmed());
         println!("  {} {}", "dui containers create my-db postgres:13 -p 5432:5432".cyan(), "→ Create container with port mapping".dimmed());
+        println!("  {} {}", "dui containers create api myapp -p 80:80 -e MODE=prod --restart always".cyan(), "→ Create a long-running service".dimmed());
         println!("  {} {}", "dui containers start my-postgres".cyan(), "→ Start a container named 'my-postgres'".dimmed());
         println!("  {} {}", "dui containers restart my-postgres".cyan(), "→ Restart a container".dimmed());
         println!("  {} {}", "dui containers exec my-postgres ls".cyan(), "→ Execute command in container".dimmed());
+        println!("  {} {}", "dui containers exec my-postgres -it -u postgres psql".cyan(), "→ Run an interactive program".dimmed());
         println!("  {} {}", "dui containers info my-postgres".cyan(), "→ Get detailed container information".dimmed());
         println!("  {} {}", "dui containers size my-postgres".cyan(), "→ Get container size".dimmed());
         println!("  {} {}", "dui containers top my-postgres".cyan(), "→ Show container processes".dimmed());
         println!("  {} {}", "dui containers commit my-postgres my-repo:latest".cyan(), "→ Commit container changes".dimmed());
-        println!("  {} {}", "dui containers cp my-postgres /data /backup".cyan(), "→ Copy files from container".dimmed());
+        println!("  {} {}", "dui containers cp my-postgres:/data ./backup".cyan(), "→ Copy files from container".dimmed());
+        println!("  {} {}", "dui containers cp ./site web:/usr/share/nginx/html".cyan(), "→ Copy a directory into a container".dimmed());
+        println!("  {} {}", "dui containers cp web:/etc/nginx - | tar -tv".cyan(), "→ Stream a tar archive to another command".dimmed());
         println!("  {} {}", "dui containers export my-postgres backup.tar".cyan(), "→ Export container filesystem".dimmed());
         println!("  {} {}", "dui images pull nginx:latest".cyan(), "→ Pull the latest nginx image".dimmed());
         println!("  {} {}", "dui images build . myapp:latest".cyan(), "→ Build image from current directory".dimmed());
+        println!("  {} {}", "dui images build . myapp:1.2 -t myapp:latest --build-arg VERSION=1.2 --target runtime".cyan(), "→ Build a stage with two tags".dimmed());
         println!("  {} {}", "dui images history nginx:latest".cyan(), "→ Show image history".dimmed());
+        println!("  {} {}", "dui images analyze myapp:latest --files".cyan(), "→ Explore layers, wasted space and efficiency".dimmed());
+        println!("  {} {}", "dui images sbom myapp:latest --format spdx -o sbom.json".cyan(), "→ Write an SPDX bill of materials".dimmed());
         println!("  {} {}", "dui images save nginx:latest nginx.tar".cyan(), "→ Save image to file".dimmed());
         println!("  {} {}", "dui images load nginx.tar".cyan(), "→ Load image from file".dimmed());
         println!("  {} {}", "dui networks".cyan(), "→ List all networks".dimmed());
         println!("  {} {}", "dui volumes".cyan(), "→ List all volumes".dimmed());
+        println!("  {} {}", "dui system df".cyan(), "→ Show disk usage and reclaimable space".dimmed());
+        println!("  {} {}", "dui prune --type image --exclude 'base-*' --yes".cyan(), "→ Remove unused images except base-*".dimmed());
+        println!("  {} {}", "dui registry tags localhost:5000/myapp".cyan(), "→ List tags in a local registry".dimmed());
+        println!("  {} {}", "dui outdated --dry-run".cyan(), "→ Report containers whose tag has a newer image".dimmed());
+        println!("  {} {}", "dui contexts use buildbox".cyan(), "→ Switch to the 'buildbox' context".dimmed());
+        println!("  {} {}", "dui --context ci containers list".cyan(), "→ List containers on the 'ci' context".dimmed());
         println!("  {} {}", "dui monitor dashboard".cyan(), "→ Show real-time dashboard".dimmed());
         println!("  {} {}", "dui charts cpu".cyan(), "→ Show CPU usage chart".dimmed());
         println!("  {} {}", "dui charts pie".cyan(), "→ Show system pie chart (WIP)".dimmed());
         println!("  {} {}", "dui interactive".cyan(), "→ Launch interactive mode".dimmed());
+        println!("  {} {}", "dui --demo interactive".cyan(), "→ Try interactive mode with sample data".dimmed());
         println!();
         
         println!("{}", "💡 Interactive Mode Features:".yellow().bold());
@@ -198,11 +257,13 @@ impl UserInterface {
         println!("  {} - Remove a specific container", "remove <name>".cyan());
         println!("  {} - Show container logs", "logs <name>".cyan());
         println!("  {} - Execute command in container", "exec <name> <cmd>".cyan());
+        println!("  {} - Open a shell in container", "shell <name>".cyan());
+        println!("  {} - Interleave the logs of several containers", "logs <name|glob...>".cyan());
         println!("  {} - Inspect container details", "inspect <name>".cyan());
         println!("  {} - Show container processes", "top <name>".cyan());
         println!("  {} - Attach to container", "attach <name>".cyan());
         println!("  {} - Commit container changes", "commit <name> <repo>".cyan());
-        println!("  {} - Copy files from container", "cp <name> <src> <dest>".cyan());
+        println!("  {} - Copy to or from a container", "cp <container:path|path|-> <container:path|path|->".cyan());
         println!("  {} - Show container diff", "diff <name>".cyan());
         println!("  {} - Export container", "export <name> <file>".cyan());
         println!("  {} - Kill container", "kill <name>".cyan());
@@ -248,6 +309,7 @@ impl UserInterface {
         println!();
         
         println!("{}", "🔧 Utility Commands:".green().bold());
+        println!("  {} - Preview orphaned containers, images, volumes and networks, then remove the selected", "prune".cyan());
         println!("  {} - Show this help message", "help".cyan());
         println!("  {} - Exit interactive mode", "exit".cyan());
         println!("  {} - Exit interactive mode", "quit".cyan());
@@ -278,6 +340,97 @@ impl UserInterface {
         println!();
     }
 
+    /// Redraws a copy's progress line. It goes to stderr, so a tar stream
+    /// on stdout stays clean.
+    pub fn show_copy_progress(&self, progress: &CopyProgress) {
+        let mut line = match progress.total {
+            Some(total) if total > 0 => {
+                let fraction = (progress.bytes as f64 / total as f64).min(1.0);
+                let filled = (fraction * 24.0).round() as usize;
+                format!(
+                    "{}{} {:>3.0}% {} / {}",
+                    "█".repeat(filled).cyan(),
+                    "░".repeat(24 - filled).dimmed(),
+                    fraction * 100.0,
+                    format_size(progress.bytes.min(total)),
+                    format_size(total)
+                )
+            }
+            _ => format_size(progress.bytes),
+        };
+        if progress.entries > 0 {
+            line.push_str(&format!(" · {} entries", progress.entries));
+        }
+        eprint!("\r{} {}\x1b[K", "📦".cyan(), line);
+        let _ = io::stderr().flush();
+    }
+
+    /// Draws one line per layer and a total line on stderr, first moving up
+    /// over the `drawn` lines of the previous frame. Returns the lines drawn.
+    pub fn show_transfer_progress(&self, progress: &TransferProgress, drawn: usize) -> usize {
+        let mut stderr = io::stderr();
+        if drawn > 0 {
+            let _ = execute!(stderr, cursor::MoveUp(drawn as u16));
+        }
+        for layer in &progress.layers {
+            let status = match layer.phase {
+                LayerPhase::Exists => layer.phase.label().dimmed(),
+                LayerPhase::Complete | LayerPhase::Pushed => layer.phase.label().green(),
+                _ => layer.phase.label().normal(),
+            };
+            let detail = match (layer.fraction(), layer.size) {
+                (Some(fraction), Some(size)) => {
+                    let fraction = fraction.min(1.0);
+                    let filled = (fraction * 20.0).round() as usize;
+                    let done = if layer.phase == LayerPhase::Extracting { layer.extracted } else { layer.transferred };
+                    format!(
+                        " {}{} {} / {}",
+                        "█".repeat(filled).cyan(),
+                        "░".repeat(20 - filled).dimmed(),
+                        format_size(done.min(size)),
+                        format_size(size)
+                    )
+                }
+                _ => String::new(),
+            };
+            eprintln!("\r  {} {:<15}{}\x1b[K", layer.id.dimmed(), status, detail);
+        }
+
+        let (done, total) = progress.bytes();
+        let mut line = if total > 0 {
+            format!("{} / {}", format_size(done), format_size(total))
+        } else {
+            format!("{} layers", progress.layers.len())
+        };
+        if progress.existing() > 0 {
+            line.push_str(&format!(" · {} already exist", progress.existing()));
+        }
+        if let Some(eta) = progress.eta() {
+            line.push_str(&format!(" · ETA {}", format_eta(eta)));
+        }
+        eprintln!("\r{} {}\x1b[K", "📦".cyan(), line);
+        let _ = stderr.flush();
+        progress.layers.len() + 1
+    }
+
+    pub fn show_build_line(&self, line: &str, kind: BuildLineKind) {
+        match kind {
+            BuildLineKind::Step => println!("{}", line.cyan().bold()),
+            BuildLineKind::Cached => println!("{}", line.green()),
+            BuildLineKind::Error => println!("{}", line.red().bold()),
+            BuildLineKind::Output => println!("{}", line.dimmed()),
+        }
+    }
+
+    /// The step a build failed at, with the last lines it printed.
+    pub fn show_failed_step(&self, step: &BuildStep) {
+        println!();
+        println!("{} {}", "💥 Failed at".red().bold(), step.name.red().bold());
+        for line in &step.output {
+            println!("   {} {}", "│".red(), line);
+        }
+    }
+
     pub fn show_success(&self, message: &str) {
         println!("{} {}", "✅".green(), message.green());
     }
@@ -286,6 +439,25 @@ impl UserInterface {
         println!("{} {}", "❌".red(), message.red());
     }
 
+    pub fn show_docker_error(&self, context: &str, error: &DockerError) {
+        self.show_error(&format!("{}: {}", context, error));
+        if let Some(suggestion) = error.suggestion() {
+            println!("{} {}", "💡".yellow(), suggestion.yellow());
+        }
+    }
+
+    /// Clears the screen for the next frame of a --watch view.
+    pub fn show_watch_header(&self, interval: Duration) {
+        let mut stdout = io::stdout();
+        let _ = execute!(stdout, cursor::MoveTo(0, 0), terminal::Clear(ClearType::All));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+        println!(
+            "{} {}",
+            format!("🔄 Every {}s", interval.as_secs_f64()).cyan().bold(),
+            format!("· {} · Ctrl+C to stop", format_timestamp(now)).dimmed()
+        );
+    }
+
     pub fn show_info(&self, message: &str) {
         println!("{} {}", "ℹ️".blue(), message.blue());
     }
@@ -445,46 +617,115 @@ impl UserInterface {
         println!("{}", "─".repeat(90).dimmed());
 
         for stat in stats {
-            let cpu_color = if stat.cpu_percent.replace('%', "").parse::<f32>().unwrap_or(0.0) > 50.0 {
-                stat.cpu_percent.red()
+            // CPU % follows docker (100% per core); the colour follows the share of all cores
+            let cpu_color = if stat.cpu_share() > 0.5 {
+                stat.cpu_text().red()
             } else {
-                stat.cpu_percent.green()
+                stat.cpu_text().green()
             };
 
-            let mem_color = if stat.memory_percent.replace('%', "").parse::<f32>().unwrap_or(0.0) > 80.0 {
-                stat.memory_percent.red()
+            let mem_color = if stat.memory_percent() > 80.0 {
+                stat.memory_text().red()
             } else {
-                stat.memory_percent.green()
+                stat.memory_text().green()
             };
 
             println!(
                 "{:<20} {:<10} {:<20} {:<10} {:<15} {:<15}",
                 stat.name.white(),
                 cpu_color,
-                stat.memory_usage.yellow(),
+                stat.memory_usage().yellow(),
                 mem_color,
-                stat.network_io.cyan(),
-                stat.block_io.dimmed()
+                stat.network_io().cyan(),
+                stat.block_io().dimmed()
             );
         }
         println!();
     }
 
-    pub fn display_logs(&self, logs: &str) {
+    /// Prints lines as they arrive, so it also serves follow mode. stderr
+    /// gets a red gutter and text, stdout a blue gutter. Severity words are
+    /// coloured, search matches highlighted and context lines dimmed.
+    pub fn display_logs(&self, lines: impl Iterator<Item = SearchLine<LogLine>>) {
         println!();
-        println!("{}", "📋 Container Logs".cyan().bold());
+        println!("{} {} {}", "📋 Container Logs".cyan().bold(), "│ stdout".blue(), "│ stderr".red());
         println!("{}", "─".repeat(80).dimmed());
-        
-        if logs.trim().is_empty() {
+
+        let (mut total, mut errors) = (0, 0);
+        for result in lines {
+            let (line, matches, context) = match result {
+                SearchLine::Match(line, matches) => (line, matches, false),
+                SearchLine::Context(line) => (line, Vec::new(), true),
+                SearchLine::Gap => {
+                    println!("{}", "--".dimmed());
+                    continue;
+                }
+            };
+            if !context {
+                total += 1;
+                errors += usize::from(line.stream == LogStream::Stderr);
+            }
+            let gutter = match line.stream {
+                LogStream::Stdout => "│".blue(),
+                LogStream::Stderr => "│".red().bold(),
+            };
+            println!("{} {}", gutter, log_text(&line, &matches, context));
+        }
+
+        if total == 0 {
             self.show_info("No logs available.");
         } else {
-            for line in logs.lines().take(50) {
-                println!("{}", line.dimmed());
-            }
+            println!("{}", format!("{} lines, {} on stderr", total, errors).dimmed());
         }
         println!();
     }
 
+    /// Lines from several containers, each behind a `name |` prefix in a
+    /// colour of its own, otherwise styled as in `display_logs`.
+    pub fn display_tagged_logs(&self, lines: impl Iterator<Item = SearchLine<TaggedLine>>) {
+        const PREFIX_COLORS: [Color; 6] =
+            [Color::Cyan, Color::Magenta, Color::Green, Color::Yellow, Color::Blue, Color::BrightMagenta];
+        let mut colors: HashMap<String, Color> = HashMap::new();
+        let mut width = 0;
+        let mut total = 0;
+        for result in lines {
+            let (tagged, matches, context) = match result {
+                SearchLine::Match(tagged, matches) => (tagged, matches, false),
+                SearchLine::Context(tagged) => (tagged, Vec::new(), true),
+                SearchLine::Gap => {
+                    println!("{}", "--".dimmed());
+                    continue;
+                }
+            };
+            total += usize::from(!context);
+            let next = PREFIX_COLORS[colors.len() % PREFIX_COLORS.len()];
+            let color = *colors.entry(tagged.container.clone()).or_insert(next);
+            // Followed containers can appear later, so the column only grows
+            width = width.max(tagged.container.chars().count());
+            let prefix = format!("{:<width$} |", tagged.container, width = width).color(color);
+            println!("{} {}", prefix, log_text(&tagged.line, &matches, context));
+        }
+        if total == 0 {
+            self.show_info("No logs available.");
+        }
+    }
+
+    /// One line per event, coloured by how alarming the action is.
+    pub fn display_event(&self, event: &DockerEvent) {
+        let action = match event.action_name() {
+            "die" | "kill" | "oom" | "destroy" | "delete" | "remove" | "untag" => event.action.red().bold(),
+            "start" | "create" | "restart" | "unpause" | "pull" | "tag" | "connect" | "mount" => event.action.green().bold(),
+            _ => event.action.yellow().bold(),
+        };
+        println!(
+            "{} {} {} {}",
+            format_timestamp(event.time).dimmed(),
+            event.kind.cyan(),
+            action,
+            event.actor.to_string().white()
+        );
+    }
+
     pub fn display_system_info(&self, info: &str) {
         println!();
         println!("{}", "🖥️  Docker System Information".cyan().bold());
@@ -552,12 +793,14 @@ impl UserInterface {
         println!("  {} - Remove container", "remove <number>".cyan());
         println!("  {} - Show logs", "logs <number>".cyan());
         println!("  {} - Execute command", "exec <number> <cmd>".cyan());
+        println!("  {} - Run interactively (psql, a shell, ...)", "exec -it <number> <cmd...>".cyan());
+        println!("  {} - Open a shell", "shell <number>".cyan());
         println!("  {} - Inspect container", "inspect <number>".cyan());
         println!("  {} - Get container info", "info <number>".cyan());
         println!("  {} - Show processes", "top <number>".cyan());
         println!("  {} - Attach to container", "attach <number>".cyan());
         println!("  {} - Commit container", "commit <number> <repo>".cyan());
-        println!("  {} - Copy files", "cp <number> <src> <dest>".cyan());
+        println!("  {} - Copy out of the container; prefix dest with ':' to copy in", "cp <number> <src> <dest>".cyan());
         println!("  {} - Show diff", "diff <number>".cyan());
         println!("  {} - Export container", "export <number> <file>".cyan());
         println!("  {} - Kill container", "kill <number>".cyan());
@@ -609,11 +852,240 @@ impl UserInterface {
         println!("  {} - Tag image", "tag <number> <new-tag>".cyan());
         println!("  {} - Push image", "push <number>".cyan());
         println!("  {} - Show history", "history <number>".cyan());
+        println!("  {} - Explore layers and wasted space", "analyze <number>".cyan());
         println!("  {} - Save image", "save <number> <file>".cyan());
         println!("  {} - Back to main menu", "back".cyan());
         println!();
     }
 
+    /// Layers with their instruction and changes, then wasted space and the
+    /// efficiency score. `files` lists every change under its layer.
+    pub fn display_image_analysis(&self, image: &str, analysis: &ImageAnalysis, files: bool) {
+        println!();
+        println!("{}", format!("🔍 Layers of {}", image).cyan().bold());
+        println!("{}", "─".repeat(100).dimmed());
+        println!("{:<4} {:<10} {:<20} {}", "#".bold(), "SIZE".bold(), "CHANGES".bold(), "INSTRUCTION".bold());
+        println!("{}", "─".repeat(100).dimmed());
+        for (index, layer) in analysis.layers.iter().enumerate() {
+            let changes = format!(
+                "+{} ~{} -{}",
+                layer.count(ChangeKind::Added),
+                layer.count(ChangeKind::Modified),
+                layer.count(ChangeKind::Deleted)
+            );
+            let instruction = if layer.instruction.is_empty() { layer.id.as_str() } else { layer.instruction.as_str() };
+            println!(
+                "{:<4} {:<10} {:<20} {}",
+                index + 1,
+                format_size(layer.size).yellow(),
+                changes,
+                truncate_string(instruction, 64).white()
+            );
+            if files {
+                for change in &layer.changes {
+                    let (mark, path) = match change.kind {
+                        ChangeKind::Added => ("+".green(), change.path.green()),
+                        ChangeKind::Modified => ("~".yellow(), change.path.yellow()),
+                        ChangeKind::Deleted => ("-".red(), change.path.red()),
+                    };
+                    println!("       {} {:>10}  {}", mark, format_size(change.size).dimmed(), path);
+                }
+            }
+        }
+
+        println!();
+        let wasted = analysis.wasted_size();
+        if analysis.wasted.is_empty() {
+            self.show_success("No wasted space: no layer overwrites or deletes files from the ones below");
+        } else {
+            println!("{}", format!("🗑️  Wasted space: {}", format_size(wasted)).yellow().bold());
+            println!("{:<8} {:>10}  {}", "COPIES".bold(), "WASTED".bold(), "PATH".bold());
+            for file in analysis.wasted.iter().take(10) {
+                println!("{:<8} {:>10}  {}", file.copies, format_size(file.size).yellow(), file.path);
+            }
+            if analysis.wasted.len() > 10 {
+                println!("{}", format!("… and {} more", analysis.wasted.len() - 10).dimmed());
+            }
+        }
+
+        let efficiency = analysis.efficiency() * 100.0;
+        let score = format!("{:.1}%", efficiency);
+        let score = if efficiency >= 95.0 { score.green() } else if efficiency >= 80.0 { score.yellow() } else { score.red() };
+        println!();
+        println!(
+            "{} {} {}",
+            "📐 Efficiency:".bold(),
+            score.bold(),
+            format!("({} of {} still visible)", format_size(analysis.total_size() - wasted), format_size(analysis.total_size())).dimmed()
+        );
+    }
+
+    /// The largest items of each kind (all of them with `all`), marking the ones a prune would remove.
+    pub fn display_disk_usage(&self, usage: &DiskUsage, all: bool) {
+        for kind in UsageKind::ALL {
+            let mut items: Vec<&UsageItem> = usage.items_of(kind).collect();
+            if items.is_empty() {
+                continue;
+            }
+            items.sort_by_key(|item| std::cmp::Reverse(item.size));
+            println!("{}", format!("{} ({})", kind.label(), items.len()).cyan().bold());
+            println!("{:<40} {:>10}  {:<28} {}", "NAME".bold(), "SIZE".bold(), "DETAIL".bold(), "RECLAIMABLE".bold());
+            let shown = if all { items.len() } else { items.len().min(10) };
+            for item in &items[..shown] {
+                let reclaim = item.reclaim.map(|reclaim| reclaim.label()).unwrap_or("");
+                println!(
+                    "{:<40} {:>10}  {:<28} {}",
+                    truncate_string(&item.name, 40).white(),
+                    format_size(item.size).yellow(),
+                    truncate_string(&item.detail, 28).dimmed(),
+                    reclaim.yellow()
+                );
+            }
+            if shown < items.len() {
+                let rest: u64 = items[shown..].iter().map(|item| item.size).sum();
+                println!("{}", format!("… and {} more ({}), see --all", items.len() - shown, format_size(rest)).dimmed());
+            }
+            println!();
+        }
+    }
+
+    /// What each prune would free. `image prune -a` includes the dangling
+    /// images, so the grand total counts them once.
+    pub fn display_reclaim_plan(&self, plan: &[ReclaimAction]) {
+        if plan.is_empty() {
+            self.show_success("Nothing to reclaim: no stopped containers, unused images, volumes or build cache");
+            return;
+        }
+        println!("{}", "🧹 Reclaim plan".cyan().bold());
+        for action in plan {
+            println!(
+                "  {:<26} {:>10}  {}",
+                action.reclaim.command().green(),
+                format_size(action.bytes).yellow().bold(),
+                format!("{} {} {}{}", action.count, action.reclaim.label(), action.reclaim.noun(), if action.count == 1 { "" } else { "s" }).dimmed()
+            );
+        }
+        let total: u64 = plan.iter().filter(|action| action.reclaim != Reclaim::DanglingImage).map(|action| action.bytes).sum();
+        println!("{}", format!("Up to {} can be freed", format_size(total)).bold());
+    }
+
+    pub fn display_prune_preview(&self, orphans: &[Orphan]) {
+        if orphans.is_empty() {
+            self.show_success("Nothing to prune: no orphaned containers, images, volumes or networks");
+            return;
+        }
+        println!();
+        println!("{}", "🧹 Prune preview".cyan().bold());
+        println!("{}", "─".repeat(100).dimmed());
+        println!("{:<4} {:<4} {:<10} {:<32} {:>10}  {}", "#".bold(), "".bold(), "KIND".bold(), "NAME".bold(), "SIZE".bold(), "WHY".bold());
+        for (index, orphan) in orphans.iter().enumerate() {
+            let mark = if orphan.selected { "[x]".green() } else { "[ ]".dimmed() };
+            let size = orphan.size.map(format_size).unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:<4} {:<4} {:<10} {:<32} {:>10}  {}",
+                index + 1,
+                mark,
+                orphan.kind.label(),
+                truncate_string(&orphan.name, 32).white(),
+                size.yellow(),
+                orphan.reason.dimmed()
+            );
+        }
+        let selected = orphans.iter().filter(|orphan| orphan.selected).count();
+        println!(
+            "{}",
+            format!("{} of {} selected, {} to free", selected, orphans.len(), format_size(prune::selected_size(orphans))).bold()
+        );
+    }
+
+    /// A registry listing, repositories or tags, one per line.
+    pub fn display_registry_names(&self, title: &str, names: &[String]) {
+        if names.is_empty() {
+            self.show_info(&format!("{}: none found.", title));
+            return;
+        }
+        println!();
+        println!("{}", format!("📦 {} ({})", title, names.len()).cyan().bold());
+        println!("{}", "─".repeat(80).dimmed());
+        for name in names {
+            println!("  {}", name.white());
+        }
+    }
+
+    pub fn display_manifest(&self, image: &ImageRef, manifest: &Manifest) {
+        println!();
+        println!("{}", format!("📜 {}", image).cyan().bold());
+        println!("{}", "─".repeat(100).dimmed());
+        println!("{:<12} {}", "Digest:".bold(), manifest.digest.white());
+        println!("{:<12} {}", "Type:".bold(), manifest.media_type.dimmed());
+        if manifest.is_list() {
+            println!("{:<12} {}", "Platforms:".bold(), manifest.manifests.len());
+            println!("{:<20} {:>10}  {}", "PLATFORM".bold(), "SIZE".bold(), "DIGEST".bold());
+            for entry in &manifest.manifests {
+                println!(
+                    "{:<20} {:>10}  {}",
+                    entry.platform.as_deref().unwrap_or("-").green(),
+                    format_size(entry.size).yellow(),
+                    entry.digest.dimmed()
+                );
+            }
+            return;
+        }
+        if let Some(config) = &manifest.config {
+            println!("{:<12} {}", "Config:".bold(), config.digest.dimmed());
+        }
+        println!("{:<12} {} in {} layers", "Size:".bold(), format_size(manifest.size()).yellow(), manifest.layers.len());
+        println!("{:<4} {:>10}  {}", "#".bold(), "SIZE".bold(), "DIGEST".bold());
+        for (index, layer) in manifest.layers.iter().enumerate() {
+            println!("{:<4} {:>10}  {}", index + 1, format_size(layer.size).yellow(), layer.digest.dimmed());
+        }
+    }
+
+    pub fn display_digest_comparison(&self, image: &ImageRef, comparison: &DigestComparison) {
+        println!();
+        println!("{}", format!("🔍 {}", image).cyan().bold());
+        println!("{:<10} {}", "Remote:".bold(), comparison.remote.white());
+        let local = match &comparison.local {
+            Some(local) if !local.is_empty() => local.join(", "),
+            _ => "-".to_string(),
+        };
+        println!("{:<10} {}", "Local:".bold(), local.white());
+        let status = comparison.status().label();
+        match comparison.status() {
+            DigestStatus::UpToDate => self.show_success(&format!("{} is {}", image, status)),
+            DigestStatus::Outdated => self.show_error(&format!("{} is {}: the tag now points to a newer image, pull it to update", image, status)),
+            DigestStatus::Unpushed | DigestStatus::Missing => self.show_info(&format!("{} is {}", image, status)),
+        }
+    }
+
+    pub fn display_stale_checks(&self, checks: &[StaleCheck]) {
+        if checks.is_empty() {
+            self.show_info("No running containers.");
+            return;
+        }
+        println!();
+        println!("{}", "🕰️  Image freshness of running containers".cyan().bold());
+        println!("{}", "─".repeat(100).dimmed());
+        println!("{:<24} {:<36} {}", "CONTAINER".bold(), "IMAGE".bold(), "STATUS".bold());
+        for check in checks {
+            let status = match &check.freshness {
+                Freshness::Current => "up to date".green(),
+                Freshness::Stale { remote } => format!("outdated, tag now at {}", truncate_string(remote, 19)).red().bold(),
+                Freshness::Unknown(reason) => format!("unknown: {}", reason).dimmed(),
+            };
+            println!("{:<24} {:<36} {}", truncate_string(&check.container, 24).white(), truncate_string(&check.image, 36), status);
+        }
+        let stale = checks.iter().filter(|check| matches!(check.freshness, Freshness::Stale { .. })).count();
+        let unknown = checks.iter().filter(|check| matches!(check.freshness, Freshness::Unknown(_))).count();
+        if stale == 0 && unknown > 0 {
+            self.show_info(&format!("No outdated images found, but {} of {} running containers couldn't be checked", unknown, checks.len()));
+        } else if stale == 0 {
+            self.show_success("No container runs an outdated image");
+        } else {
+            println!("{}", format!("{} of {} running containers use an outdated image", stale, checks.len()).bold());
+        }
+    }
+
     pub fn display_networks(&self, networks: &[Network]) {
         if networks.is_empty() {
             self.show_info("No networks found.");
@@ -646,6 +1118,39 @@ impl UserInterface {
         println!();
     }
 
+    pub fn display_contexts(&self, contexts: &[DockerContext], current: &str) {
+        println!();
+        println!("{}", "🔌 Docker Contexts".cyan().bold());
+        println!("{}", "─".repeat(80).dimmed());
+
+        // Header
+        println!(
+            "{:<2} {:<20} {:<40} {}",
+            "",
+            "NAME".bold(),
+            "DOCKER ENDPOINT".bold(),
+            "DESCRIPTION".bold()
+        );
+        println!("{}", "─".repeat(80).dimmed());
+
+        for context in contexts {
+            let marker = if context.name == current { "*" } else { "" };
+            let endpoint = if context.tls {
+                format!("{} (tls)", context.host)
+            } else {
+                context.host.clone()
+            };
+            println!(
+                "{:<2} {:<20} {:<40} {}",
+                marker.green().bold(),
+                context.name.white(),
+                endpoint.cyan(),
+                context.description.dimmed()
+            );
+        }
+        println!();
+    }
+
     pub fn display_volumes(&self, volumes: &[Volume]) {
         if volumes.is_empty() {
             self.show_info("No volumes found.");
@@ -676,3 +1181,50 @@ impl UserInterface {
         println!();
     }
 }
+
+// A log line's timestamp and text, with its severity word coloured and search
+// matches highlighted. The rest is red on stderr and dimmed for context.
+fn log_text(line: &LogLine, matches: &[Range<usize>], context: bool) -> String {
+    let text = &line.text;
+    let level = LogLevel::find(text);
+    let mut bounds = vec![0, text.len()];
+    bounds.extend(matches.iter().flat_map(|range| [range.start, range.end]));
+    bounds.extend(level.iter().flat_map(|(_, range)| [range.start, range.end]));
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut styled = line.timestamp.as_deref().map(|time| format!("{} ", time.dimmed())).unwrap_or_default();
+    for span in bounds.windows(2) {
+        let (start, end) = (span[0], span[1]);
+        let part = &text[start..end];
+        let inside = |range: &Range<usize>| range.start <= start && end <= range.end;
+        let part = if matches.iter().any(inside) {
+            part.black().on_yellow().bold()
+        } else if let Some((level, _)) = level.as_ref().filter(|(_, range)| inside(range)) {
+            match level {
+                LogLevel::Error => part.red().bold(),
+                LogLevel::Warn => part.yellow().bold(),
+                LogLevel::Info => part.green().bold(),
+                LogLevel::Debug => part.dimmed(),
+            }
+        } else if context {
+            part.dimmed()
+        } else if line.stream == LogStream::Stderr {
+            part.red()
+        } else {
+            part.normal()
+        };
+        styled.push_str(&part.to_string());
+    }
+    styled
+}
+
+// "9s", "1m05s"
+fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
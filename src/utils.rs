@@ -17,6 +17,44 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Formats a Unix timestamp (seconds) the way the Docker CLI prints creation
+/// times, e.g. "2024-01-02 03:04:05 +0000 UTC".
+pub fn format_timestamp(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} +0000 UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// RFC 3339 in UTC, e.g. "2024-01-02T03:04:05Z", as SBOM formats expect.
+pub fn format_rfc3339(secs: i64) -> String {
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, (rem % 3600) / 60, rem % 60)
+}
+
+// Converts days since 1970-01-01 into a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 pub fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
@@ -25,6 +63,189 @@ pub fn truncate_string(s: &str, max_len: usize) -> String {
     }
 }
 
+/// Shell-style wildcard match: `*` is any run of characters, `?` any one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much of the text it has swallowed
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Encodes standard base64 with padding, e.g. for HTTP Basic credentials.
+pub fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| bits | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decodes standard base64, with or without padding, as the daemon uses in headers.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut bits = 0u32;
+    let mut count = 0;
+    for byte in text.trim().trim_end_matches('=').bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | u32::from(value);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            data.push((bits >> count) as u8);
+        }
+    }
+    Some(data)
+}
+
+// Formats a number to `digits` significant digits, like Go's %.Ng
+fn significant(value: f64, digits: i32) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let decimals = (digits - 1 - magnitude).max(0) as usize;
+    let formatted = format!("{:.*}", decimals, value);
+    if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        formatted
+    }
+}
+
+/// Decimal units as `docker stats` uses for network and block I/O, e.g. "1.5kB".
+pub fn decimal_size(bytes: f64) -> String {
+    const UNITS: &[&str] = &["B", "kB", "MB", "GB", "TB", "PB"];
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{}{}", significant(size, 3), UNITS[unit])
+}
+
+/// Binary units as `docker stats` uses for memory, e.g. "24.5MiB".
+pub fn binary_size(bytes: f64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{}{}", significant(size, 4), UNITS[unit])
+}
+
+/// Parses sizes as the docker CLI prints them ("24.5MiB", "1.2GB", "8.19kB", "0B").
+/// Units with an "i" are binary, the rest decimal.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let value: f64 = text[..split].parse().ok()?;
+    let unit = text[split..].trim();
+    let base: f64 = if unit.contains('i') { 1024.0 } else { 1000.0 };
+    let power = match unit.chars().next().map(|c| c.to_ascii_uppercase()) {
+        None | Some('B') => 0,
+        Some('K') => 1,
+        Some('M') => 2,
+        Some('G') => 3,
+        Some('T') => 4,
+        Some('P') => 5,
+        _ => return None,
+    };
+    Some((value * base.powi(power)).round() as u64)
+}
+
+/// Parses the "used / limit" and "rx / tx" pairs printed by `docker stats`.
+pub fn parse_size_pair(text: &str) -> Option<(u64, u64)> {
+    let (first, second) = text.split_once('/')?;
+    Some((parse_size(first)?, parse_size(second)?))
+}
+
+/// Parses memory limits as `docker run --memory` takes them ("512m", "2g",
+/// "1.5GiB", "536870912b"). Units are always binary.
+pub fn parse_memory(text: &str) -> Option<u64> {
+    let lower = text.trim().to_ascii_lowercase();
+    let split = lower.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(lower.len());
+    let value: f64 = lower[..split].parse().ok()?;
+    let unit = lower[split..].trim_start();
+    let unit = unit.strip_suffix('b').unwrap_or(unit);
+    let unit = unit.strip_suffix('i').unwrap_or(unit);
+    let power = match unit {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        "p" => 5,
+        _ => return None,
+    };
+    Some((value * 1024f64.powi(power)).round() as u64)
+}
+
+/// Parses Go-style durations such as "30s", "1m30s" or "500ms" into nanoseconds.
+pub fn parse_duration_nanos(text: &str) -> Option<u64> {
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut total = 0f64;
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let value: f64 = rest[..split].parse().ok()?;
+        let unit_end = rest[split..].find(|c: char| c.is_ascii_digit() || c == '.').map_or(rest.len(), |end| split + end);
+        let scale = match &rest[split..unit_end] {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return None,
+        };
+        total += value * scale;
+        rest = &rest[unit_end..];
+    }
+    Some(total.round() as u64)
+}
+
+/// Parses "12.50%" into 12.5.
+pub fn parse_percent(text: &str) -> Option<f64> {
+    text.trim().trim_end_matches('%').parse().ok()
+}
+
 pub fn validate_container_name(name: &str) -> Result<(), String> {
     if name.is_empty() {
         return Err("Container name cannot be empty".to_string());
@@ -54,6 +275,55 @@ pub fn validate_image_name(name: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Accepts `[ip:]host:container[/proto]` and `container[/proto]`, where ports
+/// may be ranges such as 8000-8010.
+pub fn validate_port_mapping(mapping: &str) -> Result<(), String> {
+    let invalid = || format!("Invalid port mapping '{}': expected [ip:]host:container[/tcp|udp|sctp]", mapping);
+    let (ports, protocol) = match mapping.split_once('/') {
+        Some((ports, protocol)) => (ports, Some(protocol)),
+        None => (mapping, None),
+    };
+    if protocol.is_some_and(|protocol| !["tcp", "udp", "sctp"].contains(&protocol)) {
+        return Err(invalid());
+    }
+
+    // IPv6 host addresses are bracketed, e.g. [::1]:8080:80
+    let ports = match ports.rfind(']') {
+        Some(end) if ports.starts_with('[') => ports[end + 1..].trim_start_matches(':'),
+        _ => ports,
+    };
+    let parts: Vec<&str> = ports.split(':').collect();
+    let numbers = match parts.len() {
+        1 | 2 => &parts[..],
+        3 => &parts[1..],
+        _ => return Err(invalid()),
+    };
+    let is_port = |part: &&str| {
+        part.split('-').all(|port| port.parse::<u16>().is_ok())
+    };
+    // An empty host port lets the daemon pick one, as in 127.0.0.1::80
+    let container_ok = numbers.last().is_some_and(is_port);
+    let host_ok = numbers.len() == 1 || numbers[0].is_empty() || is_port(&numbers[0]);
+    if container_ok && host_ok {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+pub fn validate_restart_policy(policy: &str) -> Result<(), String> {
+    let valid = match policy.split_once(':') {
+        Some(("on-failure", retries)) => retries.parse::<u32>().is_ok(),
+        Some(_) => false,
+        None => ["no", "always", "unless-stopped", "on-failure"].contains(&policy),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid restart policy '{}': use no, always, unless-stopped or on-failure[:max-retries]", policy))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,12 +336,62 @@ mod tests {
         assert_eq!(format_size(1048576), "1.0 MB");
     }
 
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 +0000 UTC");
+        assert_eq!(format_timestamp(1_704_164_645), "2024-01-02 03:04:05 +0000 UTC");
+        assert_eq!(format_rfc3339(1_704_164_645), "2024-01-02T03:04:05Z");
+    }
+
     #[test]
     fn test_truncate_string() {
         assert_eq!(truncate_string("hello", 10), "hello");
         assert_eq!(truncate_string("hello world", 8), "hello...");
     }
 
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("shop-*", "shop-api-1"));
+        assert!(glob_match("*-worker-?", "shop-worker-2"));
+        assert!(glob_match("db", "db"));
+        assert!(!glob_match("db", "db-replica"));
+        assert!(!glob_match("shop-*-1", "shop-api-2"));
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        assert_eq!(base64_decode("eyJuYW1lIjoibmdpbngifQ==").unwrap(), br#"{"name":"nginx"}"#);
+        assert_eq!(base64_decode("dXNlcjpwYXNz").unwrap(), b"user:pass");
+        assert_eq!(base64_decode("not base64!"), None);
+        assert_eq!(base64_encode(b"user:pass"), "dXNlcjpwYXNz");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_decode(&base64_encode(br#"{"name":"nginx"}"#)).unwrap(), br#"{"name":"nginx"}"#);
+    }
+
+    #[test]
+    fn test_docker_stats_sizes_round_trip() {
+        assert_eq!(binary_size(1_048_576.0), "1MiB");
+        assert_eq!(decimal_size(1_536.0), "1.54kB");
+        assert_eq!(parse_size("1MiB"), Some(1_048_576));
+        assert_eq!(parse_size("1.5kB"), Some(1_500));
+        assert_eq!(parse_size("0B"), Some(0));
+        assert_eq!(parse_size("--"), None);
+        assert_eq!(parse_size_pair("24MB / 2GB"), Some((24_000_000, 2_000_000_000)));
+        assert_eq!(parse_percent("12.50%"), Some(12.5));
+    }
+
+    #[test]
+    fn test_parse_memory_and_durations() {
+        assert_eq!(parse_memory("512m"), Some(536_870_912));
+        assert_eq!(parse_memory("536870912b"), Some(536_870_912));
+        assert_eq!(parse_memory("1.5GiB"), Some(1_610_612_736));
+        assert_eq!(parse_memory("lots"), None);
+        assert_eq!(parse_duration_nanos("30s"), Some(30_000_000_000));
+        assert_eq!(parse_duration_nanos("1m30s"), Some(90_000_000_000));
+        assert_eq!(parse_duration_nanos("500ms"), Some(500_000_000));
+        assert_eq!(parse_duration_nanos("10"), None);
+    }
+
     #[test]
     fn test_validate_container_name() {
         assert!(validate_container_name("my-container").is_ok());
@@ -81,6 +401,18 @@ mod tests {
         assert!(validate_container_name("my container").is_err());
     }
 
+    #[test]
+    fn test_validate_port_mapping() {
+        for mapping in ["80", "8080:80", "127.0.0.1:8080:80/udp", "127.0.0.1::80", "8000-8010:8000-8010", "[::1]:8080:80"] {
+            assert!(validate_port_mapping(mapping).is_ok(), "{}", mapping);
+        }
+        for mapping in ["", "web:80", "8080:80/http", "1:2:3:4", "70000:80"] {
+            assert!(validate_port_mapping(mapping).is_err(), "{}", mapping);
+        }
+        assert!(validate_restart_policy("on-failure:3").is_ok());
+        assert!(validate_restart_policy("sometimes").is_err());
+    }
+
     #[test]
     fn test_validate_image_name() {
         assert!(validate_image_name("nginx:latest").is_ok());